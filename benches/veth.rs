@@ -0,0 +1,232 @@
+//! Compare the round trip time of the raw socket TX/RX backends over veth pairs.
+//!
+//! Requires root and one veth pair per backend, as backends can't be stopped once started and would
+//! otherwise receive each other's frames:
+//!
+//! ```bash
+//! for backend in raw uring tpacket; do
+//!     sudo ip link add veth-$backend type veth peer name veth-$backend-sd
+//!     sudo ip link set veth-$backend up
+//!     sudo ip link set veth-$backend-sd up
+//! done
+//!
+//! sudo -E cargo bench --bench veth
+//! ```
+//!
+//! The MainDevice uses `veth-<backend>`. A thread answers frames arriving on `veth-<backend>-sd`,
+//! acting as a line of SubDevices.
+
+#[cfg(target_os = "linux")]
+mod veth {
+    use criterion::{Criterion, Throughput};
+    use ethercrab::{
+        std::{tx_rx_task, tx_rx_task_io_uring, tx_rx_task_tpacket},
+        Command, MainDevice, MainDeviceConfig, PduRx, PduStorage, PduTx, Timeouts,
+    };
+    use std::{ffi::CString, io, mem, ptr, thread};
+
+    const SUBDEVICES: u16 = 3;
+    const ETHERCAT_ETHERTYPE: u16 = 0x88a4;
+
+    type Storage = PduStorage<16, { PduStorage::element_size(1100) }>;
+
+    /// Run a TX/RX task on an interface until the process exits.
+    type Backend = fn(&str, PduTx<'static>, PduRx<'static>);
+
+    /// A blocking raw socket on the SubDevice end of a veth pair.
+    struct Port {
+        fd: i32,
+    }
+
+    impl Port {
+        fn new(interface: &str) -> io::Result<Self> {
+            let protocol = ETHERCAT_ETHERTYPE.to_be();
+
+            let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, i32::from(protocol)) };
+
+            if fd == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let port = Self { fd };
+
+            let sockaddr = libc::sockaddr_ll {
+                sll_family: libc::AF_PACKET as u16,
+                sll_protocol: protocol,
+                sll_ifindex: interface_index(interface)? as i32,
+                sll_hatype: 0,
+                sll_pkttype: 0,
+                sll_halen: 0,
+                sll_addr: [0; 8],
+            };
+
+            let res = unsafe {
+                libc::bind(
+                    port.fd,
+                    ptr::addr_of!(sockaddr).cast(),
+                    mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+                )
+            };
+
+            if res == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(port)
+        }
+
+        /// Wait for a frame sent by the MainDevice, ignoring frames sent by this port.
+        fn recv(&self, buf: &mut [u8]) -> Option<usize> {
+            let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+
+            let n = unsafe {
+                libc::recvfrom(
+                    self.fd,
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    0,
+                    ptr::addr_of_mut!(addr).cast(),
+                    &mut addr_len,
+                )
+            };
+
+            (n > 0 && addr.sll_pkttype != libc::PACKET_OUTGOING).then_some(n as usize)
+        }
+
+        fn send(&self, frame: &[u8]) {
+            let n = unsafe { libc::send(self.fd, frame.as_ptr().cast(), frame.len(), 0) };
+
+            assert_eq!(n, frame.len() as isize, "send failed");
+        }
+    }
+
+    impl Drop for Port {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+
+    fn interface_index(interface: &str) -> io::Result<u32> {
+        let name = CString::new(interface).map_err(io::Error::other)?;
+
+        match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+            0 => Err(io::Error::last_os_error()),
+            ifindex => Ok(ifindex),
+        }
+    }
+
+    /// Increment the working counter of every PDU once for each SubDevice, and set the U/L bit of
+    /// the source address like a real SubDevice.
+    fn process(frame: &mut [u8]) {
+        frame[6] |= 0x02;
+
+        let mut pos = 16;
+
+        loop {
+            let flags = u16::from_le_bytes([frame[pos + 6], frame[pos + 7]]);
+            let len = usize::from(flags & 0x07ff);
+            let wkc = pos + 10 + len;
+
+            let count = u16::from_le_bytes([frame[wkc], frame[wkc + 1]]) + SUBDEVICES;
+
+            frame[wkc..wkc + 2].copy_from_slice(&count.to_le_bytes());
+
+            if flags & 0x8000 == 0 {
+                break;
+            }
+
+            pos = wkc + 2;
+        }
+    }
+
+    fn simulate_subdevices(port: Port) {
+        let mut buf = [0u8; 1536];
+
+        loop {
+            if let Some(n) = port.recv(&mut buf) {
+                let frame = &mut buf[0..n];
+
+                process(frame);
+                port.send(frame);
+            }
+        }
+    }
+
+    /// Start a backend on `veth-<name>` and its simulated SubDevices, or `None` if the veth pair
+    /// doesn't exist.
+    fn start(name: &str, task: Backend) -> Option<MainDevice<'static>> {
+        let interface = format!("veth-{}", name);
+
+        let port = match Port::new(&format!("{}-sd", interface)) {
+            Ok(port) => port,
+            Err(e) => {
+                eprintln!("Skipping {}: {}. See benches/veth.rs for setup.", name, e);
+
+                return None;
+            }
+        };
+
+        thread::spawn(move || simulate_subdevices(port));
+
+        let storage: &'static Storage = Box::leak(Box::new(Storage::new()));
+
+        let (tx, rx, pdu_loop) = storage.try_split().expect("can only split once");
+
+        thread::spawn(move || task(&interface, tx, rx));
+
+        Some(MainDevice::new(
+            pdu_loop,
+            Timeouts::default(),
+            MainDeviceConfig::default(),
+        ))
+    }
+
+    pub fn backends(c: &mut Criterion) {
+        let backends: [(&str, Backend); 3] = [
+            ("raw", |interface, tx, rx| {
+                smol::block_on(tx_rx_task(interface, tx, rx).expect("spawn TX/RX task"))
+                    .expect("TX/RX task");
+            }),
+            ("uring", |interface, tx, rx| {
+                tx_rx_task_io_uring(interface, tx, rx).expect("io_uring TX/RX task");
+            }),
+            ("tpacket", |interface, tx, rx| {
+                tx_rx_task_tpacket(interface, tx, rx).expect("TPACKET_V3 TX/RX task");
+            }),
+        ];
+
+        let mut group = c.benchmark_group("veth");
+
+        group.throughput(Throughput::Elements(1));
+
+        for (name, task) in backends {
+            let Some(maindevice) = start(name, task) else {
+                continue;
+            };
+
+            group.bench_function(name, |b| {
+                b.iter(|| {
+                    smol::block_on(
+                        Command::brd(0x0000)
+                            .with_wkc(SUBDEVICES)
+                            .receive::<u8>(&maindevice),
+                    )
+                    .expect("BRD")
+                })
+            });
+        }
+
+        group.finish();
+    }
+}
+
+#[cfg(target_os = "linux")]
+criterion::criterion_group!(veth, veth::backends);
+#[cfg(target_os = "linux")]
+criterion::criterion_main!(veth);
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("The veth benchmark is only available on Linux");
+}
@@ -0,0 +1,248 @@
+use crate::parse_struct::{FieldMeta, StructMeta};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use std::str::FromStr;
+use syn::DeriveInput;
+
+/// Primitive types that can be converted directly to and from little endian bytes, along with
+/// their size in bytes.
+const PRIMITIVES: &[(&str, usize)] = &[
+    ("u8", 1),
+    ("u16", 2),
+    ("u32", 4),
+    ("u64", 8),
+    ("i8", 1),
+    ("i16", 2),
+    ("i32", 4),
+    ("i64", 8),
+    ("f32", 4),
+    ("f64", 8),
+];
+
+/// How a field is accessed in the underlying buffer.
+enum Access {
+    /// A whole, byte-aligned primitive that can use `from_le_bytes`/`to_le_bytes`.
+    Primitive { size: usize },
+    /// A `bool` or `u8` that is smaller than a byte.
+    SmallInt,
+    /// Any other type that fits in a single byte, e.g. an enum.
+    SingleByte,
+    /// Any other multi-byte type, e.g. a nested struct.
+    MultiByte,
+}
+
+impl Access {
+    fn new(field: &FieldMeta) -> Self {
+        let ty_name = field.ty_name.as_ref().map(|ident| ident.to_string());
+
+        let primitive = ty_name
+            .as_deref()
+            .and_then(|name| PRIMITIVES.iter().find(|(prim, _)| *prim == name))
+            .filter(|(_, size)| field.bit_offset == 0 && field.bits.len() == size * 8);
+
+        if let Some((_, size)) = primitive {
+            Self::Primitive { size: *size }
+        } else if field.bits.len() <= 8 && matches!(ty_name.as_deref(), Some("bool" | "u8")) {
+            Self::SmallInt
+        } else if field.bits.len() <= 8 {
+            Self::SingleByte
+        } else {
+            Self::MultiByte
+        }
+    }
+}
+
+fn mask(field: &FieldMeta) -> TokenStream {
+    let mask = (2u16.pow(field.bits.len() as u32) - 1) << field.bit_offset;
+
+    TokenStream::from_str(&format!("{:#010b}", mask)).unwrap()
+}
+
+fn getter(field: &FieldMeta) -> TokenStream {
+    let name = &field.name;
+    let ty = &field.ty;
+    let byte_start = field.bytes.start;
+    let byte_end = field.bytes.end;
+    let bit_start = field.bit_offset;
+    let doc = format!("Get the value of `{}`.", name);
+
+    match Access::new(field) {
+        Access::Primitive { size } => quote! {
+            #[doc = #doc]
+            pub fn #name(&self) -> #ty {
+                let mut bytes = [0u8; #size];
+
+                bytes.copy_from_slice(&self.buf[#byte_start..#byte_end]);
+
+                <#ty>::from_le_bytes(bytes)
+            }
+        },
+        Access::SmallInt => {
+            let mask = mask(field);
+
+            let value = if field.ty_name.as_ref().is_some_and(|ty| ty == "bool") {
+                quote! { ((self.buf[#byte_start] & #mask) >> #bit_start) > 0 }
+            } else {
+                quote! { (self.buf[#byte_start] & #mask) >> #bit_start }
+            };
+
+            quote! {
+                #[doc = #doc]
+                pub fn #name(&self) -> #ty {
+                    #value
+                }
+            }
+        }
+        Access::SingleByte => {
+            let mask = mask(field);
+
+            quote! {
+                #[doc = #doc]
+                pub fn #name(&self) -> Result<#ty, ::ethercrab_wire::WireError> {
+                    let masked = (self.buf[#byte_start] & #mask) >> #bit_start;
+
+                    <#ty as ::ethercrab_wire::EtherCrabWireRead>::unpack_from_slice(&[masked])
+                }
+            }
+        }
+        Access::MultiByte => quote! {
+            #[doc = #doc]
+            pub fn #name(&self) -> Result<#ty, ::ethercrab_wire::WireError> {
+                <#ty as ::ethercrab_wire::EtherCrabWireRead>::unpack_from_slice(&self.buf[#byte_start..#byte_end])
+            }
+        },
+    }
+}
+
+fn setter(field: &FieldMeta) -> TokenStream {
+    let name = &field.name;
+    let setter_name = format_ident!("set_{}", name);
+    let ty = &field.ty;
+    let byte_start = field.bytes.start;
+    let byte_end = field.bytes.end;
+    let bit_start = field.bit_offset;
+    let doc = format!("Set the value of `{}`.", name);
+
+    let body = match Access::new(field) {
+        Access::Primitive { .. } => quote! {
+            self.buf[#byte_start..#byte_end].copy_from_slice(&value.to_le_bytes());
+        },
+        Access::SmallInt => {
+            let mask = mask(field);
+
+            let value = if field.ty_name.as_ref().is_some_and(|ty| ty == "bool") {
+                quote! { u8::from(value) }
+            } else {
+                quote! { value }
+            };
+
+            quote! {
+                self.buf[#byte_start] = (self.buf[#byte_start] & !#mask) | ((#value << #bit_start) & #mask);
+            }
+        }
+        Access::SingleByte => {
+            let mask = mask(field);
+
+            quote! {
+                let mut field_buf = [0u8; 1];
+                let packed = <#ty as ::ethercrab_wire::EtherCrabWireWrite>::pack_to_slice_unchecked(&value, &mut field_buf)[0];
+
+                self.buf[#byte_start] = (self.buf[#byte_start] & !#mask) | ((packed << #bit_start) & #mask);
+            }
+        }
+        Access::MultiByte => quote! {
+            <#ty as ::ethercrab_wire::EtherCrabWireWrite>::pack_to_slice_unchecked(&value, &mut self.buf[#byte_start..#byte_end]);
+        },
+    };
+
+    quote! {
+        #[doc = #doc]
+        pub fn #setter_name(&mut self, value: #ty) {
+            #body
+        }
+    }
+}
+
+pub fn generate_view(parsed: &StructMeta, input: &DeriveInput) -> TokenStream {
+    let name = input.ident.clone();
+    let vis = input.vis.clone();
+    let size_bytes = parsed.width_bits.div_ceil(8);
+
+    let view_name = Ident::new(&format!("{}View", name), Span::call_site());
+    let view_mut_name = Ident::new(&format!("{}ViewMut", name), Span::call_site());
+
+    let view_doc = format!("Read-only view of a [`{}`] in a byte buffer.", name);
+    let view_mut_doc = format!("Read/write view of a [`{}`] in a byte buffer.", name);
+
+    let field_names = parsed.fields.iter().map(|field| &field.name);
+
+    let fields = parsed.fields.iter().filter(|field| !field.skip);
+
+    let getters = fields.clone().map(getter).collect::<Vec<_>>();
+    let setters = fields.map(setter);
+
+    quote! {
+        #[doc = #view_doc]
+        #[derive(Debug, Copy, Clone)]
+        #vis struct #view_name<'buf> {
+            buf: &'buf [u8],
+        }
+
+        impl<'buf> #view_name<'buf> {
+            /// Get the raw bytes this view refers to.
+            pub fn as_bytes(&self) -> &'buf [u8] {
+                self.buf
+            }
+
+            #(#getters)*
+        }
+
+        #[doc = #view_mut_doc]
+        #[derive(Debug)]
+        #vis struct #view_mut_name<'buf> {
+            buf: &'buf mut [u8],
+        }
+
+        impl<'buf> #view_mut_name<'buf> {
+            /// Get a read-only view of the same data.
+            pub fn as_view(&self) -> #view_name<'_> {
+                #view_name { buf: self.buf }
+            }
+
+            /// Get the raw bytes this view refers to.
+            pub fn as_bytes(&self) -> &[u8] {
+                self.buf
+            }
+
+            #(#getters)*
+
+            #(#setters)*
+        }
+
+        impl ::ethercrab_wire::EtherCrabWireView for #name {
+            const VIEW_LEN: usize = #size_bytes;
+
+            type View<'buf> = #view_name<'buf>;
+
+            type ViewMut<'buf> = #view_mut_name<'buf>;
+
+            fn view(buf: &[u8]) -> Result<Self::View<'_>, ::ethercrab_wire::WireError> {
+                // The annotated struct only describes the layout and is never constructed, so
+                // touch each field to stop dead code warnings in user code.
+                let _ = |layout: &Self| {
+                    #(let _ = &layout.#field_names;)*
+                };
+
+                let buf = buf.get(0..#size_bytes).ok_or(::ethercrab_wire::WireError::ReadBufferTooShort)?;
+
+                Ok(#view_name { buf })
+            }
+
+            fn view_mut(buf: &mut [u8]) -> Result<Self::ViewMut<'_>, ::ethercrab_wire::WireError> {
+                let buf = buf.get_mut(0..#size_bytes).ok_or(::ethercrab_wire::WireError::WriteBufferTooShort)?;
+
+                Ok(#view_mut_name { buf })
+            }
+        }
+    }
+}
@@ -168,12 +168,14 @@
 
 mod generate_enum;
 mod generate_struct;
+mod generate_view;
 mod help;
 mod parse_enum;
 mod parse_struct;
 
 use generate_enum::{generate_enum_read, generate_enum_write};
 use generate_struct::{generate_sized_impl, generate_struct_read, generate_struct_write};
+use generate_view::generate_view;
 use parse_enum::parse_enum;
 use parse_struct::parse_struct;
 use proc_macro::TokenStream;
@@ -291,6 +293,73 @@ pub fn ether_crab_wire_write(input: TokenStream) -> TokenStream {
     TokenStream::from(res)
 }
 
+/// Zero-copy typed views into a byte buffer, e.g. a SubDevice's process data image.
+///
+/// This derive generates two types alongside the annotated struct: `<Name>View` with a getter for
+/// every field, and `<Name>ViewMut` which adds a `set_<field>` setter for every field. The struct
+/// itself is never constructed; it only describes the layout using the same `#[wire]` attributes
+/// as [`EtherCrabWireReadWrite`].
+///
+/// Whole primitives and sub-byte `bool`/`u8` fields are read and written in place and return their
+/// value directly. Other field types, e.g. enums or nested structs, are decoded with
+/// `EtherCrabWireRead` and so return a `Result`.
+///
+/// Only structs are supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use ethercrab_wire::EtherCrabWireView;
+///
+/// #[derive(ethercrab_wire::EtherCrabWireView)]
+/// #[wire(bytes = 3)]
+/// struct Inputs {
+///     #[wire(bits = 1)]
+///     ready: bool,
+///     #[wire(bits = 4, post_skip = 3)]
+///     mode: u8,
+///     #[wire(bytes = 2)]
+///     position: i16,
+/// }
+///
+/// let mut pdi = [0u8; 3];
+///
+/// let mut view = Inputs::view_mut(&mut pdi).unwrap();
+///
+/// view.set_ready(true);
+/// view.set_mode(0b1010);
+/// view.set_position(-2);
+///
+/// assert_eq!(pdi, [0b0001_0101, 0xfe, 0xff]);
+///
+/// let view = Inputs::view(&pdi).unwrap();
+///
+/// assert!(view.ready());
+/// assert_eq!(view.mode(), 0b1010);
+/// assert_eq!(view.position(), -2);
+/// ```
+#[proc_macro_derive(EtherCrabWireView, attributes(wire))]
+pub fn ether_crab_wire_view(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let res = match input.clone().data {
+        Data::Struct(s) => {
+            parse_struct(s, input.clone()).map(|parsed| generate_view(&parsed, &input))
+        }
+        Data::Enum(_) | Data::Union(_) => Err(syn::Error::new(
+            input.ident.span(),
+            "Only structs are supported",
+        )),
+    };
+
+    let res = match res {
+        Ok(res) => res,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    TokenStream::from(res)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
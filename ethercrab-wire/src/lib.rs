@@ -26,7 +26,9 @@ mod error;
 mod impls;
 
 pub use error::WireError;
-pub use ethercrab_wire_derive::{EtherCrabWireRead, EtherCrabWireReadWrite, EtherCrabWireWrite};
+pub use ethercrab_wire_derive::{
+    EtherCrabWireRead, EtherCrabWireReadWrite, EtherCrabWireView, EtherCrabWireWrite,
+};
 
 /// A type to be received from the wire, according to EtherCAT spec rules (packed bits, little
 /// endian).
@@ -102,3 +104,33 @@ pub trait EtherCrabWireWriteSized: EtherCrabWireSized {
 pub trait EtherCrabWireReadSized: EtherCrabWireRead + EtherCrabWireSized {}
 
 impl<T> EtherCrabWireReadSized for T where T: EtherCrabWireRead + EtherCrabWireSized {}
+
+/// Zero-copy typed access to a packed representation stored in a byte buffer.
+///
+/// Unlike [`EtherCrabWireRead`] and [`EtherCrabWireWrite`], no copy of the data is made. Instead,
+/// [`view`](EtherCrabWireView::view) and [`view_mut`](EtherCrabWireView::view_mut) borrow the
+/// buffer and return types with getters and setters for each field.
+///
+/// This trait is [derivable](https://docs.rs/ethercrab-wire-derive).
+pub trait EtherCrabWireView {
+    /// Number of bytes a buffer must contain to be viewed as this type.
+    const VIEW_LEN: usize;
+
+    /// Read-only view type.
+    type View<'buf>;
+
+    /// Read/write view type.
+    type ViewMut<'buf>;
+
+    /// Create a read-only view over the beginning of `buf`.
+    ///
+    /// Returns [`WireError::ReadBufferTooShort`] if `buf` is shorter than
+    /// [`VIEW_LEN`](EtherCrabWireView::VIEW_LEN).
+    fn view(buf: &[u8]) -> Result<Self::View<'_>, WireError>;
+
+    /// Create a read/write view over the beginning of `buf`.
+    ///
+    /// Returns [`WireError::WriteBufferTooShort`] if `buf` is shorter than
+    /// [`VIEW_LEN`](EtherCrabWireView::VIEW_LEN).
+    fn view_mut(buf: &mut [u8]) -> Result<Self::ViewMut<'_>, WireError>;
+}
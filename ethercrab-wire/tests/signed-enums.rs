@@ -124,6 +124,7 @@ fn cia402_control_word() {
 fn sized() {
     #[derive(ethercrab_wire::EtherCrabWireRead)]
     #[wire(bytes = 9)]
+    #[allow(unused)]
     struct DriveState {
         #[wire(bytes = 4)]
         actual_position: u32,
@@ -144,6 +145,7 @@ fn sized() {
     #[derive(Copy, Clone, ethercrab_wire::EtherCrabWireWrite)]
     #[wire(bytes = 1)]
     #[repr(u8)]
+    #[allow(unused)]
     enum ControlState {
         Init = 0x01,
         Conf = 0x04,
@@ -0,0 +1,144 @@
+use ethercrab_wire::{EtherCrabWireReadWrite, EtherCrabWireView, WireError};
+
+#[derive(Debug, Copy, Clone, PartialEq, EtherCrabWireReadWrite)]
+#[repr(u8)]
+enum Mode {
+    Idle = 0x00,
+    Velocity = 0x03,
+    Position = 0x05,
+    #[wire(catch_all)]
+    Unknown(u8),
+}
+
+#[derive(Debug, PartialEq, EtherCrabWireReadWrite)]
+#[wire(bytes = 2)]
+struct Nested {
+    #[wire(bytes = 1)]
+    a: u8,
+    #[wire(bytes = 1)]
+    b: u8,
+}
+
+#[derive(EtherCrabWireView)]
+#[wire(bytes = 12)]
+struct Drive {
+    #[wire(bits = 1)]
+    enabled: bool,
+    #[wire(bits = 1)]
+    fault: bool,
+    #[wire(bits = 3)]
+    counter: u8,
+    #[wire(bits = 3)]
+    mode: Mode,
+    #[wire(bytes = 2)]
+    status: u16,
+    #[wire(bytes = 4)]
+    position: i32,
+    #[wire(bytes = 2)]
+    nested: Nested,
+    #[wire(bytes = 1)]
+    op_mode: Mode,
+    #[wire(pre_skip_bytes = 1, bytes = 1)]
+    last: u8,
+}
+
+#[test]
+fn read() {
+    let buf = [
+        0b1010_1101,
+        0x34,
+        0x12,
+        0xfe,
+        0xff,
+        0xff,
+        0xff,
+        0xaa,
+        0xbb,
+        0x03,
+        0xee,
+        0x99,
+    ];
+
+    let view = Drive::view(&buf).unwrap();
+
+    assert!(view.enabled());
+    assert!(!view.fault());
+    assert_eq!(view.counter(), 0b011);
+    assert_eq!(view.mode(), Ok(Mode::Position));
+    assert_eq!(view.status(), 0x1234);
+    assert_eq!(view.position(), -2);
+    assert_eq!(view.nested(), Ok(Nested { a: 0xaa, b: 0xbb }));
+    assert_eq!(view.op_mode(), Ok(Mode::Velocity));
+    assert_eq!(view.last(), 0x99);
+}
+
+#[test]
+fn write_preserves_neighbours() {
+    let mut buf = [0xffu8; 12];
+
+    let mut view = Drive::view_mut(&mut buf).unwrap();
+
+    view.set_fault(false);
+    view.set_counter(0b010);
+    view.set_mode(Mode::Idle);
+
+    assert_eq!(view.as_bytes()[0], 0b0000_1001);
+
+    view.set_status(0xabcd);
+    view.set_position(0x0102_0304);
+    view.set_nested(Nested { a: 1, b: 2 });
+    view.set_op_mode(Mode::Unknown(0x42));
+    view.set_last(0x00);
+
+    assert_eq!(view.as_view().position(), 0x0102_0304);
+
+    assert_eq!(
+        buf,
+        [
+            0b0000_1001,
+            0xcd,
+            0xab,
+            0x04,
+            0x03,
+            0x02,
+            0x01,
+            1,
+            2,
+            0x42,
+            0xff,
+            0x00
+        ]
+    );
+}
+
+#[test]
+fn out_of_range_bits_are_masked() {
+    let mut buf = [0u8; 12];
+
+    let mut view = Drive::view_mut(&mut buf).unwrap();
+
+    // Only 3 bits are available, so the upper bits are discarded instead of clobbering `mode`.
+    view.set_counter(0xff);
+
+    assert_eq!(view.counter(), 0b111);
+    assert_eq!(view.mode(), Ok(Mode::Idle));
+}
+
+#[test]
+fn buffer_length() {
+    assert_eq!(Drive::VIEW_LEN, 12);
+
+    assert!(matches!(
+        Drive::view(&[0u8; 11]),
+        Err(WireError::ReadBufferTooShort)
+    ));
+    assert!(matches!(
+        Drive::view_mut(&mut [0u8; 11]),
+        Err(WireError::WriteBufferTooShort)
+    ));
+
+    // Longer buffers are allowed, but only the first `VIEW_LEN` bytes are viewed.
+    let buf = [0u8; 16];
+
+    assert_eq!(Drive::view(&buf).unwrap().as_bytes().len(), 12);
+}
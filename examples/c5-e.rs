@@ -99,7 +99,7 @@ async fn main() -> anyhow::Result<()> {
             .context("current %")?;
         // Max motor current max duration in ms
         subdevice
-            .sdo_write(0x203b, 02, 100u32)
+            .sdo_write(0x203b, 2, 100u32)
             .await
             .context("max current duration")?;
         // Motor type: stepper
@@ -6,7 +6,7 @@ use ethercrab::{
     error::Error,
     std::{ethercat_now, tx_rx_task},
     subdevice_group::{CycleInfo, DcConfiguration},
-    DcSync, MainDevice, MainDeviceConfig, PduStorage, RegisterAddress, SubDeviceGroup, Timeouts,
+    DcSync, MainDevice, MainDeviceConfig, PduStorage, SubDeviceGroup, Timeouts,
 };
 use futures_lite::StreamExt;
 use std::{
@@ -141,8 +141,8 @@ fn main() -> Result<(), Error> {
 
         log::info!("Moving into PRE-OP with PDI");
 
-        let mut slow_group = slow_group.into_pre_op_pdi(&maindevice).await?;
-        let mut fast_group = fast_group.into_pre_op_pdi(&maindevice).await?;
+        let slow_group = slow_group.into_pre_op_pdi(&maindevice).await?;
+        let fast_group = fast_group.into_pre_op_pdi(&maindevice).await?;
 
         log::info!("Done. PDI available. Waiting for SubDevices to align");
 
@@ -158,33 +158,12 @@ fn main() -> Result<(), Error> {
             if now.elapsed() >= Duration::from_millis(25) {
                 now = Instant::now();
 
-                let mut max_deviation = 0;
-
-                for s1 in slow_group.iter(&maindevice) {
-                    let diff = match s1
-                        .register_read::<u32>(RegisterAddress::DcSystemTimeDifference)
-                        .await
-                    {
-                        Ok(value) =>
-                        // The returned value is NOT in two's compliment, rather the upper bit specifies
-                        // whether the number in the remaining bits is odd or even, so we convert the
-                        // value to `i32` using that logic here.
-                        {
-                            let flag = 0b1u32 << 31;
-
-                            if value >= flag {
-                                // Strip off negative flag bit and negate value as normal
-                                -((value & !flag) as i32)
-                            } else {
-                                value as i32
-                            }
-                        }
-                        Err(Error::WorkingCounter { .. }) => 0,
-                        Err(e) => return Err(e),
-                    };
-
-                    max_deviation = max_deviation.max(diff.abs());
-                }
+                let max_deviation = slow_group
+                    .dc_sync_status(&maindevice)
+                    .await?
+                    .max_deviation()
+                    .map(|d| d.abs())
+                    .unwrap_or(0);
 
                 log::debug!("--> Max deviation {} ns", max_deviation);
 
@@ -215,35 +194,12 @@ fn main() -> Result<(), Error> {
             if now.elapsed() >= Duration::from_millis(25) {
                 now = Instant::now();
 
-                let mut max_deviation = 0;
-
-                for s1 in fast_group.iter(&maindevice) {
-                    let diff = match s1
-                        .register_read::<u32>(RegisterAddress::DcSystemTimeDifference)
-                        .await
-                        // The returned value is NOT in two's compliment, rather the upper bit
-                        // specifies whether the number in the remaining bits is odd or even, so we
-                        // convert the value to `i32` using that logic here.
-                        .map(|value| {
-                            let flag = 0b1u32 << 31;
-
-                            let less_than = value & flag > 0;
-
-                            let value = value & !flag;
-
-                            if less_than {
-                                -(value as i32)
-                            } else {
-                                value as i32
-                            }
-                        }) {
-                        Ok(diff) => diff,
-                        Err(Error::WorkingCounter { .. }) => 0,
-                        Err(e) => return Err(e),
-                    };
-
-                    max_deviation = max_deviation.max(diff.abs());
-                }
+                let max_deviation = fast_group
+                    .dc_sync_status(&maindevice)
+                    .await?
+                    .max_deviation()
+                    .map(|d| d.abs())
+                    .unwrap_or(0);
 
                 log::debug!("--> Max deviation {} ns", max_deviation);
 
@@ -258,6 +258,7 @@ fn main() -> Result<(), Error> {
                     let diff = match s1
                         .register_read::<u32>(RegisterAddress::DcSystemTimeDifference)
                         .await
+                        .map_err(Error::without_context)
                     {
                         Ok(value) =>
                         // The returned value is NOT in two's compliment, rather the upper bit specifies
@@ -60,6 +60,28 @@ fn main() {
                     .unwrap_or(heapless::String::<64>::from_str("[no description]").unwrap()),
                 subdevice.identity()
             );
+
+            let topology = subdevice.topology();
+
+            log::info!(
+                "    {:?}, parent {:?} port {:?}, propagation delay {} ns",
+                topology.topology,
+                topology.parent_index,
+                topology.parent_port,
+                topology.propagation_delay
+            );
+        }
+
+        let network = maindevice
+            .topology::<MAX_SUBDEVICES>(group.topology())
+            .expect("Topology report");
+
+        for junction in network.junctions() {
+            log::info!(
+                "Network branches at SubDevice {:#06x} into {} SubDevice(s)",
+                junction.configured_address,
+                network.children(junction.index).count()
+            );
         }
     });
 
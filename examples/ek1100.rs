@@ -18,6 +18,7 @@ use env_logger::Env;
 use ethercrab::{
     error::Error,
     std::{ethercat_now, tx_rx_task},
+    subdevice_group::ShutdownConfig,
     MainDevice, MainDeviceConfig, PduStorage, Timeouts,
 };
 use std::{
@@ -138,23 +139,13 @@ async fn main() -> Result<(), Error> {
         tick_interval.tick().await;
     }
 
-    let group = group
-        .into_safe_op(&maindevice)
+    // Zero all outputs, then OP -> SAFE-OP -> PRE-OP -> INIT
+    let _group = group
+        .shutdown(&maindevice, ShutdownConfig::default())
         .await
-        .expect("OP -> SAFE-OP");
+        .expect("Shutdown");
 
-    log::info!("OP -> SAFE-OP");
-
-    let group = group
-        .into_pre_op(&maindevice)
-        .await
-        .expect("SAFE-OP -> PRE-OP");
-
-    log::info!("SAFE-OP -> PRE-OP");
-
-    let _group = group.into_init(&maindevice).await.expect("PRE-OP -> INIT");
-
-    log::info!("PRE-OP -> INIT, shutdown complete");
+    log::info!("Shutdown complete");
 
     Ok(())
 }
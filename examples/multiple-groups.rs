@@ -1,4 +1,5 @@
-//! Demonstrate sorting SubDevices into multiple SubDevice groups.
+//! Demonstrate sorting SubDevices into multiple SubDevice groups, and exchanging the process data
+//! of all groups in the same frames with `MainDevice::tx_rx_groups`.
 //!
 //! This demo is designed to be used with the following SubDevices:
 //!
@@ -12,10 +13,7 @@ use ethercrab::{
     std::{ethercat_now, tx_rx_task},
     MainDevice, MainDeviceConfig, PduStorage, SubDeviceGroup, Timeouts,
 };
-use std::{
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 use tokio::time::MissedTickBehavior;
 
 /// Maximum number of SubDevices that can be stored. This must be a power of 2 greater than 1.
@@ -70,8 +68,6 @@ async fn main() -> Result<(), Error> {
     // the `rt-multi-thread` feature is enabled.
     tokio::spawn(tx_rx_task(&interface, tx, rx).expect("spawn TX/RX task"));
 
-    let maindevice = Arc::new(maindevice);
-
     // Read configurations from SubDevice EEPROMs and configure devices.
     let groups = maindevice
         .init::<MAX_SUBDEVICES, _>(ethercat_now, |groups: &Groups, subdevice| {
@@ -89,78 +85,67 @@ async fn main() -> Result<(), Error> {
         fast_outputs,
     } = groups;
 
-    let maindevice_slow = maindevice.clone();
-
-    let slow_task = tokio::spawn(async move {
-        let slow_outputs = slow_outputs
-            .into_op(&maindevice_slow)
-            .await
-            .expect("PRE-OP -> OP");
-
-        let mut slow_cycle_time = tokio::time::interval(Duration::from_millis(3));
-        slow_cycle_time.set_missed_tick_behavior(MissedTickBehavior::Skip);
-
-        let slow_duration = Duration::from_millis(250);
-
-        // Only update "slow" outputs every 250ms using this instant
-        let mut tick = Instant::now();
+    let slow_outputs = slow_outputs
+        .into_op(&maindevice)
+        .await
+        .expect("Slow PRE-OP -> OP");
 
-        // EK1100 is first SubDevice, EL2889 is second
-        let mut el2889 = slow_outputs
-            .subdevice(&maindevice_slow, 1)
-            .expect("EL2889 not present!");
+    let mut fast_outputs = fast_outputs
+        .into_op(&maindevice)
+        .await
+        .expect("Fast PRE-OP -> OP");
 
-        // Set initial output state
-        el2889.io_raw_mut().1[0] = 0x01;
-        el2889.io_raw_mut().1[1] = 0x80;
+    let mut cycle_time = tokio::time::interval(Duration::from_millis(5));
+    cycle_time.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
-        loop {
-            slow_outputs.tx_rx(&maindevice_slow).await.expect("TX/RX");
+    let slow_duration = Duration::from_millis(250);
 
-            // Increment every output byte for every SubDevice by one
-            if tick.elapsed() > slow_duration {
-                tick = Instant::now();
+    // Only update "slow" outputs every 250ms using this instant
+    let mut tick = Instant::now();
 
-                let (_i, o) = el2889.io_raw_mut();
+    // EK1100 is first SubDevice, EL2889 is second
+    let mut el2889 = slow_outputs
+        .subdevice(&maindevice, 1)
+        .expect("EL2889 not present!");
 
-                // Make a nice pattern on EL2889 LEDs
-                o[0] = o[0].rotate_left(1);
-                o[1] = o[1].rotate_right(1);
-            }
+    // Set initial output state
+    el2889.io_raw_mut().1[0] = 0x01;
+    el2889.io_raw_mut().1[1] = 0x80;
 
-            slow_cycle_time.tick().await;
+    loop {
+        // Both groups are small, so their process data is sent in a single frame
+        let [slow_response, fast_response] = maindevice
+            .tx_rx_groups([&slow_outputs, &fast_outputs])
+            .await
+            .expect("TX/RX");
+
+        if !slow_response.working_counter_ok() || !fast_response.working_counter_ok() {
+            log::warn!(
+                "Working counter mismatch: slow {:?}, fast {:?}",
+                slow_response,
+                fast_response
+            );
         }
-    });
 
-    let fast_task = tokio::spawn(async move {
-        let mut fast_outputs = fast_outputs
-            .into_op(&maindevice)
-            .await
-            .expect("PRE-OP -> OP");
+        if tick.elapsed() > slow_duration {
+            tick = Instant::now();
 
-        let mut fast_cycle_time = tokio::time::interval(Duration::from_millis(5));
-        fast_cycle_time.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let (_i, o) = el2889.io_raw_mut();
 
-        loop {
-            fast_outputs.tx_rx(&maindevice).await.expect("TX/RX");
+            // Make a nice pattern on EL2889 LEDs
+            o[0] = o[0].rotate_left(1);
+            o[1] = o[1].rotate_right(1);
+        }
 
-            // Increment every output byte for every SubDevice by one
-            for mut subdevice in fast_outputs.iter(&maindevice) {
-                let (_i, o) = subdevice.io_raw_mut();
+        // Increment every output byte for every SubDevice by one
+        for mut subdevice in fast_outputs.iter(&maindevice) {
+            let (_i, o) = subdevice.io_raw_mut();
 
-                for byte in o.iter_mut() {
-                    *byte = byte.wrapping_add(1);
-                }
+            for byte in o.iter_mut() {
+                *byte = byte.wrapping_add(1);
             }
-
-            fast_cycle_time.tick().await;
         }
-    });
-
-    let (slow, fast) = tokio::join!(slow_task, fast_task);
-
-    slow.expect("slow task failed");
-    fast.expect("fast task failed");
 
-    Ok(())
+        cycle_time.tick().await;
+    }
 }
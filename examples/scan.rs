@@ -0,0 +1,75 @@
+//! Passively inspect a network that is owned by another MainDevice, without changing any
+//! SubDevice configuration.
+
+use env_logger::Env;
+use ethercrab::{std::tx_rx_task, MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+
+/// Maximum number of SubDevices that can be stored.
+const MAX_SUBDEVICES: usize = 128;
+/// Maximum PDU data payload size.
+const MAX_PDU_DATA: usize = PduStorage::element_size(1100);
+/// Maximum number of EtherCAT frames that can be in flight at any one time.
+const MAX_FRAMES: usize = 16;
+
+static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+
+fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let interface = std::env::args()
+        .nth(1)
+        .expect("Provide network interface as first argument.");
+
+    log::info!("Scanning EtherCAT network on {}...", interface);
+
+    let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+
+    let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+
+    smol::block_on(async {
+        smol::spawn(tx_rx_task(&interface, tx, rx).expect("spawn TX/RX task")).detach();
+
+        let scan = maindevice.scan::<MAX_SUBDEVICES>().await.expect("Scan");
+
+        log::info!("Found {} SubDevices", scan.subdevices.len());
+
+        for subdevice in scan.subdevices.iter() {
+            let topology = subdevice.topology;
+
+            log::info!(
+                "--> #{} address {:#06x} alias {:#06x}, {}{}",
+                topology.index,
+                topology.configured_address,
+                subdevice.alias_address,
+                subdevice.state,
+                subdevice
+                    .status_code
+                    .map(|code| format!(" ({})", code))
+                    .unwrap_or_default()
+            );
+
+            log::info!(
+                "    {:?}, parent {:?} port {:?}, ESC type {:#04x} rev {} build {}",
+                topology.topology,
+                topology.parent_index,
+                topology.parent_port,
+                subdevice.esc.esc_type,
+                subdevice.esc.revision,
+                subdevice.esc.build
+            );
+        }
+
+        for link in scan.link_errors() {
+            log::warn!(
+                "{} errors on cable into #{} port {} from {:?} port {:?}",
+                link.errors,
+                link.index,
+                link.entry_port,
+                link.parent_index,
+                link.parent_port
+            );
+        }
+    });
+
+    log::info!("Done.");
+}
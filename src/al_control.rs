@@ -1,4 +1,4 @@
-use crate::subdevice_state::SubDeviceState;
+use crate::subdevice_state::{StateRequest, SubDeviceState};
 
 /// The AL control/status word for an individual SubDevice.
 ///
@@ -37,6 +37,16 @@ impl AlControl {
     }
 }
 
+impl From<StateRequest> for AlControl {
+    fn from(request: StateRequest) -> Self {
+        Self {
+            state: request.state,
+            error: request.acknowledge,
+            id_request: false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,6 +78,17 @@ mod tests {
         assert_eq!(value, parsed);
     }
 
+    #[test]
+    fn acknowledge_request() {
+        let request = StateRequest::new(SubDeviceState::PreOp).acknowledge_error();
+
+        assert_eq!(AlControl::from(request).pack(), [0x02 | 0x10, 0x00]);
+        assert_eq!(
+            AlControl::from(StateRequest::from(SubDeviceState::PreOp)).pack(),
+            [0x02, 0x00]
+        );
+    }
+
     #[test]
     fn unpack_short() {
         let parsed = AlControl::unpack_from_slice(&[0x04 | 0x10]);
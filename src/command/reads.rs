@@ -1,4 +1,10 @@
-use crate::{error::Error, pdu_loop::ReceivedPdu, MainDevice};
+use crate::{
+    command::Command,
+    error::{Error, ErrorContext},
+    events::Event,
+    pdu_loop::ReceivedPdu,
+    piggyback, MainDevice,
+};
 use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
 
 /// Read commands that send no data.
@@ -93,9 +99,40 @@ impl WrappedRead {
         T: EtherCrabWireRead + EtherCrabWireSized,
     {
         self.common(maindevice, T::PACKED_LEN as u16)
-            .await?
-            .maybe_wkc(self.wkc)
+            .await
+            .and_then(|response| response.maybe_wkc(self.wkc))
             .and_then(|data| Ok(T::unpack_from_slice(&data)?))
+            .map_err(|e| self.context(maindevice, e))
+    }
+
+    /// Receive data and decode into a `T`, sending the PDU in the spare space of the next cyclic
+    /// process data frame.
+    ///
+    /// If no cyclic frame picks the PDU up within [`Timeouts::pdu`](crate::Timeouts::pdu), or `T`
+    /// is longer than 32 bytes, the PDU is sent in its own frame like
+    /// [`receive`](WrappedRead::receive).
+    pub async fn receive_piggyback<'maindevice, T>(
+        self,
+        maindevice: &'maindevice MainDevice<'maindevice>,
+    ) -> Result<T, Error>
+    where
+        T: EtherCrabWireRead + EtherCrabWireSized,
+    {
+        if T::PACKED_LEN > piggyback::MAX_DATA {
+            return self.receive(maindevice).await;
+        }
+
+        match maindevice
+            .piggyback_pdu(self.command.into(), &[], Some(T::PACKED_LEN as u16))
+            .await
+            .map_err(|e| self.context(maindevice, e))?
+        {
+            Some(response) => response
+                .maybe_wkc(self.wkc)
+                .and_then(|response| Ok(T::unpack_from_slice(response.data())?))
+                .map_err(|e| self.context(maindevice, e)),
+            None => self.receive(maindevice).await,
+        }
     }
 
     /// Receive a given number of bytes and return it as a slice.
@@ -104,7 +141,10 @@ impl WrappedRead {
         maindevice: &'maindevice MainDevice<'maindevice>,
         len: u16,
     ) -> Result<ReceivedPdu<'maindevice>, Error> {
-        self.common(maindevice, len).await?.maybe_wkc(self.wkc)
+        self.common(maindevice, len)
+            .await
+            .and_then(|response| response.maybe_wkc(self.wkc))
+            .map_err(|e| self.context(maindevice, e))
     }
 
     /// Receive only the working counter.
@@ -124,6 +164,22 @@ impl WrappedRead {
         self.common(maindevice, T::PACKED_LEN as u16)
             .await
             .map(|res| res.working_counter)
+            .map_err(|e| self.context(maindevice, e))
+    }
+
+    /// Attach this command to an error, publishing any working counter mismatch.
+    fn context(&self, maindevice: &MainDevice<'_>, error: Error) -> Error {
+        let command = Command::from(self.command);
+
+        if let Error::WorkingCounter { expected, received } = error {
+            maindevice.publish(Event::WorkingCounter {
+                command,
+                expected,
+                received,
+            });
+        }
+
+        error.with_context(ErrorContext::from(command))
     }
 
     // Some manual monomorphisation
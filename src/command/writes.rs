@@ -1,4 +1,10 @@
-use crate::{error::Error, pdu_loop::ReceivedPdu, MainDevice};
+use crate::{
+    command::Command,
+    error::{Error, ErrorContext},
+    events::Event,
+    pdu_loop::ReceivedPdu,
+    piggyback, MainDevice,
+};
 use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireWrite};
 
 /// Write commands.
@@ -95,7 +101,39 @@ impl WrappedWrite {
         maindevice: &'maindevice MainDevice<'maindevice>,
         data: impl EtherCrabWireWrite,
     ) -> Result<(), Error> {
-        self.common(maindevice, data, self.len_override).await?;
+        self.common(maindevice, data, self.len_override)
+            .await
+            .map_err(|e| self.context(maindevice, e))?;
+
+        Ok(())
+    }
+
+    /// Like [`send`](WrappedWrite::send), but send the PDU in the spare space of the next cyclic
+    /// process data frame.
+    ///
+    /// If no cyclic frame picks the PDU up within [`Timeouts::pdu`](crate::Timeouts::pdu), or the
+    /// PDU is longer than 32 bytes, it is sent in its own frame instead.
+    pub async fn send_piggyback<'maindevice>(
+        self,
+        maindevice: &'maindevice MainDevice<'maindevice>,
+        data: impl EtherCrabWireWrite,
+    ) -> Result<(), Error> {
+        let mut buf = [0u8; piggyback::MAX_DATA];
+
+        let Ok(packed) = data.pack_to_slice(&mut buf) else {
+            return self.send(maindevice, data).await;
+        };
+
+        if maindevice
+            .piggyback_pdu(self.command.into(), packed, self.len_override)
+            .await
+            .map_err(|e| self.context(maindevice, e))?
+            .is_none()
+        {
+            self.common(maindevice, packed, self.len_override)
+                .await
+                .map_err(|e| self.context(maindevice, e))?;
+        }
 
         Ok(())
     }
@@ -110,9 +148,10 @@ impl WrappedWrite {
         T: EtherCrabWireRead,
     {
         self.common(maindevice, value, None)
-            .await?
-            .maybe_wkc(self.wkc)
+            .await
+            .and_then(|response| response.maybe_wkc(self.wkc))
             .and_then(|data| Ok(T::unpack_from_slice(&data)?))
+            .map_err(|e| self.context(maindevice, e))
     }
 
     /// Similar to [`send_receive`](WrappedWrite::send_receive) but returns a slice.
@@ -122,8 +161,24 @@ impl WrappedWrite {
         value: impl EtherCrabWireWrite,
     ) -> Result<ReceivedPdu<'maindevice>, Error> {
         self.common(maindevice, value, None)
-            .await?
-            .maybe_wkc(self.wkc)
+            .await
+            .and_then(|response| response.maybe_wkc(self.wkc))
+            .map_err(|e| self.context(maindevice, e))
+    }
+
+    /// Attach this command to an error, publishing any working counter mismatch.
+    fn context(&self, maindevice: &MainDevice<'_>, error: Error) -> Error {
+        let command = Command::from(self.command);
+
+        if let Error::WorkingCounter { expected, received } = error {
+            maindevice.publish(Event::WorkingCounter {
+                command,
+                expected,
+                received,
+            });
+        }
+
+        error.with_context(ErrorContext::from(command))
     }
 
     // Some manual monomorphisation
@@ -91,6 +91,46 @@ async fn write_dc_parameters(
         now_nanos
     );
 
+    write_system_time_offset(maindevice, subdevice, system_time_offset).await
+}
+
+/// Restore the DC system time offset and propagation delay of a SubDevice that has lost them, e.g.
+/// because it was power cycled.
+///
+/// The offset is computed from the current time of the DC reference SubDevice. Any remaining error
+/// is corrected by the drift compensation sent with each process data cycle.
+pub(crate) async fn restore_dc_parameters(
+    maindevice: &MainDevice<'_>,
+    subdevice: &SubDevice,
+    reference: u16,
+) -> Result<(), Error> {
+    let reference_time = Command::fprd(reference, RegisterAddress::DcSystemTime.into())
+        .receive::<u64>(maindevice)
+        .await?;
+
+    let local_time = Command::fprd(
+        subdevice.configured_address(),
+        RegisterAddress::DcSystemTime.into(),
+    )
+    .receive::<u64>(maindevice)
+    .await?;
+
+    let system_time_offset = reference_time.wrapping_sub(local_time) as i64;
+
+    fmt::debug!(
+        "Restoring SubDevice {:#06x} system time offset to {} ns",
+        subdevice.configured_address(),
+        system_time_offset
+    );
+
+    write_system_time_offset(maindevice, subdevice, system_time_offset).await
+}
+
+async fn write_system_time_offset(
+    maindevice: &MainDevice<'_>,
+    subdevice: &SubDevice,
+    system_time_offset: i64,
+) -> Result<(), Error> {
     Command::fpwr(
         subdevice.configured_address(),
         RegisterAddress::DcSystemTimeOffset.into(),
@@ -303,34 +343,52 @@ fn configure_subdevice_offsets(
     }
 }
 
-/// Assign parent/child relationships and compute propagation delays for all SubDevices.
-fn assign_parent_relationships(subdevices: &mut [SubDevice]) -> Result<(), Error> {
-    let mut delay_accum = 0;
+/// Find a SubDevice's parent and assign the parent's next open port to it.
+fn assign_parent(parents: &mut [SubDevice], subdevice: &mut SubDevice) -> Result<(), Error> {
+    subdevice.parent_index = find_subdevice_parent(parents, subdevice)?;
 
+    fmt::debug!(
+        "SubDevice {:#06x} {} {}",
+        subdevice.configured_address(),
+        subdevice.name,
+        subdevice.flags
+    );
+
+    // If this SubDevice has a parent, find it, then assign the parent's next open port to this
+    // SubDevice, estabilishing the relationship between them by index.
+    if let Some(parent_idx) = subdevice.parent_index {
+        let parent = fmt::unwrap_opt!(parents.iter_mut().find(|parent| parent.index == parent_idx));
+
+        subdevice.parent_port = Some(fmt::unwrap_opt!(
+            parent.ports.assign_next_downstream_port(subdevice.index),
+            "no free ports on parent"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Assign parent/child relationships for all SubDevices without computing propagation delays.
+pub(crate) fn assign_topology(subdevices: &mut [SubDevice]) -> Result<(), Error> {
     for i in 0..subdevices.len() {
         let (parents, rest) = subdevices.split_at_mut(i);
         let subdevice = rest.first_mut().ok_or(Error::Internal)?;
 
-        subdevice.parent_index = find_subdevice_parent(parents, subdevice)?;
+        assign_parent(parents, subdevice)?;
+    }
 
-        fmt::debug!(
-            "SubDevice {:#06x} {} {}",
-            subdevice.configured_address(),
-            subdevice.name,
-            subdevice.flags
-        );
+    Ok(())
+}
+
+/// Assign parent/child relationships and compute propagation delays for all SubDevices.
+fn assign_parent_relationships(subdevices: &mut [SubDevice]) -> Result<(), Error> {
+    let mut delay_accum = 0;
 
-        // If this SubDevice has a parent, find it, then assign the parent's next open port to this
-        // SubDevice, estabilishing the relationship between them by index.
-        if let Some(parent_idx) = subdevice.parent_index {
-            let parent =
-                fmt::unwrap_opt!(parents.iter_mut().find(|parent| parent.index == parent_idx));
+    for i in 0..subdevices.len() {
+        let (parents, rest) = subdevices.split_at_mut(i);
+        let subdevice = rest.first_mut().ok_or(Error::Internal)?;
 
-            fmt::unwrap_opt!(
-                parent.ports.assign_next_downstream_port(subdevice.index),
-                "no free ports on parent"
-            );
-        }
+        assign_parent(parents, subdevice)?;
 
         if subdevice.flags.dc_supported {
             configure_subdevice_offsets(subdevice, parents, &mut delay_accum);
@@ -396,8 +454,8 @@ fn assign_parent_relationships(subdevices: &mut [SubDevice]) -> Result<(), Error
             print!("],");
 
             print!(
-                "{:?}, {}, ",
-                subdevice.parent_index, subdevice.propagation_delay
+                "{:?}, {:?}, {}, ",
+                subdevice.parent_index, subdevice.parent_port, subdevice.propagation_delay
             );
 
             print!("),");
@@ -816,25 +874,26 @@ mod tests {
 
         let downstreams = [
             // Index 0: EK1100 (Fork)
-            ([None, None, Some(1), Some(3)], None, 0),
+            ([None, None, Some(1), Some(3)], None, None, 0),
             // Index 1: EK1122 (Passthrough)
-            ([None, None, None, Some(2)], Some(0), 145),
+            ([None, None, None, Some(2)], Some(0), Some(1), 145),
             // Index 2: EL9560 (LineEnd)
-            ([None, None, None, None], Some(1), 300),
+            ([None, None, None, None], Some(1), Some(2), 300),
             // Index 3: EK1914 (Passthrough)
-            ([None, None, Some(4), None], Some(0), 1085),
+            ([None, None, Some(4), None], Some(0), Some(2), 1085),
             // Index 4: EL1008 (LineEnd)
-            ([None, None, None, None], Some(3), 1240),
+            ([None, None, None, None], Some(3), Some(1), 1240),
         ];
 
         let expected = {
             let mut expected = subdevices.clone();
 
             expected.iter_mut().zip(downstreams).for_each(
-                |(subdevice, ([d0, d3, d1, d2], parent_index, propagation_delay))| {
+                |(subdevice, ([d0, d3, d1, d2], parent_index, parent_port, propagation_delay))| {
                     subdevice.ports.set_downstreams(d0, d3, d1, d2);
 
                     subdevice.parent_index = parent_index;
+                    subdevice.parent_port = parent_port;
                     subdevice.propagation_delay = propagation_delay;
                 },
             );
@@ -929,27 +988,28 @@ mod tests {
 
         let downstreams = [
             // Index 0: EK1100 (Passthrough)
-            ([None, None, Some(1), None], None, 0),
+            ([None, None, Some(1), None], None, None, 0),
             // Index 1: EK1122 (Cross)
-            ([None, Some(2), Some(4), Some(5)], Some(0), 145),
+            ([None, Some(2), Some(4), Some(5)], Some(0), Some(1), 145),
             // Index 2: EK1914 (Passthrough)
-            ([None, None, Some(3), None], Some(1), 665),
+            ([None, None, Some(3), None], Some(1), Some(3), 665),
             // Index 3: EL1008 (LineEnd)
-            ([None, None, None, None], Some(2), 820),
+            ([None, None, None, None], Some(2), Some(1), 820),
             // Index 4: EK1101 (LineEnd)
-            ([None, None, None, None], Some(1), 2035),
+            ([None, None, None, None], Some(1), Some(1), 2035),
             // Index 5: EL9560 (LineEnd)
-            ([None, None, None, None], Some(1), 2720),
+            ([None, None, None, None], Some(1), Some(2), 2720),
         ];
 
         let expected = {
             let mut expected = subdevices.clone();
 
             expected.iter_mut().zip(downstreams).for_each(
-                |(subdevice, ([d0, d3, d1, d2], parent_index, propagation_delay))| {
+                |(subdevice, ([d0, d3, d1, d2], parent_index, parent_port, propagation_delay))| {
                     subdevice.ports.set_downstreams(d0, d3, d1, d2);
 
                     subdevice.parent_index = parent_index;
+                    subdevice.parent_port = parent_port;
                     subdevice.propagation_delay = propagation_delay;
                 },
             );
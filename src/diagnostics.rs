@@ -0,0 +1,368 @@
+//! Link and port error counters for finding faulty cables and connectors.
+
+use crate::{command::Command, error::Error, MainDevice, RegisterAddress, SubDeviceTopology};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized, WireError};
+
+/// Error counters of a single SubDevice port.
+///
+/// All counters saturate at `255`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PortErrorCounters {
+    /// Frames received with an invalid structure or checksum.
+    pub invalid_frames: u8,
+
+    /// Physical layer errors, e.g. coding errors, seen while receiving.
+    pub rx_errors: u8,
+
+    /// Frames that arrived already marked as faulty by a SubDevice earlier in the network.
+    ///
+    /// These errors did not happen on the cable connected to this port.
+    pub forwarded_rx_errors: u8,
+
+    /// The number of times the link on this port was lost.
+    pub lost_links: u8,
+}
+
+impl PortErrorCounters {
+    /// The number of errors that happened on the cable connected to this port, i.e. every counter
+    /// except [`forwarded_rx_errors`](PortErrorCounters::forwarded_rx_errors).
+    pub fn local_errors(&self) -> u32 {
+        u32::from(self.invalid_frames) + u32::from(self.rx_errors) + u32::from(self.lost_links)
+    }
+}
+
+/// The error counters of a SubDevice, read from registers `0x0300` to `0x0313`.
+///
+/// These can be cleared with [`MainDevice::clear_error_counters`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ErrorCounters {
+    /// Counters for ports 0 to 3, indexed by port number.
+    pub ports: [PortErrorCounters; 4],
+
+    /// Frames that the EtherCAT Processing Unit could not process, e.g. because of a malformed
+    /// datagram.
+    pub processing_unit_errors: u8,
+
+    /// Errors on the Process Data Interface between the EtherCAT controller and the SubDevice
+    /// application.
+    pub pdi_errors: u8,
+}
+
+impl EtherCrabWireSized for ErrorCounters {
+    const PACKED_LEN: usize = 0x14;
+
+    type Buffer = [u8; Self::PACKED_LEN];
+
+    fn buffer() -> Self::Buffer {
+        [0u8; Self::PACKED_LEN]
+    }
+}
+
+impl EtherCrabWireRead for ErrorCounters {
+    fn unpack_from_slice(buf: &[u8]) -> Result<Self, WireError> {
+        let buf = buf
+            .get(0..Self::PACKED_LEN)
+            .ok_or(WireError::ReadBufferTooShort)?;
+
+        let port = |number: usize| PortErrorCounters {
+            invalid_frames: buf[number * 2],
+            rx_errors: buf[number * 2 + 1],
+            forwarded_rx_errors: buf[0x08 + number],
+            lost_links: buf[0x10 + number],
+        };
+
+        Ok(Self {
+            ports: [port(0), port(1), port(2), port(3)],
+            processing_unit_errors: buf[0x0c],
+            pdi_errors: buf[0x0d],
+        })
+    }
+}
+
+/// Errors counted on the cable between a SubDevice and its parent in the network tree.
+///
+/// Errors in frames travelling away from the MainDevice are counted by the SubDevice's entry port,
+/// and errors in frames returning to the MainDevice by the parent's port.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LinkErrors {
+    /// The index of the SubDevice at the far end of the cable.
+    pub index: u16,
+
+    /// The configured station address of the SubDevice at the far end of the cable.
+    pub configured_address: u16,
+
+    /// The port on the SubDevice the cable is plugged into.
+    pub entry_port: u8,
+
+    /// The index of the parent SubDevice, or `None` if the cable goes to the MainDevice.
+    pub parent_index: Option<u16>,
+
+    /// The port on the parent SubDevice the cable is plugged into.
+    pub parent_port: Option<u8>,
+
+    /// Errors counted at both ends of the cable.
+    ///
+    /// If the parent is not in the list of SubDevices passed to [`link_errors`], only errors
+    /// counted by this SubDevice are included.
+    pub errors: u32,
+}
+
+/// Attribute error counters to the cables between SubDevices, using the topology discovered during
+/// [`MainDevice::init`].
+///
+/// Only cables with at least one error are returned, sorted with the most errors first. The first
+/// entry is the most likely location of a bad cable or connector. At most `N` cables are returned.
+pub fn link_errors<const N: usize>(
+    subdevices: &[(SubDeviceTopology, ErrorCounters)],
+) -> heapless::Vec<LinkErrors, N> {
+    let links = subdevices
+        .iter()
+        .filter_map(|(topology, counters)| {
+            let downstream = counters
+                .ports
+                .get(usize::from(topology.entry_port))?
+                .local_errors();
+
+            let upstream = topology
+                .parent_index
+                .zip(topology.parent_port)
+                .and_then(|(parent_index, parent_port)| {
+                    let (_, parent) = subdevices
+                        .iter()
+                        .find(|(parent, _)| parent.index == parent_index)?;
+
+                    parent.ports.get(usize::from(parent_port))
+                })
+                .map_or(0, PortErrorCounters::local_errors);
+
+            Some(LinkErrors {
+                index: topology.index,
+                configured_address: topology.configured_address,
+                entry_port: topology.entry_port,
+                parent_index: topology.parent_index,
+                parent_port: topology.parent_port,
+                errors: downstream + upstream,
+            })
+        })
+        .filter(|link| link.errors > 0);
+
+    // Keep the `N` links with the most errors, most errors first. Links with the same number of
+    // errors stay in network order.
+    let mut worst = heapless::Vec::<LinkErrors, N>::new();
+
+    for link in links {
+        let position = worst
+            .iter()
+            .position(|other| other.errors < link.errors)
+            .unwrap_or(worst.len());
+
+        if position < N {
+            if worst.is_full() {
+                worst.pop();
+            }
+
+            // Can't fail as there is space at `position`
+            let _ = worst.insert(position, link);
+        }
+    }
+
+    worst
+}
+
+impl<'sto> MainDevice<'sto> {
+    /// Reset the RX error, forwarded RX error, processing unit, PDI and lost link counters of every
+    /// SubDevice on the network.
+    pub async fn clear_error_counters(&self) -> Result<(), Error> {
+        Command::bwr(RegisterAddress::RxErrorCounter.into())
+            .ignore_wkc()
+            .send(self, [0u8; ErrorCounters::PACKED_LEN])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SubDevicePort, Topology};
+
+    fn topology(index: u16, parent: Option<(u16, u8)>, entry_port: u8) -> SubDeviceTopology {
+        let port = |number| SubDevicePort {
+            number,
+            active: true,
+            dc_receive_time: 0,
+            downstream_to: None,
+        };
+
+        SubDeviceTopology {
+            index,
+            configured_address: 0x1000 + index,
+            parent_index: parent.map(|(index, _)| index),
+            parent_port: parent.map(|(_, port)| port),
+            entry_port,
+            topology: Topology::Passthrough,
+            ports: [port(0), port(3), port(1), port(2)],
+            dc_supported: false,
+            dc_receive_time: 0,
+            loop_propagation_time: None,
+            propagation_delay: 0,
+        }
+    }
+
+    fn counters(port: usize, counts: PortErrorCounters) -> ErrorCounters {
+        let mut counters = ErrorCounters::default();
+
+        counters.ports[port] = counts;
+
+        counters
+    }
+
+    #[test]
+    fn unpack() {
+        let raw = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // RX errors
+            0x09, 0x0a, 0x0b, 0x0c, // Forwarded RX errors
+            0x0d, 0x0e, 0x00, 0x00, // ECAT processing unit, PDI, reserved
+            0x0f, 0x10, 0x11, 0x12, // Lost links
+        ];
+
+        let counters = ErrorCounters::unpack_from_slice(&raw).expect("unpack");
+
+        assert_eq!(
+            counters.ports[1],
+            PortErrorCounters {
+                invalid_frames: 0x03,
+                rx_errors: 0x04,
+                forwarded_rx_errors: 0x0a,
+                lost_links: 0x10,
+            }
+        );
+        assert_eq!(counters.ports[3].lost_links, 0x12);
+        assert_eq!(counters.processing_unit_errors, 0x0d);
+        assert_eq!(counters.pdi_errors, 0x0e);
+    }
+
+    #[test]
+    fn bad_cable_in_line() {
+        let subdevices = [
+            (topology(0, None, 0), ErrorCounters::default()),
+            (
+                topology(1, Some((0, 1)), 0),
+                // Errors on the return path into 0x1001 from the cable to 0x1002
+                counters(
+                    1,
+                    PortErrorCounters {
+                        rx_errors: 2,
+                        ..PortErrorCounters::default()
+                    },
+                ),
+            ),
+            (
+                topology(2, Some((1, 1)), 0),
+                counters(
+                    0,
+                    PortErrorCounters {
+                        invalid_frames: 5,
+                        rx_errors: 3,
+                        ..PortErrorCounters::default()
+                    },
+                ),
+            ),
+            (
+                topology(3, Some((2, 1)), 0),
+                // Errors that happened upstream don't count against this cable
+                counters(
+                    0,
+                    PortErrorCounters {
+                        forwarded_rx_errors: 8,
+                        ..PortErrorCounters::default()
+                    },
+                ),
+            ),
+        ];
+
+        let links = link_errors::<4>(&subdevices);
+
+        assert_eq!(
+            links.as_slice(),
+            &[LinkErrors {
+                index: 2,
+                configured_address: 0x1002,
+                entry_port: 0,
+                parent_index: Some(1),
+                parent_port: Some(1),
+                errors: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn sorted_by_errors() {
+        let lost_link = PortErrorCounters {
+            lost_links: 1,
+            ..PortErrorCounters::default()
+        };
+
+        let subdevices = [
+            (topology(0, None, 0), counters(0, lost_link)),
+            (
+                topology(1, Some((0, 3)), 0),
+                counters(
+                    0,
+                    PortErrorCounters {
+                        rx_errors: 4,
+                        ..PortErrorCounters::default()
+                    },
+                ),
+            ),
+        ];
+
+        let links = link_errors::<4>(&subdevices);
+
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| link.index)
+                .collect::<heapless::Vec<_, 4>>(),
+            [1, 0]
+        );
+        assert_eq!(links[1].parent_index, None);
+    }
+
+    #[test]
+    fn most_errors_kept() {
+        let errors = |rx_errors| {
+            counters(
+                0,
+                PortErrorCounters {
+                    rx_errors,
+                    ..PortErrorCounters::default()
+                },
+            )
+        };
+
+        let subdevices = [
+            (topology(0, None, 0), errors(1)),
+            (topology(1, Some((0, 1)), 0), errors(2)),
+            (topology(2, Some((1, 1)), 0), errors(5)),
+            (topology(3, Some((2, 1)), 0), errors(2)),
+        ];
+
+        let links = link_errors::<2>(&subdevices);
+
+        // The worst cable is last in the network but must still be returned, and equal counts stay
+        // in network order
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| (link.index, link.errors))
+                .collect::<heapless::Vec<_, 2>>(),
+            [(2, 5), (1, 2)]
+        );
+    }
+}
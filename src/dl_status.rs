@@ -52,6 +52,19 @@ pub struct DlStatus {
     pub signal_port3: bool,
 }
 
+impl DlStatus {
+    /// Whether the given physical port (0-3) has a link.
+    pub fn link(&self, port: u8) -> bool {
+        match port {
+            0 => self.link_port0,
+            1 => self.link_port1,
+            2 => self.link_port2,
+            3 => self.link_port3,
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,10 @@
 //! EtherCrab error types.
 
 pub use crate::coe::abort_code::CoeAbortCode;
-use crate::{command::Command, fmt, AlStatusCode, SubDeviceState};
+use crate::{
+    command::{Command, Reads, Writes},
+    fmt, AlStatusCode, PdoMappingEntry, SubDeviceState,
+};
 use core::{cell::BorrowError, num::TryFromIntError};
 
 /// An EtherCrab error.
@@ -88,6 +91,20 @@ pub enum Error {
         /// SubDevice address.
         configured_address: u16,
     },
+    /// A SubDevice in a group did not reach the state requested for the whole group.
+    TransitionFailed {
+        /// SubDevice address.
+        configured_address: u16,
+
+        /// The state requested for the group.
+        expected: SubDeviceState,
+
+        /// The state the SubDevice is in, or [`SubDeviceState::None`] if it did not respond.
+        actual: SubDeviceState,
+
+        /// The AL status code read from the SubDevice.
+        status_code: AlStatusCode,
+    },
 
     /// An error occurred encoding or decoding an item.
     Wire(ethercrab_wire::WireError),
@@ -97,6 +114,115 @@ pub enum Error {
 
     /// A distributed clock error occurred.
     DistributedClock(DistributedClockError),
+
+    /// A SubDevice's process data does not match the requested layout.
+    PdiLayout(PdiLayoutError),
+
+    /// A watchdog configuration is invalid.
+    Watchdog(WatchdogError),
+
+    /// A hot-connect segment could not be connected.
+    HotConnect(HotConnectError),
+
+    /// An error with details of the command and SubDevice that caused it.
+    ///
+    /// Errors returned when sending a [`Command`] are wrapped in this variant. Use
+    /// [`without_context`](Error::without_context) to get the plain error, e.g. to match on
+    /// [`Error::WorkingCounter`].
+    Context {
+        /// The underlying error.
+        error: ContextError,
+
+        /// Where the error happened.
+        context: ErrorContext,
+    },
+}
+
+impl Error {
+    /// Attach a command and SubDevice context to this error.
+    ///
+    /// Only errors that can be represented by [`ContextError`] get a context. Other errors are
+    /// returned unchanged. If this error already has a context, any fields that are not set are
+    /// filled in from `context`.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        let error = match self {
+            Error::Context {
+                error,
+                context: existing,
+            } => {
+                return Error::Context {
+                    error,
+                    context: ErrorContext {
+                        command: existing.command.or(context.command),
+                        configured_address: existing
+                            .configured_address
+                            .or(context.configured_address),
+                        #[cfg(feature = "error-subdevice-name")]
+                        subdevice_name: existing.subdevice_name.or(context.subdevice_name),
+                    },
+                }
+            }
+            Error::Timeout => ContextError::Timeout,
+            Error::WorkingCounter { expected, received } => {
+                ContextError::WorkingCounter { expected, received }
+            }
+            Error::Pdu(e) => ContextError::Pdu(e),
+            Error::Wire(e) => ContextError::Wire(e),
+            other => return other,
+        };
+
+        Error::Context { error, context }
+    }
+
+    /// Attach the address of the SubDevice that caused this error, and its name if the
+    /// `error-subdevice-name` feature is enabled.
+    #[cfg_attr(not(feature = "error-subdevice-name"), allow(unused_variables))]
+    pub(crate) fn with_subdevice(self, configured_address: u16, name: &str) -> Self {
+        self.with_context(ErrorContext {
+            command: None,
+            configured_address: Some(configured_address),
+            #[cfg(feature = "error-subdevice-name")]
+            subdevice_name: Some(SubDeviceName::new(name)),
+        })
+    }
+
+    /// Remove any [`ErrorContext`], returning the underlying error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ethercrab::{
+    ///     error::{Error, ErrorContext},
+    ///     Command, Reads,
+    /// };
+    ///
+    /// let command = Command::Read(Reads::Fprd {
+    ///     address: 0x1001,
+    ///     register: 0x0130,
+    /// });
+    ///
+    /// let error = Error::WorkingCounter {
+    ///     expected: 1,
+    ///     received: 0,
+    /// }
+    /// .with_context(ErrorContext::from(command));
+    ///
+    /// assert!(matches!(error.without_context(), Error::WorkingCounter { .. }));
+    /// ```
+    pub fn without_context(self) -> Self {
+        match self {
+            Error::Context { error, .. } => error.into(),
+            other => other,
+        }
+    }
+
+    /// Get the command and SubDevice that caused this error, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(feature = "std")]
@@ -154,10 +280,195 @@ impl core::fmt::Display for Error {
                 "SubDevice {:#06x} state is invalid: {}, expected {}",
                 configured_address, actual, expected
             ),
+            Error::TransitionFailed {
+                configured_address,
+                expected,
+                actual,
+                status_code,
+            } => write!(
+                f,
+                "SubDevice {:#06x} did not reach {}, state is {}: {}",
+                configured_address, expected, actual, status_code
+            ),
             Error::Wire(e) => write!(f, "wire encode/decode error: {}", e),
             Error::SubDevice(e) => write!(f, "subdevice error: {}", e),
             Error::DistributedClock(e) => write!(f, "distributed clock: {}", e),
+            Error::PdiLayout(e) => write!(f, "PDI layout: {}", e),
+            Error::Watchdog(e) => write!(f, "watchdog: {}", e),
+            Error::HotConnect(e) => write!(f, "hot-connect: {}", e),
+            Error::Context { error, context } => {
+                write!(f, "{} ({})", Error::from(*error), context)
+            }
+        }
+    }
+}
+
+/// An error that can be returned with an [`ErrorContext`] in [`Error::Context`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ContextError {
+    /// See [`Error::Timeout`].
+    Timeout,
+    /// See [`Error::WorkingCounter`].
+    WorkingCounter {
+        /// The expected working counter value.
+        expected: u16,
+        /// The actual value received.
+        received: u16,
+    },
+    /// See [`Error::Pdu`].
+    Pdu(PduError),
+    /// See [`Error::Wire`].
+    Wire(ethercrab_wire::WireError),
+}
+
+impl From<ContextError> for Error {
+    fn from(value: ContextError) -> Self {
+        match value {
+            ContextError::Timeout => Error::Timeout,
+            ContextError::WorkingCounter { expected, received } => {
+                Error::WorkingCounter { expected, received }
+            }
+            ContextError::Pdu(e) => Error::Pdu(e),
+            ContextError::Wire(e) => Error::Wire(e),
+        }
+    }
+}
+
+/// The command and SubDevice involved in an error.
+///
+/// Every field is optional as not all of them are known everywhere an error can happen. For
+/// example, a broadcast read has a command but no SubDevice.
+///
+/// The SubDevice name is only stored with the `error-subdevice-name` feature, as it roughly doubles
+/// the size of every [`Error`]. Without it, find the SubDevice by its
+/// [`configured_address`](ErrorContext::configured_address) to report its name.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ErrorContext {
+    /// The command that failed, including its address and register.
+    pub command: Option<Command>,
+
+    /// The configured station address of the SubDevice.
+    pub configured_address: Option<u16>,
+
+    /// The name of the SubDevice.
+    #[cfg(feature = "error-subdevice-name")]
+    pub subdevice_name: Option<SubDeviceName>,
+}
+
+impl From<Command> for ErrorContext {
+    fn from(command: Command) -> Self {
+        let configured_address = match command {
+            Command::Read(Reads::Fprd { address, .. } | Reads::Frmw { address, .. })
+            | Command::Write(Writes::Fpwr { address, .. }) => Some(address),
+            _ => None,
+        };
+
+        Self {
+            command: Some(command),
+            configured_address,
+            #[cfg(feature = "error-subdevice-name")]
+            subdevice_name: None,
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let separator = match self.command {
+            Some(command) => {
+                write!(f, "{}", command)?;
+
+                ", "
+            }
+            None => "",
+        };
+
+        #[cfg(feature = "error-subdevice-name")]
+        if let Some(name) = self.subdevice_name {
+            return match self.configured_address {
+                Some(address) => write!(f, "{}SubDevice {:#06x} {}", separator, address, name),
+                None => write!(f, "{}SubDevice {}", separator, name),
+            };
+        }
+
+        match self.configured_address {
+            Some(address) => write!(f, "{}SubDevice {:#06x}", separator, address),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A SubDevice name stored in an [`ErrorContext`].
+///
+/// Names longer than 31 bytes are truncated so errors stay small and `Copy`. Only available with
+/// the `error-subdevice-name` feature.
+#[cfg(feature = "error-subdevice-name")]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct SubDeviceName {
+    buf: [u8; Self::CAPACITY],
+    len: u8,
+}
+
+#[cfg(feature = "error-subdevice-name")]
+impl SubDeviceName {
+    const CAPACITY: usize = 31;
+
+    pub(crate) fn new(name: &str) -> Self {
+        let mut len = name.len().min(Self::CAPACITY);
+
+        while !name.is_char_boundary(len) {
+            len -= 1;
         }
+
+        let mut buf = [0u8; Self::CAPACITY];
+
+        buf[0..len].copy_from_slice(&name.as_bytes()[0..len]);
+
+        Self {
+            buf,
+            len: len as u8,
+        }
+    }
+
+    /// Get the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only ever created from a `&str` cut at a char boundary
+        core::str::from_utf8(&self.buf[0..usize::from(self.len)]).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "error-subdevice-name")]
+impl core::fmt::Debug for SubDeviceName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(feature = "error-subdevice-name")]
+impl core::fmt::Display for SubDeviceName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(all(feature = "error-subdevice-name", feature = "defmt"))]
+impl defmt::Format for SubDeviceName {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.as_str())
+    }
+}
+
+#[cfg(all(feature = "error-subdevice-name", feature = "serde"))]
+impl serde::Serialize for SubDeviceName {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
     }
 }
 
@@ -235,19 +546,185 @@ impl core::fmt::Display for PduError {
     }
 }
 
-/// CoE mailbox error.
+/// Distributed Clock error.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DistributedClockError {
     /// No DC System Time reference SubDevice was found.
     NoReference,
+    /// The SYNC0 cycle time is zero or does not fit in the 32 bit cycle time register.
+    InvalidCycleTime,
+    /// A SYNC0 or SYNC1 shift is not shorter than the SYNC0 cycle time.
+    ShiftTooLong,
+    /// The SYNC1 cycle time plus shift does not fit in the 32 bit cycle time register.
+    InvalidSync1CycleTime,
+    /// The SYNC pulse length is not shorter than the SYNC0 cycle time, or does not fit in the pulse
+    /// length register.
+    InvalidPulseLength,
 }
 
 impl core::fmt::Display for DistributedClockError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::NoReference => f.write_str("No DC reference SubDevice found"),
+            Self::InvalidCycleTime => f.write_str("invalid SYNC0 cycle time"),
+            Self::ShiftTooLong => f.write_str("SYNC shift must be shorter than SYNC0 cycle time"),
+            Self::InvalidSync1CycleTime => f.write_str("invalid SYNC1 cycle time"),
+            Self::InvalidPulseLength => f.write_str("invalid SYNC pulse length"),
+        }
+    }
+}
+
+/// Watchdog configuration error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum WatchdogError {
+    /// The watchdog tick is shorter than 80ns or longer than 2.62ms.
+    InvalidTick,
+    /// A watchdog timeout is non-zero but shorter than one tick, or longer than `65535` ticks.
+    InvalidTimeout,
+}
+
+impl core::fmt::Display for WatchdogError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidTick => f.write_str("invalid watchdog tick"),
+            Self::InvalidTimeout => f.write_str("watchdog timeout cannot be represented"),
+        }
+    }
+}
+
+/// Hot-connect segment error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum HotConnectError {
+    /// The segment's parent port is not in the range `0..=3`.
+    InvalidPort(u8),
+    /// The segment's configured station addresses overlap the addresses assigned during init.
+    AddressInUse,
+    /// The group to attach the segment to already contains SubDevices.
+    GroupNotEmpty,
+    /// The segment's parent has no link on the segment port, or no unconfigured SubDevices were
+    /// found directly after it on the network.
+    NoSubDevices,
+}
+
+impl core::fmt::Display for HotConnectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPort(port) => write!(f, "invalid parent port {}", port),
+            Self::AddressInUse => f.write_str("segment addresses overlap existing SubDevices"),
+            Self::GroupNotEmpty => f.write_str("group already contains SubDevices"),
+            Self::NoSubDevices => f.write_str("no unconfigured SubDevices found"),
+        }
+    }
+}
+
+/// Process Data Image (PDI) layout error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PdiLayoutError {
+    /// The SubDevice's input data is a different length to the requested layout.
+    InputLength {
+        /// Length of the requested layout in bytes.
+        expected: usize,
+        /// Length of the SubDevice's input data in bytes.
+        actual: usize,
+    },
+    /// The SubDevice's output data is a different length to the requested layout.
+    OutputLength {
+        /// Length of the requested layout in bytes.
+        expected: usize,
+        /// Length of the SubDevice's output data in bytes.
+        actual: usize,
+    },
+    /// The SubDevice's input PDO mapping is different to the expected mapping.
+    InputMapping(PdoMappingError),
+    /// The SubDevice's output PDO mapping is different to the expected mapping.
+    OutputMapping(PdoMappingError),
+}
+
+impl core::fmt::Display for PdiLayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InputLength { expected, actual } => {
+                write!(f, "inputs are {} bytes, expected {}", actual, expected)
+            }
+            Self::OutputLength { expected, actual } => {
+                write!(f, "outputs are {} bytes, expected {}", actual, expected)
+            }
+            Self::InputMapping(e) => write!(f, "input mapping: {}", e),
+            Self::OutputMapping(e) => write!(f, "output mapping: {}", e),
+        }
+    }
+}
+
+/// A discovered PDO mapping does not match the expected mapping.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PdoMappingError {
+    /// The mapping has not been discovered yet, had too many entries to be stored, or the
+    /// `pdo-mapping` feature is disabled.
+    Unavailable,
+    /// A mapping entry differs from the expected entry.
+    Mismatch {
+        /// Position of the entry in the mapping, starting at `0`.
+        position: usize,
+        /// The expected entry.
+        expected: PdoMappingEntry,
+        /// The entry discovered on the SubDevice.
+        actual: PdoMappingEntry,
+    },
+    /// The SubDevice maps fewer entries than expected.
+    Missing {
+        /// Position of the first missing entry.
+        position: usize,
+        /// The expected entry.
+        expected: PdoMappingEntry,
+    },
+    /// The SubDevice maps more entries than expected.
+    Unexpected {
+        /// Position of the first extra entry.
+        position: usize,
+        /// The entry discovered on the SubDevice.
+        actual: PdoMappingEntry,
+    },
+}
+
+impl core::fmt::Display for PdoMappingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unavailable => f.write_str("mapping is not available"),
+            Self::Mismatch {
+                position,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "entry {} is {:#06x}:{} ({} bits), expected {:#06x}:{} ({} bits)",
+                position,
+                actual.index,
+                actual.sub_index,
+                actual.bit_len,
+                expected.index,
+                expected.sub_index,
+                expected.bit_len
+            ),
+            Self::Missing { position, expected } => write!(
+                f,
+                "entry {} is missing, expected {:#06x}:{} ({} bits)",
+                position, expected.index, expected.sub_index, expected.bit_len
+            ),
+            Self::Unexpected { position, actual } => write!(
+                f,
+                "unexpected entry {}: {:#06x}:{} ({} bits)",
+                position, actual.index, actual.sub_index, actual.bit_len
+            ),
         }
     }
 }
@@ -440,6 +917,24 @@ impl From<DistributedClockError> for Error {
     }
 }
 
+impl From<WatchdogError> for Error {
+    fn from(e: WatchdogError) -> Self {
+        Self::Watchdog(e)
+    }
+}
+
+impl From<HotConnectError> for Error {
+    fn from(e: HotConnectError) -> Self {
+        Self::HotConnect(e)
+    }
+}
+
+impl From<PdiLayoutError> for Error {
+    fn from(e: PdiLayoutError) -> Self {
+        Self::PdiLayout(e)
+    }
+}
+
 impl From<PduValidationError> for PduError {
     fn from(e: PduValidationError) -> Self {
         Self::Validation(e)
@@ -459,3 +954,93 @@ impl From<ethercrab_wire::WireError> for Error {
         Self::Wire(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    const FPRD: Command = Command::Read(Reads::Fprd {
+        address: 0x1001,
+        register: 0x0130,
+    });
+
+    #[test]
+    fn context_round_trip() {
+        let error = Error::WorkingCounter {
+            expected: 1,
+            received: 0,
+        };
+
+        let with_context = error
+            .with_context(ErrorContext::from(FPRD))
+            .with_subdevice(0x1001, "EL2889");
+
+        assert_eq!(
+            with_context.context().and_then(|context| context.command),
+            Some(FPRD)
+        );
+        assert_eq!(with_context.without_context(), error);
+
+        let mut s = heapless::String::<128>::new();
+
+        write!(s, "{}", with_context).unwrap();
+
+        #[cfg(not(feature = "error-subdevice-name"))]
+        assert_eq!(
+            s.as_str(),
+            "working counter expected 1, got 0 (FPRD(addr 0x1001, reg 0x0130), SubDevice 0x1001)"
+        );
+
+        #[cfg(feature = "error-subdevice-name")]
+        assert_eq!(
+            s.as_str(),
+            "working counter expected 1, got 0 (FPRD(addr 0x1001, reg 0x0130), SubDevice 0x1001 EL2889)"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "error-subdevice-name"))]
+    fn context_is_small() {
+        // Errors are returned from almost every function, so the context must not grow them much
+        assert!(core::mem::size_of::<ErrorContext>() <= 16);
+    }
+
+    #[test]
+    #[cfg(feature = "error-subdevice-name")]
+    fn truncate_name() {
+        assert_eq!(SubDeviceName::new("EL2889").as_str(), "EL2889");
+
+        // 30 bytes, then a 2 byte character crossing the capacity
+        let long = "abcdefghijklmnopqrstuvwxyz0123é";
+
+        assert_eq!(
+            SubDeviceName::new(long).as_str(),
+            "abcdefghijklmnopqrstuvwxyz0123"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "error-subdevice-name")]
+    fn name_without_address() {
+        let context = ErrorContext {
+            subdevice_name: Some(SubDeviceName::new("EK1100")),
+            ..ErrorContext::default()
+        };
+
+        let mut s = heapless::String::<32>::new();
+
+        write!(s, "{}", context).unwrap();
+
+        assert_eq!(s.as_str(), "SubDevice EK1100");
+    }
+
+    #[test]
+    fn no_context() {
+        assert_eq!(
+            Error::Borrow.with_context(ErrorContext::default()),
+            Error::Borrow
+        );
+        assert_eq!(Error::Borrow.context(), None);
+    }
+}
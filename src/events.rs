@@ -0,0 +1,364 @@
+//! Notifications of state, network and frame changes for alarm handling and monitoring.
+
+use crate::{
+    dl_status::DlStatus, error::Error, fmt, AlStatusCode, Command, MainDevice, RegisterAddress,
+    SubDeviceState,
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// Something that happened on the network or to a SubDevice, published to an [`EventSink`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A SubDevice was found in a new AL state.
+    ///
+    /// This is published when a SubDevice or group reaches a requested state, and by
+    /// [`SubDeviceGroup::check_subdevices`](crate::SubDeviceGroup::check_subdevices) for each
+    /// SubDevice that has left OP. A SubDevice that did not respond is reported in
+    /// [`SubDeviceState::None`].
+    StateChange {
+        /// The configured station address of the SubDevice.
+        configured_address: u16,
+        /// The state the SubDevice is in.
+        state: SubDeviceState,
+    },
+
+    /// A SubDevice set its AL error flag.
+    AlStatus {
+        /// The configured station address of the SubDevice.
+        configured_address: u16,
+        /// The state the SubDevice is in.
+        state: SubDeviceState,
+        /// The reason for the error.
+        status_code: AlStatusCode,
+    },
+
+    /// An acyclic command was answered with the wrong working counter.
+    WorkingCounter {
+        /// The command that was sent.
+        command: Command,
+        /// The expected working counter.
+        expected: u16,
+        /// The working counter returned by the network.
+        received: u16,
+    },
+
+    /// A process data exchange with a group was answered with the wrong working counter.
+    ProcessDataWorkingCounter {
+        /// The logical start address of the group's process data image.
+        logical_address: u32,
+        /// The expected working counter.
+        expected: u16,
+        /// The working counter returned by the network.
+        received: u16,
+    },
+
+    /// A SubDevice sent a CoE emergency message.
+    Emergency {
+        /// The configured station address of the SubDevice.
+        configured_address: u16,
+        /// The emergency error code.
+        error_code: u16,
+        /// The value of the SubDevice's error register, object `0x1001`.
+        error_register: u8,
+    },
+
+    /// The link on a SubDevice port went up or down.
+    Link {
+        /// The configured station address of the SubDevice.
+        configured_address: u16,
+        /// The physical port number, `0` to `3`.
+        port: u8,
+        /// Whether the link is now up.
+        up: bool,
+    },
+
+    /// No response to a frame was received in time and it will be sent again.
+    ///
+    /// See [`RetryBehaviour`](crate::RetryBehaviour).
+    FrameRetry {
+        /// The index of the frame in the Ethernet header.
+        frame_index: u8,
+        /// The number of retries left after this one.
+        retries_left: usize,
+    },
+
+    /// No response to a frame was received in time and there are no retries left.
+    FrameTimeout {
+        /// The index of the frame in the Ethernet header.
+        frame_index: u8,
+    },
+}
+
+/// A receiver of [`Event`]s, registered with [`MainDevice::with_event_sink`].
+///
+/// Events are published from inside EtherCrab's futures, including the ones that drive process
+/// data, so [`publish`](EventSink::publish) must return quickly and must not block. Use an
+/// [`EventQueue`] to hand events to another task.
+pub trait EventSink: Sync {
+    /// Handle an event.
+    fn publish(&self, event: Event);
+}
+
+/// A bounded, lock-free queue of [`Event`]s.
+///
+/// Events are dropped if the queue is full. The number of dropped events can be read with
+/// [`dropped`](EventQueue::dropped).
+///
+/// `N` must be a power of two, and at most 128.
+///
+/// # Examples
+///
+/// ```rust
+/// use ethercrab::{EventQueue, MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+///
+/// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+/// static EVENTS: EventQueue<64> = EventQueue::new();
+///
+/// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default())
+///     .with_event_sink(&EVENTS);
+///
+/// // In a monitoring task
+/// while let Some(event) = EVENTS.pop() {
+///     println!("{:?}", event);
+/// }
+/// ```
+pub struct EventQueue<const N: usize> {
+    queue: heapless::mpmc::MpMcQueue<Event, N>,
+    dropped: AtomicU32,
+}
+
+impl<const N: usize> EventQueue<N> {
+    /// Create a new, empty queue.
+    pub const fn new() -> Self {
+        Self {
+            queue: heapless::mpmc::MpMcQueue::new(),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Take the oldest event from the queue.
+    pub fn pop(&self) -> Option<Event> {
+        self.queue.dequeue()
+    }
+
+    /// The number of events dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for EventQueue<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EventQueue")
+            .field("dropped", &self.dropped())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const N: usize> EventSink for EventQueue<N> {
+    fn publish(&self, event: Event) {
+        if self.queue.enqueue(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The event sink registered with a [`MainDevice`], if any.
+#[derive(Copy, Clone)]
+pub(crate) struct Events<'sto>(Option<&'sto dyn EventSink>);
+
+impl<'sto> Events<'sto> {
+    pub const fn none() -> Self {
+        Self(None)
+    }
+
+    pub const fn new(sink: &'sto dyn EventSink) -> Self {
+        Self(Some(sink))
+    }
+
+    pub fn publish(&self, event: Event) {
+        if let Some(sink) = self.0 {
+            sink.publish(event);
+        }
+    }
+}
+
+impl<'sto> core::fmt::Debug for Events<'sto> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Events").field(&self.0.is_some()).finish()
+    }
+}
+
+/// Watch the ports of every SubDevice on the network for links going up or down.
+///
+/// Each call to [`poll`](LinkMonitor::poll) reads the DL status of every SubDevice and publishes an
+/// [`Event::Link`] for each port whose link changed since the last poll. It can be called
+/// periodically from a task separate to the process data loop.
+///
+/// SubDevices are identified by their configured station address, so SubDevices that appear or
+/// disappear between polls, e.g. a [`HotConnectSegment`](crate::HotConnectSegment), are compared
+/// correctly. The ports of a SubDevice seen for the first time do not produce any events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkMonitor<const MAX_SUBDEVICES: usize> {
+    /// Configured address and link state of ports 0 to 3 at the last poll.
+    links: heapless::Vec<(u16, [bool; 4]), MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> LinkMonitor<MAX_SUBDEVICES> {
+    /// Create a new monitor, using the current state of the network as a baseline.
+    pub async fn new(maindevice: &MainDevice<'_>) -> Result<Self, Error> {
+        Ok(Self {
+            links: Self::read(maindevice).await?,
+        })
+    }
+
+    /// Check every SubDevice for link changes, publishing an [`Event::Link`] for each one.
+    ///
+    /// Returns the number of changed links.
+    pub async fn poll(&mut self, maindevice: &MainDevice<'_>) -> Result<usize, Error> {
+        let links = Self::read(maindevice).await?;
+
+        let mut changes = 0;
+
+        for (configured_address, ports) in links.iter() {
+            let Some((_, previous)) = self
+                .links
+                .iter()
+                .find(|(address, _)| address == configured_address)
+            else {
+                continue;
+            };
+
+            for (port, (up, was_up)) in ports.iter().zip(previous).enumerate() {
+                if up != was_up {
+                    fmt::debug!(
+                        "SubDevice {:#06x} port {} link {}",
+                        configured_address,
+                        port,
+                        if *up { "up" } else { "down" }
+                    );
+
+                    maindevice.publish(Event::Link {
+                        configured_address: *configured_address,
+                        port: port as u8,
+                        up: *up,
+                    });
+
+                    changes += 1;
+                }
+            }
+        }
+
+        self.links = links;
+
+        Ok(changes)
+    }
+
+    async fn read(
+        maindevice: &MainDevice<'_>,
+    ) -> Result<heapless::Vec<(u16, [bool; 4]), MAX_SUBDEVICES>, Error> {
+        let num_subdevices = maindevice
+            .count_subdevices()
+            .await?
+            .min(MAX_SUBDEVICES as u16);
+
+        let mut links = heapless::Vec::<(u16, [bool; 4]), MAX_SUBDEVICES>::new();
+
+        maindevice
+            .read_all(
+                num_subdevices,
+                RegisterAddress::ConfiguredStationAddress,
+                2,
+                |pdu| {
+                    // Can't overflow as the count is limited above
+                    let _ = links.push((u16::unpack_from_slice(pdu)?, [false; 4]));
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        let mut ports = links.iter_mut().map(|(_, ports)| ports);
+
+        maindevice
+            .read_all(
+                num_subdevices,
+                RegisterAddress::DlStatus,
+                DlStatus::PACKED_LEN,
+                |pdu| {
+                    let status = DlStatus::unpack_from_slice(pdu)?;
+
+                    if let Some(ports) = ports.next() {
+                        *ports = [0, 1, 2, 3].map(|port| status.link(port));
+                    }
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        Ok(links)
+    }
+}
+
+impl<'sto> MainDevice<'sto> {
+    /// Publish [`Event`]s to `sink`.
+    ///
+    /// Events are published for SubDevice state changes, AL status codes, working counter
+    /// mismatches, CoE emergencies, frame retries and timeouts. Link changes are published by a
+    /// [`LinkMonitor`].
+    pub fn with_event_sink(mut self, sink: &'sto dyn EventSink) -> Self {
+        self.pdu_loop.set_events(Events::new(sink));
+
+        self
+    }
+
+    pub(crate) fn publish(&self, event: Event) {
+        self.pdu_loop.publish(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_order() {
+        let queue = EventQueue::<4>::new();
+
+        queue.publish(Event::FrameTimeout { frame_index: 1 });
+        queue.publish(Event::FrameTimeout { frame_index: 2 });
+
+        assert_eq!(queue.pop(), Some(Event::FrameTimeout { frame_index: 1 }));
+        assert_eq!(queue.pop(), Some(Event::FrameTimeout { frame_index: 2 }));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn queue_full() {
+        let queue = EventQueue::<2>::new();
+
+        for frame_index in 0..5 {
+            queue.publish(Event::FrameTimeout { frame_index });
+        }
+
+        assert_eq!(queue.dropped(), 3);
+        assert_eq!(queue.pop(), Some(Event::FrameTimeout { frame_index: 0 }));
+    }
+
+    #[test]
+    fn no_sink() {
+        // Must not panic
+        Events::none().publish(Event::FrameTimeout { frame_index: 0 });
+    }
+}
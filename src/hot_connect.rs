@@ -0,0 +1,470 @@
+//! Detection of optional network segments that are connected and disconnected at runtime.
+
+use crate::{
+    command::Command,
+    dl_status::DlStatus,
+    error::{Error, HotConnectError, Item},
+    fmt, MainDevice, RegisterAddress, BASE_SUBDEVICE_ADDRESS,
+};
+
+/// An optional network segment, e.g. the SubDevices on a tool changer, that may be plugged in and
+/// out while the rest of the network is running.
+///
+/// SubDevices in the segment are given consecutive configured station addresses starting at
+/// [`first_address`](HotConnectSegment::first_address). These must not overlap the addresses
+/// given to SubDevices found by [`MainDevice::init`], which start at `0x1000`, or the addresses of
+/// any other segment.
+///
+/// A segment is attached to its own [`SubDeviceGroup`](crate::SubDeviceGroup) with
+/// [`SubDeviceGroup::connect_segment`](crate::SubDeviceGroup::connect_segment). Use a
+/// [`HotConnectMonitor`] to detect when it is plugged in or out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HotConnectSegment {
+    /// The configured station address of the SubDevice the segment is plugged into.
+    pub parent: u16,
+
+    /// The physical port (0-3) on the parent SubDevice the segment is plugged into.
+    pub port: u8,
+
+    /// The configured station address of the first SubDevice in the segment.
+    pub first_address: u16,
+}
+
+impl HotConnectSegment {
+    /// Create a new segment plugged into `port` of the SubDevice with the configured address
+    /// `parent`.
+    pub const fn new(parent: u16, port: u8, first_address: u16) -> Self {
+        Self {
+            parent,
+            port,
+            first_address,
+        }
+    }
+
+    /// The configured station addresses of a segment with up to `len` SubDevices.
+    pub(crate) fn addresses(&self, len: usize) -> core::ops::Range<u16> {
+        self.first_address..self.first_address.saturating_add(len as u16)
+    }
+
+    /// Check the segment port is one a SubDevice can have.
+    ///
+    /// This doesn't know how many SubDevices the segment holds, so doesn't check its addresses.
+    pub(crate) fn validate_port(&self) -> Result<(), Error> {
+        if self.port > 3 {
+            return Err(HotConnectError::InvalidPort(self.port).into());
+        }
+
+        Ok(())
+    }
+
+    /// Check the segment port, and that the addresses of a segment with up to `len` SubDevices
+    /// don't overlap those of the SubDevices discovered during init.
+    pub(crate) fn validate(&self, maindevice: &MainDevice<'_>, len: usize) -> Result<(), Error> {
+        self.validate_port()?;
+
+        self.check_addresses(maindevice.num_subdevices() as u16, len)
+    }
+
+    fn check_addresses(&self, num_fixed: u16, len: usize) -> Result<(), Error> {
+        let fixed = BASE_SUBDEVICE_ADDRESS..BASE_SUBDEVICE_ADDRESS + num_fixed;
+        let segment = self.addresses(len);
+
+        if segment.start < fixed.end && fixed.start < segment.end {
+            fmt::error!(
+                "Segment addresses {:#06x}..{:#06x} overlap SubDevice addresses {:#06x}..{:#06x}",
+                segment.start,
+                segment.end,
+                fixed.start,
+                fixed.end
+            );
+
+            return Err(HotConnectError::AddressInUse.into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether the parent SubDevice reports a link on the segment port.
+    pub(crate) async fn link(&self, maindevice: &MainDevice<'_>) -> Result<bool, Error> {
+        Command::fprd(self.parent, RegisterAddress::DlStatus.into())
+            .receive::<DlStatus>(maindevice)
+            .await
+            .map(|status| status.link(self.port))
+    }
+}
+
+/// Find the SubDevices of a [`HotConnectSegment`] from the configured station address at each
+/// position on the network.
+///
+/// The segment is the run of SubDevices directly after the parent that have no configured station
+/// address, or that still have an address from a previous connection of the segment.
+#[derive(Debug)]
+pub(crate) struct SegmentScan<const N: usize> {
+    parent: u16,
+    addresses: core::ops::Range<u16>,
+    state: ScanState,
+    /// Positions of the SubDevices in the segment, and whether each one kept its address from a
+    /// previous connection.
+    positions: heapless::Vec<(u16, bool), N>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ScanState {
+    /// Looking for the parent SubDevice.
+    Parent,
+    /// Collecting the SubDevices after the parent.
+    Segment,
+    /// Reached the first SubDevice after the segment.
+    Done,
+}
+
+impl<const N: usize> SegmentScan<N> {
+    pub fn new(segment: &HotConnectSegment) -> Self {
+        Self {
+            parent: segment.parent,
+            addresses: segment.addresses(N),
+            state: ScanState::Parent,
+            positions: heapless::Vec::new(),
+        }
+    }
+
+    /// Add the configured station address read from the next position on the network.
+    pub fn push(&mut self, position: u16, address: u16) -> Result<(), Error> {
+        match self.state {
+            ScanState::Parent if address == self.parent => self.state = ScanState::Segment,
+            ScanState::Segment if address == 0 || self.addresses.contains(&address) => self
+                .positions
+                .push((position, address != 0))
+                .map_err(|_| Error::Capacity(Item::SubDevice))?,
+            ScanState::Segment => self.state = ScanState::Done,
+            ScanState::Parent | ScanState::Done => (),
+        }
+
+        Ok(())
+    }
+
+    /// The positions of the SubDevices in the segment, and whether each one kept its address from
+    /// a previous connection.
+    pub fn finish(self) -> Result<heapless::Vec<(u16, bool), N>, Error> {
+        if self.positions.is_empty() {
+            return Err(HotConnectError::NoSubDevices.into());
+        }
+
+        Ok(self.positions)
+    }
+}
+
+/// A change in a [`HotConnectSegment`] detected by [`HotConnectMonitor::poll`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum HotConnectEvent {
+    /// The segment link came up and new SubDevices responded on the network.
+    Connected {
+        /// The number of SubDevices that appeared.
+        subdevices: u16,
+    },
+
+    /// The segment link went down or SubDevices dropped off the network.
+    Disconnected {
+        /// The number of SubDevices that disappeared.
+        subdevices: u16,
+    },
+}
+
+/// Watch a [`HotConnectSegment`] for being plugged in or out.
+///
+/// Each call to [`poll`](HotConnectMonitor::poll) counts the SubDevices on the network with a `BRD`
+/// and reads the DL status of the segment's parent SubDevice to check the link on the segment
+/// port. It can be called periodically from a task separate to the process data loop.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use ethercrab::{
+/// #     error::Error, std::ethercat_now, HotConnectEvent, HotConnectMonitor, HotConnectSegment,
+/// #     MainDevice, MainDeviceConfig, PduStorage, SubDeviceGroup, Timeouts,
+/// # };
+/// # use std::time::Duration;
+/// # static PDU_STORAGE: PduStorage<8, 32> = PduStorage::new();
+/// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+/// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+/// #[derive(Default)]
+/// struct Groups {
+///     fixed: SubDeviceGroup<8, 64>,
+///     tool: SubDeviceGroup<4, 32>,
+/// }
+///
+/// // The tool plugs into port 1 of the SubDevice at `0x1003`
+/// let segment = HotConnectSegment::new(0x1003, 1, 0x2000);
+///
+/// # async {
+/// let groups = maindevice
+///     .init::<16, _>(ethercat_now, |groups: &Groups, subdevice| {
+///         if subdevice.configured_address() > 0x1003 {
+///             Ok(&groups.tool)
+///         } else {
+///             Ok(&groups.fixed)
+///         }
+///     })
+///     .await?;
+///
+/// let mut tool = groups.tool;
+/// let mut monitor = HotConnectMonitor::new(&maindevice, segment).await?;
+///
+/// loop {
+///     match monitor.poll(&maindevice).await? {
+///         Some(HotConnectEvent::Connected { .. }) => {
+///             tool.connect_segment(&maindevice, &segment).await?;
+///
+///             let op = tool.into_op(&maindevice).await?;
+///
+///             // Exchange process data with `op` until the segment is disconnected, then
+///             tool = op.disconnect_segment();
+///         }
+///         Some(HotConnectEvent::Disconnected { .. }) | None => (),
+///     }
+///
+///     tokio::time::sleep(Duration::from_millis(100)).await;
+/// }
+/// # Ok::<(), Error>(())
+/// # };
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HotConnectMonitor {
+    segment: HotConnectSegment,
+    /// The number of SubDevices on the network at the last poll.
+    subdevices: u16,
+    connected: bool,
+}
+
+impl HotConnectMonitor {
+    /// Create a new monitor, using the current state of the network as a baseline.
+    ///
+    /// If the segment is already connected, for example because it was present when
+    /// [`MainDevice::init`] ran, the first event will be a
+    /// [`Disconnected`](HotConnectEvent::Disconnected).
+    ///
+    /// Only the segment port is checked here, returning [`HotConnectError::InvalidPort`] if it is
+    /// out of range. The monitor doesn't know how many SubDevices the segment holds, so its
+    /// addresses are checked against the rest of the network by
+    /// [`SubDeviceGroup::connect_segment`](crate::SubDeviceGroup::connect_segment) instead.
+    pub async fn new(
+        maindevice: &MainDevice<'_>,
+        segment: HotConnectSegment,
+    ) -> Result<Self, Error> {
+        segment.validate_port()?;
+
+        Ok(Self {
+            segment,
+            subdevices: maindevice.count_subdevices().await?,
+            connected: segment.link(maindevice).await?,
+        })
+    }
+
+    /// Whether the segment was connected at the last poll.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Check the network for changes to the segment.
+    ///
+    /// A [`Connected`](HotConnectEvent::Connected) event is returned once the segment link is up
+    /// and more SubDevices respond than before, and a
+    /// [`Disconnected`](HotConnectEvent::Disconnected) event when the link goes down or fewer
+    /// SubDevices respond.
+    pub async fn poll(
+        &mut self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<Option<HotConnectEvent>, Error> {
+        let subdevices = maindevice.count_subdevices().await?;
+        let link = self.segment.link(maindevice).await?;
+
+        Ok(self.update(subdevices, link))
+    }
+
+    fn update(&mut self, subdevices: u16, link: bool) -> Option<HotConnectEvent> {
+        let event = match self.connected {
+            false if link && subdevices > self.subdevices => {
+                fmt::info!(
+                    "Segment on SubDevice {:#06x} port {} connected, {} new SubDevice(s)",
+                    self.segment.parent,
+                    self.segment.port,
+                    subdevices - self.subdevices
+                );
+
+                Some(HotConnectEvent::Connected {
+                    subdevices: subdevices - self.subdevices,
+                })
+            }
+            true if !link || subdevices < self.subdevices => {
+                fmt::info!(
+                    "Segment on SubDevice {:#06x} port {} disconnected",
+                    self.segment.parent,
+                    self.segment.port,
+                );
+
+                Some(HotConnectEvent::Disconnected {
+                    subdevices: self.subdevices.saturating_sub(subdevices),
+                })
+            }
+            _ => None,
+        };
+
+        if let Some(event) = event {
+            self.connected = matches!(event, HotConnectEvent::Connected { .. });
+        }
+
+        self.subdevices = subdevices;
+
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_disconnect() {
+        let mut monitor = HotConnectMonitor {
+            segment: HotConnectSegment::new(0x1001, 1, 0x2000),
+            subdevices: 2,
+            connected: false,
+        };
+
+        // Link is up but the SubDevices behind it haven't responded yet
+        assert_eq!(monitor.update(2, true), None);
+        assert_eq!(
+            monitor.update(5, true),
+            Some(HotConnectEvent::Connected { subdevices: 3 })
+        );
+        assert!(monitor.is_connected());
+        assert_eq!(monitor.update(5, true), None);
+        assert_eq!(
+            monitor.update(2, false),
+            Some(HotConnectEvent::Disconnected { subdevices: 3 })
+        );
+        assert!(!monitor.is_connected());
+    }
+
+    #[test]
+    fn other_subdevices_change() {
+        let mut monitor = HotConnectMonitor {
+            segment: HotConnectSegment::new(0x1001, 1, 0x2000),
+            subdevices: 2,
+            connected: false,
+        };
+
+        // SubDevices appearing elsewhere on the network don't connect the segment
+        assert_eq!(monitor.update(3, false), None);
+        assert_eq!(
+            monitor.update(4, true),
+            Some(HotConnectEvent::Connected { subdevices: 1 })
+        );
+    }
+
+    fn scan(
+        segment: HotConnectSegment,
+        addresses: &[u16],
+    ) -> Result<heapless::Vec<(u16, bool), 4>, Error> {
+        let mut scan = SegmentScan::<4>::new(&segment);
+
+        for (position, address) in addresses.iter().enumerate() {
+            scan.push(position as u16, *address)?;
+        }
+
+        scan.finish()
+    }
+
+    #[test]
+    fn segment_after_parent() {
+        let segment = HotConnectSegment::new(0x1001, 1, 0x2000);
+
+        assert_eq!(
+            scan(segment, &[0x1000, 0x1001, 0, 0, 0x1002]).unwrap(),
+            [(2, false), (3, false)]
+        );
+
+        // Reconnected without being power cycled
+        assert_eq!(
+            scan(segment, &[0x1000, 0x1001, 0x2000, 0x2001, 0x1002]).unwrap(),
+            [(2, true), (3, true)]
+        );
+    }
+
+    #[test]
+    fn segment_ignores_other_unconfigured() {
+        let segment = HotConnectSegment::new(0x1001, 1, 0x2000);
+
+        // Unconfigured SubDevices before the parent belong to another segment
+        assert_eq!(
+            scan(segment, &[0, 0, 0x1000, 0x1001, 0, 0x1002, 0]).unwrap(),
+            [(4, false)]
+        );
+    }
+
+    #[test]
+    fn segment_not_directly_after_parent() {
+        let segment = HotConnectSegment::new(0x1001, 1, 0x2000);
+
+        assert_eq!(
+            scan(segment, &[0x1000, 0x1001, 0x1002, 0, 0]),
+            Err(HotConnectError::NoSubDevices.into())
+        );
+    }
+
+    #[test]
+    fn segment_parent_missing() {
+        let segment = HotConnectSegment::new(0x1005, 1, 0x2000);
+
+        assert_eq!(
+            scan(segment, &[0x1000, 0x1001, 0, 0]),
+            Err(HotConnectError::NoSubDevices.into())
+        );
+    }
+
+    #[test]
+    fn segment_too_long() {
+        let segment = HotConnectSegment::new(0x1000, 1, 0x2000);
+
+        assert_eq!(
+            scan(segment, &[0x1000, 0, 0, 0, 0, 0]),
+            Err(Error::Capacity(Item::SubDevice))
+        );
+    }
+
+    #[test]
+    fn segment_addresses() {
+        let segment = HotConnectSegment::new(0x1001, 1, 0x1004);
+
+        // Init found 0x1000..0x1004
+        assert_eq!(segment.check_addresses(4, 4), Ok(()));
+        assert_eq!(
+            segment.check_addresses(5, 4),
+            Err(Error::HotConnect(HotConnectError::AddressInUse))
+        );
+        assert_eq!(
+            HotConnectSegment::new(0x1001, 1, 0x0ffe).check_addresses(4, 4),
+            Err(Error::HotConnect(HotConnectError::AddressInUse))
+        );
+        assert_eq!(
+            HotConnectSegment::new(0x1001, 1, 0x0ffc).check_addresses(4, 4),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn segment_port() {
+        assert_eq!(
+            HotConnectSegment::new(0x1001, 3, 0x2000).validate_port(),
+            Ok(())
+        );
+        assert_eq!(
+            HotConnectSegment::new(0x1001, 4, 0x2000).validate_port(),
+            Err(Error::HotConnect(HotConnectError::InvalidPort(4)))
+        );
+    }
+}
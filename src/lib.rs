@@ -16,6 +16,10 @@
 //! - `log` - enable logging with the [`log`](https://docs.rs/log) crate. This is enabled by default
 //!   when the `std` feature is enabled.
 //! - `serde` - enable `serde` impls for some public items.
+//! - `pdo-mapping` - retain the PDO mapping of each SubDevice discovered during init so it can be
+//!   read back and validated. This uses about 0.5 KiB per SubDevice.
+//! - `error-subdevice-name` - store the name of the SubDevice in the context of errors it caused,
+//!   so it is shown in logs. This roughly doubles the size of [`error::Error`].
 //!
 //! For `no_std` targets, it is recommended to add this crate with
 //!
@@ -169,19 +173,25 @@ mod base_data_types;
 mod coe;
 mod command;
 mod dc;
+mod diagnostics;
 mod dl_status;
 pub mod ds402;
 mod eeprom;
 pub mod error;
 mod ethernet;
+mod events;
 mod fmmu;
 mod generate;
+mod hot_connect;
 mod mailbox;
 mod maindevice;
 mod maindevice_config;
 mod pdi;
 mod pdu_loop;
+mod piggyback;
+mod redundancy;
 mod register;
+mod scan;
 mod subdevice;
 pub mod subdevice_group;
 mod subdevice_state;
@@ -198,18 +208,29 @@ pub mod std;
 pub use al_status_code::AlStatusCode;
 pub use coe::SubIndex;
 pub use command::{Command, Reads, WrappedRead, WrappedWrite, Writes};
+pub use diagnostics::{link_errors, ErrorCounters, LinkErrors, PortErrorCounters};
 pub use ethercrab_wire::{
     EtherCrabWireRead, EtherCrabWireReadSized, EtherCrabWireReadWrite, EtherCrabWireSized,
-    EtherCrabWireWrite, EtherCrabWireWriteSized,
+    EtherCrabWireView, EtherCrabWireWrite, EtherCrabWireWriteSized,
 };
 use ethernet::EthernetAddress;
+pub use events::{Event, EventQueue, EventSink, LinkMonitor};
+pub use hot_connect::{HotConnectEvent, HotConnectMonitor, HotConnectSegment};
 pub use maindevice::MainDevice;
 pub use maindevice_config::{MainDeviceConfig, RetryBehaviour};
-pub use pdu_loop::{PduLoop, PduRx, PduStorage, PduTx, ReceiveAction, SendableFrame};
+pub use pdu_loop::{
+    FramePriority, PduLoop, PduRx, PduStorage, PduTx, ReceiveAction, SendableFrame,
+};
+pub use redundancy::RingBreak;
 pub use register::{DcSupport, RegisterAddress};
-pub use subdevice::{DcSync, SubDevice, SubDeviceIdentity, SubDevicePdi, SubDeviceRef};
+pub use scan::{EscInfo, NetworkScan, ScannedSubDevice};
+pub use subdevice::{
+    DcSync, DcSyncTiming, NetworkTopology, PdoMapping, PdoMappingEntry, SubDevice,
+    SubDeviceIdentity, SubDevicePdi, SubDevicePort, SubDeviceRef, SubDeviceTopology, Topology,
+    WatchdogConfig, WatchdogStatus,
+};
 pub use subdevice_group::{GroupId, GroupSubDeviceIterator, SubDeviceGroup, SubDeviceGroupHandle};
-pub use subdevice_state::SubDeviceState;
+pub use subdevice_state::{StateRequest, SubDeviceState};
 pub use timer_factory::Timeouts;
 
 const LEN_MASK: u16 = 0b0000_0111_1111_1111;
@@ -3,20 +3,22 @@ use crate::{
     al_status_code::AlStatusCode,
     command::Command,
     dc,
-    error::{Error, Item},
+    error::{Error, Item, PduError},
     fmt,
     pdi::PdiOffset,
-    pdu_loop::{PduLoop, ReceivedPdu},
+    pdu_loop::{CreatedFrame, PduLoop, ReceivedPdu},
+    piggyback::{PiggybackQueue, PiggybackResponse},
     register::RegisterAddress,
     subdevice::SubDevice,
-    subdevice_group::{self, SubDeviceGroupHandle},
+    subdevice_group::{self, CyclicGroup, SubDeviceGroupHandle, TxRxResponse},
     subdevice_state::SubDeviceState,
     timer_factory::IntoTimeout,
-    MainDeviceConfig, SubDeviceGroup, Timeouts, BASE_SUBDEVICE_ADDRESS,
+    MainDeviceConfig, NetworkTopology, SubDeviceGroup, SubDeviceTopology, Timeouts,
+    BASE_SUBDEVICE_ADDRESS,
 };
 use core::{
     ops::Range,
-    sync::atomic::{AtomicU16, Ordering},
+    sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering},
 };
 use ethercrab_wire::EtherCrabWireWrite;
 use heapless::FnvIndexMap;
@@ -41,6 +43,13 @@ pub struct MainDevice<'sto> {
     dc_reference_configured_address: AtomicU16,
     pub(crate) timeouts: Timeouts,
     pub(crate) config: MainDeviceConfig,
+    /// Acyclic PDUs waiting to be sent in the next cyclic process data frame.
+    pub(crate) piggyback: PiggybackQueue,
+    /// Send mailbox status polls in cyclic process data frames.
+    piggyback_mailbox_polling: AtomicBool,
+    /// The first logical address not used by a group, used to place groups that are populated
+    /// after init.
+    pdi_end: AtomicU32,
 }
 
 unsafe impl<'sto> Sync for MainDevice<'sto> {}
@@ -58,9 +67,30 @@ impl<'sto> MainDevice<'sto> {
             dc_reference_configured_address: AtomicU16::new(0),
             timeouts,
             config,
+            piggyback: PiggybackQueue::new(),
+            piggyback_mailbox_polling: AtomicBool::new(false),
+            pdi_end: AtomicU32::new(0),
         }
     }
 
+    /// Send the SyncManager status polls used by mailbox (e.g. CoE) transfers in the spare space of
+    /// cyclic process data frames instead of in their own frames.
+    ///
+    /// This should only be enabled while process data is being exchanged with
+    /// [`SubDeviceGroup::tx_rx`] or [`MainDevice::tx_rx_groups`] at a cycle time shorter than
+    /// [`Timeouts::pdu`]. Polls that are not picked up by a cyclic frame within [`Timeouts::pdu`] are
+    /// sent in their own frame, which slows mailbox transfers down considerably.
+    ///
+    /// Disabled by default.
+    pub fn set_piggyback_mailbox_polling(&self, enabled: bool) {
+        self.piggyback_mailbox_polling
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn piggyback_mailbox_polling(&self) -> bool {
+        self.piggyback_mailbox_polling.load(Ordering::Relaxed)
+    }
+
     /// Write zeroes to every SubDevice's memory in chunks.
     async fn blank_memory(&self, start: impl Into<u16>, len: u16) -> Result<(), Error> {
         let step = self.pdu_loop.max_frame_data();
@@ -331,6 +361,8 @@ impl<'sto> MainDevice<'sto> {
             }
 
             fmt::debug!("Total PDI {} bytes", offset.start_address);
+
+            self.pdi_end.store(offset.start_address, Ordering::Relaxed);
         }
 
         // Check that all SubDevices reached PRE-OP
@@ -428,8 +460,15 @@ impl<'sto> MainDevice<'sto> {
             .await
     }
 
+    /// Reserve `len` bytes of logical address space after all other groups.
+    pub(crate) fn reserve_pdi(&self, len: u16) -> PdiOffset {
+        PdiOffset {
+            start_address: self.pdi_end.fetch_add(u32::from(len), Ordering::Relaxed),
+        }
+    }
+
     /// Count the number of SubDevices on the network.
-    async fn count_subdevices(&self) -> Result<u16, Error> {
+    pub(crate) async fn count_subdevices(&self) -> Result<u16, Error> {
         Command::brd(RegisterAddress::Type.into())
             .receive_wkc::<u8>(self)
             .await
@@ -443,6 +482,82 @@ impl<'sto> MainDevice<'sto> {
         usize::from(self.num_subdevices.load(Ordering::Relaxed))
     }
 
+    /// Build a report of the network topology computed during [`init`](MainDevice::init) from the
+    /// SubDevices of one or more groups.
+    ///
+    /// SubDevices are sorted into network order, so groups can be given in any order. The report
+    /// only contains the SubDevices passed in, so pass the [`topology`](SubDeviceGroup::topology)
+    /// of every group to describe the whole network.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Capacity`] if more than `MAX_SUBDEVICES` SubDevices are given.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     error::Error, std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage,
+    ///     SubDeviceGroup, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// #[derive(Default)]
+    /// struct Groups {
+    ///     slow_outputs: SubDeviceGroup<2, 2>,
+    ///     fast_outputs: SubDeviceGroup<2, 2>,
+    /// }
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let groups = maindevice
+    ///     .init::<4, _>(ethercat_now, |groups: &Groups, subdevice| match subdevice.name() {
+    ///         "EL2004" => Ok(&groups.fast_outputs),
+    ///         _ => Ok(&groups.slow_outputs),
+    ///     })
+    ///     .await?;
+    ///
+    /// let topology = maindevice.topology::<4>(
+    ///     groups
+    ///         .slow_outputs
+    ///         .topology()
+    ///         .chain(groups.fast_outputs.topology()),
+    /// )?;
+    ///
+    /// for subdevice in topology.junctions() {
+    ///     println!("Junction at SubDevice {:#06x}", subdevice.configured_address);
+    /// }
+    /// # Ok::<(), Error>(())
+    /// # };
+    /// ```
+    pub fn topology<const MAX_SUBDEVICES: usize>(
+        &self,
+        subdevices: impl IntoIterator<Item = SubDeviceTopology>,
+    ) -> Result<NetworkTopology<MAX_SUBDEVICES>, Error> {
+        let mut report = NetworkTopology {
+            subdevices: heapless::Vec::new(),
+            num_subdevices: self.num_subdevices.load(Ordering::Relaxed),
+            dc_reference: self.dc_ref_address(),
+        };
+
+        for subdevice in subdevices {
+            report
+                .subdevices
+                .push(subdevice)
+                .map_err(|_| Error::Capacity(Item::SubDevice))?;
+        }
+
+        report
+            .subdevices
+            .sort_unstable_by_key(|subdevice| subdevice.index);
+
+        Ok(report)
+    }
+
     /// Get the configured address of the designated DC reference subdevice.
     pub(crate) fn dc_ref_address(&self) -> Option<u16> {
         let addr = self.dc_reference_configured_address.load(Ordering::Relaxed);
@@ -504,8 +619,81 @@ impl<'sto> MainDevice<'sto> {
         .await
     }
 
-    pub(crate) const fn max_frame_data(&self) -> usize {
-        self.pdu_loop.max_frame_data()
+    /// Drive the inputs and outputs of multiple groups at once, packing the process data of all
+    /// groups into as few frames as possible.
+    ///
+    /// This is equivalent to calling [`SubDeviceGroup::tx_rx`] on each group, but uses fewer
+    /// frames per cycle when the groups are small. Each group keeps its own PDI, state and
+    /// [`CyclicCommandStrategy`](crate::subdevice_group::CyclicCommandStrategy). The returned
+    /// responses are in the same order as `groups`.
+    ///
+    /// Groups using
+    /// [`CyclicCommandStrategy::LrdLwrSeparateFrames`](crate::subdevice_group::CyclicCommandStrategy::LrdLwrSeparateFrames)
+    /// only keep their own `LRD` and `LWR` apart, so either may share a frame with PDUs from the
+    /// other groups.
+    ///
+    /// # Errors
+    ///
+    /// This method will return with an error if a PDU could not be sent over the network, or the
+    /// response times out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::Op};
+    /// # async fn case(
+    /// #     maindevice: &MainDevice<'_>,
+    /// #     fast: &SubDeviceGroup<4, 32, Op>,
+    /// #     slow: &SubDeviceGroup<8, 64, Op>,
+    /// # ) -> Result<(), Error> {
+    /// let [fast_response, slow_response] = maindevice.tx_rx_groups([fast, slow]).await?;
+    ///
+    /// assert!(fast_response.working_counter_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tx_rx_groups<const N: usize>(
+        &'sto self,
+        groups: [&dyn CyclicGroup; N],
+    ) -> Result<[TxRxResponse; N], Error> {
+        subdevice_group::exchange::exchange(self, groups, None)
+            .await
+            .map(|(responses, _time)| responses)
+    }
+
+    /// Drive the inputs and outputs of multiple groups at once and synchronise EtherCAT system
+    /// time with `FRMW`.
+    ///
+    /// This behaves like [`tx_rx_groups`](MainDevice::tx_rx_groups), with a single DC system
+    /// time `FRMW` sent in the first frame. The current EtherCAT system time in nanoseconds is
+    /// returned if a DC reference SubDevice was found during init, otherwise no `FRMW` is sent and
+    /// `None` is returned.
+    pub async fn tx_rx_groups_sync_system_time<const N: usize>(
+        &'sto self,
+        groups: [&dyn CyclicGroup; N],
+    ) -> Result<([TxRxResponse; N], Option<u64>), Error> {
+        subdevice_group::exchange::exchange(self, groups, self.dc_ref_address()).await
+    }
+
+    /// Allocate a frame for acyclic traffic.
+    ///
+    /// If frames are reserved for cyclic process data, this waits for up to [`Timeouts::pdu`] for
+    /// an unreserved frame to become free.
+    pub(crate) async fn alloc_frame(&self) -> Result<CreatedFrame<'sto>, Error> {
+        if !self.pdu_loop.has_reserved_frames() {
+            return self.pdu_loop.alloc_frame();
+        }
+
+        async {
+            loop {
+                match self.pdu_loop.alloc_frame() {
+                    Err(Error::Pdu(PduError::SwapState)) => self.timeouts.loop_tick().await,
+                    res => break res,
+                }
+            }
+        }
+        .timeout(self.timeouts.pdu)
+        .await
     }
 
     /// Send a single PDU in a frame.
@@ -515,7 +703,7 @@ impl<'sto> MainDevice<'sto> {
         data: impl EtherCrabWireWrite,
         len_override: Option<u16>,
     ) -> Result<ReceivedPdu<'sto>, Error> {
-        let mut frame = self.pdu_loop.alloc_frame()?;
+        let mut frame = self.alloc_frame().await?;
 
         let handle = frame.push_pdu(command, data, len_override)?;
 
@@ -529,6 +717,114 @@ impl<'sto> MainDevice<'sto> {
 
         frame.await?.first_pdu(handle)
     }
+
+    /// Send a PDU in the spare space of the next cyclic process data frame.
+    ///
+    /// Returns `Ok(None)` if the PDU could not be queued or was not picked up by a cyclic frame
+    /// within [`Timeouts::pdu`], in which case the caller should send it in its own frame.
+    pub(crate) async fn piggyback_pdu(
+        &self,
+        command: Command,
+        data: &[u8],
+        len_override: Option<u16>,
+    ) -> Result<Option<PiggybackResponse>, Error> {
+        let Some(mut ticket) = self.piggyback.enqueue(command, data, len_override) else {
+            return Ok(None);
+        };
+
+        match async { Ok(ticket.response().await) }
+            .timeout(self.timeouts.pdu)
+            .await
+        {
+            Err(Error::Timeout) => (),
+            res => return res.map(Some),
+        }
+
+        if ticket.withdraw() {
+            fmt::trace!(
+                "No cyclic frame picked up {:?}, sending separately",
+                command
+            );
+
+            return Ok(None);
+        }
+
+        // Already sent in a cyclic frame, so wait for the response
+        async { Ok(ticket.response().await) }
+            .timeout(self.timeouts.pdu)
+            .await
+            .map(Some)
+    }
+
+    /// Send one PDU per item in `commands`, packing as many PDUs into each frame as will fit.
+    ///
+    /// `len` is the number of bytes to read or write with each PDU. Responses are passed to
+    /// `on_response` in order, along with the index of the command that produced them.
+    pub(crate) async fn batch_pdus<F>(
+        &'sto self,
+        commands: impl IntoIterator<Item = Command>,
+        len: u16,
+        mut on_response: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(usize, ReceivedPdu<'_>) -> Result<(), Error>,
+    {
+        let mut commands = commands.into_iter().peekable();
+
+        let mut response_idx = 0;
+
+        loop {
+            let mut frame = self.alloc_frame().await?;
+
+            let mut num_in_this_frame = 0;
+
+            while let Some(command) = commands.peek() {
+                match frame.push_pdu(*command, (), Some(len)) {
+                    Ok(_) => {
+                        let _ = commands.next();
+                    }
+                    // Frame is full, we'll do more next time round
+                    Err(PduError::TooLong) => break,
+                    Err(e) => return Err(e.into()),
+                }
+
+                num_in_this_frame += 1;
+
+                // A PDU with a small payload, e.g. a 2 byte status check, is 14 bytes, meaning we
+                // can fit at most just over 100 per normal EtherCAT frame. This leaves spare PDU
+                // indices available for other purposes, however if the user is using jumbo frames
+                // or something, we should always leave some indices free for e.g. other threads.
+                if num_in_this_frame > 128 {
+                    break;
+                }
+            }
+
+            if num_in_this_frame == 0 {
+                // A command that doesn't fit in an empty frame will never be sent
+                if commands.peek().is_some() {
+                    fmt::error!("PDU of {} bytes does not fit in an empty frame", len);
+
+                    break Err(PduError::TooLong.into());
+                }
+
+                break Ok(());
+            }
+
+            let frame = frame.mark_sendable(
+                &self.pdu_loop,
+                self.timeouts.pdu,
+                self.config.retry_behaviour.retry_count(),
+            );
+
+            self.pdu_loop.wake_sender();
+
+            for pdu in frame.await?.into_iter() {
+                on_response(response_idx, pdu?)?;
+
+                response_idx += 1;
+            }
+        }
+    }
 }
 
 fn blank_mem_iter(
@@ -557,6 +853,7 @@ fn blank_mem_iter(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::PduStorage;
 
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -590,4 +887,33 @@ mod tests {
             Ok(())
         });
     }
+
+    #[test]
+    fn batch_pdu_too_long() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        static STORAGE: PduStorage<1, 128> = PduStorage::<1, 128>::new();
+        let (_tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        let maindevice =
+            MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+
+        let mut responses = 0;
+
+        let res = cassette::block_on(maindevice.batch_pdus(
+            [
+                Command::fprd(0x1000, 0x0130).into(),
+                Command::fprd(0x1001, 0x0130).into(),
+            ],
+            256,
+            |_, _| {
+                responses += 1;
+
+                Ok(())
+            },
+        ));
+
+        assert_eq!(res, Err(Error::Pdu(PduError::TooLong)));
+        assert_eq!(responses, 0);
+    }
 }
@@ -54,6 +54,18 @@ impl<'sto> CreatedFrame<'sto> {
         })
     }
 
+    /// Mark whether this frame holds cyclic process data.
+    pub(in crate::pdu_loop) fn set_cyclic(&mut self, cyclic: bool) {
+        self.inner.set_cyclic(cyclic);
+    }
+
+    /// Return an unused frame to the storage so it can be allocated again.
+    ///
+    /// Must only be called before any PDUs are pushed.
+    pub(in crate::pdu_loop) fn release(self) {
+        self.inner.set_state(FrameState::None);
+    }
+
     /// The frame has been initialised, filled with a data payload (if required), and is now ready
     /// to be sent.
     ///
@@ -340,7 +352,7 @@ mod tests {
     use core::{
         cell::UnsafeCell,
         ptr::NonNull,
-        sync::atomic::{AtomicU16, AtomicU8},
+        sync::atomic::{AtomicBool, AtomicU16, AtomicU8},
     };
 
     #[test]
@@ -360,6 +372,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         }]);
 
         let mut created = CreatedFrame::claim_created(
@@ -399,6 +412,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         }]);
 
         let mut created = CreatedFrame::claim_created(
@@ -429,6 +443,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         }]);
 
         let mut created = CreatedFrame::claim_created(
@@ -481,6 +496,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         }]);
 
         let mut created = CreatedFrame::claim_created(
@@ -540,6 +556,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         }]);
 
         let mut created = CreatedFrame::claim_created(
@@ -61,7 +61,7 @@ impl<'sto> FrameBox<'sto> {
         unsafe {
             addr_of_mut!((*self.frame.as_ptr()).waker).write(AtomicWaker::new());
 
-            (&*addr_of_mut!((*self.frame.as_ptr()).first_pdu))
+            (*addr_of_mut!((*self.frame.as_ptr()).first_pdu))
                 .store(FIRST_PDU_EMPTY, Ordering::Relaxed);
 
             addr_of_mut!((*self.frame.as_ptr()).pdu_payload_len).write(0);
@@ -170,6 +170,10 @@ impl<'sto> FrameBox<'sto> {
         unsafe { FrameElement::swap_state(self.frame, from, to) }.map(|_| ())
     }
 
+    pub fn set_cyclic(&self, cyclic: bool) {
+        unsafe { FrameElement::<0>::set_cyclic(self.frame, cyclic) };
+    }
+
     pub fn clear_first_pdu(&self) {
         unsafe {
             FrameElement::<0>::clear_first_pdu(self.frame);
@@ -10,7 +10,7 @@ use crate::{
 use atomic_waker::AtomicWaker;
 use core::{
     ptr::{addr_of, addr_of_mut, NonNull},
-    sync::atomic::{AtomicU16, Ordering},
+    sync::atomic::{AtomicBool, AtomicU16, Ordering},
 };
 use frame_box::FrameBox;
 
@@ -91,6 +91,9 @@ pub struct FrameElement<const N: usize> {
     /// whether the PDU has been set or not.
     first_pdu: AtomicU16,
 
+    /// Whether this frame holds cyclic process data, used to prioritise it over other frames.
+    cyclic: AtomicBool,
+
     // MUST be the last element otherwise pointer arithmetic doesn't work for
     // `NonNull<FrameElement<0>>`.
     ethernet_frame: [u8; N],
@@ -104,6 +107,7 @@ impl<const N: usize> Default for FrameElement<N> {
             frame_index: 0,
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
             waker: AtomicWaker::default(),
         }
     }
@@ -187,6 +191,24 @@ impl<const N: usize> FrameElement<N> {
         Ok(this)
     }
 
+    /// Returns `true` if the frame is not in use.
+    pub(in crate::pdu_loop) unsafe fn is_free(this: NonNull<FrameElement<0>>) -> bool {
+        (*addr_of!((*this.as_ptr()).status)).load(Ordering::Acquire) == FrameState::None
+    }
+
+    /// Returns `true` if the frame is waiting to be sent.
+    pub(in crate::pdu_loop) unsafe fn is_sendable(this: NonNull<FrameElement<0>>) -> bool {
+        (*addr_of!((*this.as_ptr()).status)).load(Ordering::Acquire) == FrameState::Sendable
+    }
+
+    pub(in crate::pdu_loop) unsafe fn is_cyclic(this: NonNull<FrameElement<0>>) -> bool {
+        (*addr_of!((*this.as_ptr()).cyclic)).load(Ordering::Acquire)
+    }
+
+    unsafe fn set_cyclic(this: NonNull<FrameElement<0>>, cyclic: bool) {
+        (*addr_of!((*this.as_ptr()).cyclic)).store(cyclic, Ordering::Release);
+    }
+
     unsafe fn claim_sending(this: NonNull<FrameElement<N>>) -> Option<NonNull<FrameElement<N>>> {
         Self::swap_state(this, FrameState::Sendable, FrameState::Sending).ok()
     }
@@ -247,7 +269,10 @@ mod tests {
     use super::*;
     use crate::pdu_loop::frame_element::{AtomicFrameState, FrameElement, FIRST_PDU_EMPTY};
     use atomic_waker::AtomicWaker;
-    use core::{ptr::NonNull, sync::atomic::AtomicU16};
+    use core::{
+        ptr::NonNull,
+        sync::atomic::{AtomicBool, AtomicU16},
+    };
 
     #[test]
     fn set_first_pdu_only_once() {
@@ -262,6 +287,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         };
 
         let frame_ptr = NonNull::from(&frame);
@@ -285,14 +311,12 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         };
 
         let frame_ptr = NonNull::from(&frame);
 
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr.cast(), 0) },
-            false
-        );
+        assert!(!unsafe { FrameElement::<0>::first_pdu_is(frame_ptr.cast(), 0) });
     }
 
     #[test]
@@ -308,16 +332,14 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         };
 
         let frame_ptr = NonNull::from(&frame);
 
         unsafe { FrameElement::<0>::set_first_pdu(frame_ptr.cast(), 0) }
 
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr.cast(), 0) },
-            true
-        );
+        assert!(unsafe { FrameElement::<0>::first_pdu_is(frame_ptr.cast(), 0) });
     }
 
     #[test]
@@ -333,6 +355,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         };
 
         let frame_ptr_0 = NonNull::from(&frame_0);
@@ -348,6 +371,7 @@ mod tests {
             ethernet_frame: [0u8; BUF_LEN],
             pdu_payload_len: 0,
             first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+            cyclic: AtomicBool::new(false),
         };
 
         let frame_ptr_1 = NonNull::from(&frame_1);
@@ -356,30 +380,12 @@ mod tests {
 
         // ---
 
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 0) },
-            false
-        );
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 123) },
-            true
-        );
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 0xff) },
-            false
-        );
+        assert!(!unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 0) });
+        assert!(unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 123) });
+        assert!(!unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_0.cast(), 0xff) });
 
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 0) },
-            false
-        );
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 123) },
-            false
-        );
-        assert_eq!(
-            unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 0xff) },
-            true
-        );
+        assert!(!unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 0) });
+        assert!(!unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 123) });
+        assert!(unsafe { FrameElement::<0>::first_pdu_is(frame_ptr_1.cast(), 0xff) });
     }
 }
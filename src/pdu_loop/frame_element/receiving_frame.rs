@@ -1,5 +1,6 @@
 use crate::{
     error::{Error, PduError},
+    events::Event,
     fmt,
     pdu_loop::frame_element::{received_frame::ReceivedFrame, FrameBox, FrameElement, FrameState},
     PduLoop,
@@ -189,6 +190,10 @@ impl<'sto> Future for ReceiveFrameFut<'sto> {
                     // Release frame and PDU slots for reuse
                     Self::release(rxin);
 
+                    self.pdu_loop.publish(Event::FrameTimeout {
+                        frame_index: frame_idx,
+                    });
+
                     return Poll::Ready(Err(Error::Timeout));
                 }
 
@@ -205,6 +210,11 @@ impl<'sto> Future for ReceiveFrameFut<'sto> {
                 self.pdu_loop.wake_sender();
 
                 self.retries_left -= 1;
+
+                self.pdu_loop.publish(Event::FrameRetry {
+                    frame_index: frame_idx,
+                    retries_left: self.retries_left,
+                });
             }
             Poll::Pending => {
                 // Haven't timed out yet. Nothing to do - still waiting to be woken from the network
@@ -7,8 +7,14 @@ mod pdu_tx;
 // NOTE: Pub so doc links work
 pub mod storage;
 
-use crate::{command::Command, error::Error, pdu_loop::storage::PduStorageRef};
-use core::time::Duration;
+use crate::{
+    command::Command,
+    error::Error,
+    events::{Event, Events},
+    fmt,
+    pdu_loop::storage::PduStorageRef,
+};
+use core::{sync::atomic::Ordering, time::Duration};
 pub use pdu_rx::PduRx;
 // NOTE: Allowing unused because `ReceiveAction` isn't used when `xdp` is not enabled.
 #[allow(unused)]
@@ -16,7 +22,7 @@ pub use pdu_rx::ReceiveAction;
 pub use pdu_tx::PduTx;
 pub use storage::PduStorage;
 
-pub(crate) use self::frame_element::created_frame::CreatedFrame;
+pub(crate) use self::frame_element::created_frame::{CreatedFrame, PduResponseHandle};
 pub(crate) use frame_element::received_frame::ReceivedPdu;
 
 pub use frame_element::sendable_frame::SendableFrame;
@@ -26,6 +32,46 @@ pub use frame_header::EthercatFrameHeader;
 #[cfg(feature = "__internals")]
 pub use pdu_header::PduHeader;
 
+/// Whether a frame carries cyclic process data or other, acyclic traffic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum FrameClass {
+    /// Cyclic process data exchanged by a [`SubDeviceGroup`](crate::SubDeviceGroup).
+    Cyclic,
+    /// Everything else, e.g. mailbox, EEPROM and register access.
+    Acyclic,
+}
+
+/// Prioritisation of cyclic process data frames over acyclic traffic like mailbox or EEPROM
+/// accesses.
+///
+/// By default all frames are treated equally, so large mailbox transfers can use every slot in
+/// [`PduStorage`] and cause the cyclic process data exchange to fail to allocate a frame.
+///
+/// Set with [`PduLoop::set_frame_priority`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FramePriority {
+    /// The number of [`PduStorage`] slots that can only be used by cyclic process data.
+    ///
+    /// Acyclic operations wait for a free unreserved slot for up to
+    /// [`Timeouts::pdu`](crate::Timeouts::pdu). This value must be less than the number of slots
+    /// in the storage, and is reduced if it is not.
+    pub reserved_cyclic_frames: u8,
+
+    /// The maximum number of acyclic frames sent each time the TX task is woken.
+    ///
+    /// Once the budget is used up, any remaining acyclic frames are held until the next cyclic
+    /// frame is sent, which limits the acyclic frames sent between two process data cycles. `None`
+    /// places no limit on acyclic frames. A limit of zero is treated as one.
+    ///
+    /// Acyclic frames held back by the budget are only sent once cyclic process data is
+    /// exchanged again, and time out if it never is. Only set a budget while a process data cycle
+    /// is running, and set it back to `None` before stopping the cycle, e.g. before
+    /// [`SubDeviceGroup::shutdown`](crate::SubDeviceGroup::shutdown).
+    pub acyclic_frames_per_wakeup: Option<u8>,
+}
+
 /// The core EtherCrab network communications driver.
 ///
 // TODO: Update the following docs. The current text is out of date.
@@ -57,6 +103,7 @@ pub use pdu_header::PduHeader;
 #[derive(Debug)]
 pub struct PduLoop<'sto> {
     storage: PduStorageRef<'sto>,
+    events: Events<'sto>,
 }
 
 impl<'sto> PduLoop<'sto> {
@@ -64,13 +111,74 @@ impl<'sto> PduLoop<'sto> {
     pub(in crate::pdu_loop) const fn new(storage: PduStorageRef<'sto>) -> Self {
         assert!(storage.num_frames <= u8::MAX as usize);
 
-        Self { storage }
+        Self {
+            storage,
+            events: Events::none(),
+        }
     }
 
     pub(crate) const fn max_frame_data(&self) -> usize {
         self.storage.frame_data_len
     }
 
+    /// Reserve frames for cyclic process data and limit how many acyclic frames are sent at once.
+    ///
+    /// When any priority is set, the TX task also sends all pending cyclic frames before any
+    /// acyclic ones.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use ethercrab::{FramePriority, PduStorage};
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// pdu_loop.set_frame_priority(FramePriority {
+    ///     reserved_cyclic_frames: 4,
+    ///     acyclic_frames_per_wakeup: Some(2),
+    /// });
+    /// ```
+    pub fn set_frame_priority(&self, priority: FramePriority) {
+        let max_reserved = u8::try_from(self.storage.num_frames - 1).unwrap_or(u8::MAX);
+
+        if priority.reserved_cyclic_frames > max_reserved {
+            fmt::warn!(
+                "Cannot reserve {} of {} frames for cyclic data, reserving {}",
+                priority.reserved_cyclic_frames,
+                self.storage.num_frames,
+                max_reserved
+            );
+        }
+
+        self.storage.reserved_cyclic.store(
+            priority.reserved_cyclic_frames.min(max_reserved),
+            Ordering::Relaxed,
+        );
+
+        self.storage.acyclic_budget.store(
+            priority
+                .acyclic_frames_per_wakeup
+                .map(|budget| budget.max(1))
+                .unwrap_or(0),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Returns `true` if some frames are reserved for cyclic process data.
+    pub(crate) fn has_reserved_frames(&self) -> bool {
+        self.storage.reserved_cyclic.load(Ordering::Relaxed) > 0
+    }
+
+    pub(crate) fn set_events(&mut self, events: Events<'sto>) {
+        self.events = events;
+    }
+
+    pub(crate) fn publish(&self, event: Event) {
+        self.events.publish(event);
+    }
+
     /// Tell the packet sender there are PDUs ready to send.
     pub(crate) fn wake_sender(&self) {
         self.storage.tx_waker.wake();
@@ -100,6 +208,12 @@ impl<'sto> PduLoop<'sto> {
     pub(crate) fn alloc_frame(&self) -> Result<CreatedFrame<'sto>, Error> {
         self.storage.alloc_frame()
     }
+
+    /// Allocate a frame for cyclic process data, which may use the reserved frames set with
+    /// [`set_frame_priority`](PduLoop::set_frame_priority).
+    pub(crate) fn alloc_cyclic_frame(&self) -> Result<CreatedFrame<'sto>, Error> {
+        self.storage.alloc_frame_class(FrameClass::Cyclic)
+    }
 }
 
 #[cfg(test)]
@@ -112,7 +226,7 @@ mod tests {
         fmt,
         pdu_loop::frame_element::created_frame::CreatedFrame,
         timer_factory::IntoTimeout,
-        Command, PduStorage, Reads,
+        Command, FramePriority, PduStorage, Reads,
     };
     use cassette::Cassette;
     use core::{future::poll_fn, ops::Deref, pin::pin, task::Poll, time::Duration};
@@ -213,7 +327,7 @@ mod tests {
             let mut frame = pdu_loop.storage.alloc_frame().expect("Frame alloc");
 
             let handle = frame
-                .push_pdu(Command::fpwr(0x5678, 0x1234).into(), &data, None)
+                .push_pdu(Command::fpwr(0x5678, 0x1234).into(), data, None)
                 .expect("Push PDU");
 
             let mut frame_fut = pin!(frame.mark_sendable(&pdu_loop, Duration::MAX, usize::MAX));
@@ -361,7 +475,7 @@ mod tests {
             let mut frame = pdu_loop.storage.alloc_frame().unwrap();
 
             let handle = frame
-                .push_pdu(Command::fpwr(0x6789, 0x1234).into(), &data_bytes, None)
+                .push_pdu(Command::fpwr(0x6789, 0x1234).into(), data_bytes, None)
                 .expect("Push PDU");
 
             let mut frame_fut = pin!(frame.mark_sendable(&pdu_loop, Duration::MAX, usize::MAX));
@@ -742,4 +856,120 @@ mod tests {
 
         assert_eq!(&remaining[sent..], &[]);
     }
+
+    #[test]
+    fn prioritise_cyclic_frames() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        static STORAGE: PduStorage<4, { PduStorage::element_size(16) }> = PduStorage::new();
+        let (mut tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        pdu_loop.set_frame_priority(FramePriority {
+            reserved_cyclic_frames: 1,
+            ..FramePriority::default()
+        });
+
+        let sendable = |frame: CreatedFrame<'static>| {
+            frame.mark_sendable(&pdu_loop, Duration::MAX, usize::MAX)
+        };
+
+        // Frames are allocated in index order
+        let _a = sendable(pdu_loop.alloc_frame().unwrap());
+        let _b = sendable(pdu_loop.alloc_frame().unwrap());
+        let _c = sendable(pdu_loop.alloc_frame().unwrap());
+
+        // The last frame is reserved
+        assert_eq!(
+            pdu_loop.alloc_frame().unwrap_err(),
+            PduError::SwapState.into()
+        );
+
+        let _d = sendable(pdu_loop.alloc_cyclic_frame().unwrap());
+
+        let mut next = || tx.next_sendable_frame().map(|frame| frame.index());
+
+        // Cyclic frame is sent first, then acyclic frames in index order
+        assert_eq!(next(), Some(3));
+        assert_eq!(next(), Some(0));
+        assert_eq!(next(), Some(1));
+        assert_eq!(next(), Some(2));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn acyclic_budget() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        static STORAGE: PduStorage<8, { PduStorage::element_size(16) }> = PduStorage::new();
+        let (mut tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        pdu_loop.set_frame_priority(FramePriority {
+            reserved_cyclic_frames: 1,
+            acyclic_frames_per_wakeup: Some(2),
+        });
+
+        let sendable = |frame: CreatedFrame<'static>| {
+            frame.mark_sendable(&pdu_loop, Duration::MAX, usize::MAX)
+        };
+
+        let _acyclic = [
+            sendable(pdu_loop.alloc_frame().unwrap()),
+            sendable(pdu_loop.alloc_frame().unwrap()),
+            sendable(pdu_loop.alloc_frame().unwrap()),
+            sendable(pdu_loop.alloc_frame().unwrap()),
+            sendable(pdu_loop.alloc_frame().unwrap()),
+        ];
+
+        let mut next = || tx.next_sendable_frame().map(|frame| frame.index());
+
+        // First wakeup: only the budget is sent
+        assert_eq!(next(), Some(0));
+        assert_eq!(next(), Some(1));
+        assert_eq!(next(), None);
+
+        // Another wakeup without any cyclic frames doesn't send any more
+        assert_eq!(next(), None);
+
+        // The next cyclic frame releases another budget's worth of acyclic frames
+        let _cyclic = sendable(pdu_loop.alloc_cyclic_frame().unwrap());
+
+        let mut next = || tx.next_sendable_frame().map(|frame| frame.index());
+
+        assert_eq!(next(), Some(5));
+        assert_eq!(next(), Some(2));
+        assert_eq!(next(), Some(3));
+        assert_eq!(next(), None);
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn acyclic_budget_not_used_up() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        static STORAGE: PduStorage<4, { PduStorage::element_size(16) }> = PduStorage::new();
+        let (mut tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        pdu_loop.set_frame_priority(FramePriority {
+            reserved_cyclic_frames: 0,
+            acyclic_frames_per_wakeup: Some(2),
+        });
+
+        let sendable = |frame: CreatedFrame<'static>| {
+            frame.mark_sendable(&pdu_loop, Duration::MAX, usize::MAX)
+        };
+
+        // Exactly the budget is sent with nothing left over, so acyclic traffic isn't held
+        let _a = sendable(pdu_loop.alloc_frame().unwrap());
+        let _b = sendable(pdu_loop.alloc_frame().unwrap());
+
+        assert_eq!(tx.next_sendable_frame().map(|frame| frame.index()), Some(0));
+        assert_eq!(tx.next_sendable_frame().map(|frame| frame.index()), Some(1));
+        assert_eq!(tx.next_sendable_frame().map(|frame| frame.index()), None);
+
+        // Next wakeup
+        let _c = sendable(pdu_loop.alloc_frame().unwrap());
+
+        assert_eq!(tx.next_sendable_frame().map(|frame| frame.index()), Some(2));
+        assert_eq!(tx.next_sendable_frame().map(|frame| frame.index()), None);
+    }
 }
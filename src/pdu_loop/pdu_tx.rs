@@ -1,14 +1,26 @@
-use super::{frame_element::sendable_frame::SendableFrame, storage::PduStorageRef};
-use core::task::Waker;
+use super::{
+    frame_element::{sendable_frame::SendableFrame, FrameElement},
+    storage::PduStorageRef,
+};
+use crate::fmt;
+use core::{sync::atomic::Ordering, task::Waker};
 
 /// EtherCAT frame transmit adapter.
 pub struct PduTx<'sto> {
     storage: PduStorageRef<'sto>,
+    /// Acyclic frames sent since the last cyclic frame or the end of the last TX wakeup.
+    acyclic_sent: u8,
+    /// The acyclic budget is used up, so acyclic frames are held until a cyclic frame is sent.
+    holding: bool,
 }
 
 impl<'sto> PduTx<'sto> {
     pub(in crate::pdu_loop) fn new(storage: PduStorageRef<'sto>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            acyclic_sent: 0,
+            holding: false,
+        }
     }
 
     /// The number of frames that can be in flight at once.
@@ -17,11 +29,80 @@ impl<'sto> PduTx<'sto> {
     }
 
     /// Get the next sendable frame, if any are available.
+    ///
+    /// If a [`FramePriority`](crate::FramePriority) is set, cyclic process data frames are
+    /// returned before any other frames. Once the acyclic frame budget is used up, this method
+    /// only returns cyclic frames until one has been sent, so the TX task goes back to sleep
+    /// leaving the remaining acyclic frames queued.
     // NOTE: Mutable so it can only be used in one task.
     pub fn next_sendable_frame(&mut self) -> Option<SendableFrame<'sto>> {
+        let budget = self.storage.acyclic_budget.load(Ordering::Relaxed);
+
+        if budget == 0 {
+            self.holding = false;
+
+            if self.storage.reserved_cyclic.load(Ordering::Relaxed) == 0 {
+                return self.claim_sendable(|_cyclic| true);
+            }
+        }
+
+        if let Some(frame) = self.claim_sendable(|cyclic| cyclic) {
+            self.acyclic_sent = 0;
+            self.holding = false;
+
+            return Some(frame);
+        }
+
+        if self.holding {
+            return None;
+        }
+
+        if budget > 0 && self.acyclic_sent >= budget {
+            self.acyclic_sent = 0;
+
+            // Only hold if there is something to hold, otherwise acyclic traffic without any
+            // cyclic frames would stop after exactly `budget` frames.
+            if self.has_sendable(|cyclic| !cyclic) {
+                fmt::trace!("Acyclic frame budget of {} used up", budget);
+
+                self.holding = true;
+            }
+
+            return None;
+        }
+
+        let frame = self.claim_sendable(|cyclic| !cyclic);
+
+        if frame.is_some() {
+            self.acyclic_sent = self.acyclic_sent.saturating_add(1);
+        } else {
+            // Nothing left to send, so the next wakeup starts with a fresh budget
+            self.acyclic_sent = 0;
+        }
+
+        frame
+    }
+
+    /// Whether any frame whose class matches `filter` is waiting to be sent.
+    fn has_sendable(&self, filter: impl Fn(bool) -> bool) -> bool {
+        (0..self.storage.num_frames).any(|idx| {
+            let frame = self.storage.frame_at_index(idx);
+
+            unsafe {
+                filter(FrameElement::<0>::is_cyclic(frame)) && FrameElement::<0>::is_sendable(frame)
+            }
+        })
+    }
+
+    /// Claim the first sendable frame whose class matches `filter`.
+    fn claim_sendable(&self, filter: impl Fn(bool) -> bool) -> Option<SendableFrame<'sto>> {
         for idx in 0..self.storage.num_frames {
             let frame = self.storage.frame_at_index(idx);
 
+            if !filter(unsafe { FrameElement::<0>::is_cyclic(frame) }) {
+                continue;
+            }
+
             let Some(sending) = SendableFrame::claim_sending(
                 frame,
                 self.storage.pdu_idx,
@@ -8,6 +8,7 @@ use crate::{
             created_frame::CreatedFrame, receiving_frame::ReceivingFrame, FrameElement,
         },
         pdu_flags::PduFlags,
+        FrameClass,
     },
     PduLoop,
 };
@@ -43,6 +44,10 @@ pub struct PduStorage<const N: usize, const DATA: usize> {
     is_split: AtomicBool,
     /// A waker used to wake up the TX task when a new frame is ready to be sent.
     pub(in crate::pdu_loop) tx_waker: AtomicWaker,
+    /// Number of frames that may only be allocated for cyclic process data.
+    reserved_cyclic: AtomicU8,
+    /// Maximum number of acyclic frames sent per TX wakeup, or zero for no limit.
+    acyclic_budget: AtomicU8,
 }
 
 unsafe impl<const N: usize, const DATA: usize> Sync for PduStorage<N, DATA> {}
@@ -86,6 +91,7 @@ impl<const N: usize, const DATA: usize> PduStorage<N, DATA> {
     /// - `N` is larger than `u8::MAX, or not a power of two, or
     /// - `DATA` is less than 28 as this is the minimum size required to hold an EtherCAT frame with
     ///   zero PDU length.
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
         // MSRV: Make `N` a `u8` when `generic_const_exprs` is stablised
         // If possible, try using `NonZeroU8`.
@@ -118,6 +124,8 @@ impl<const N: usize, const DATA: usize> PduStorage<N, DATA> {
             pdu_idx: AtomicU8::new(0),
             is_split: AtomicBool::new(false),
             tx_waker: AtomicWaker::new(),
+            reserved_cyclic: AtomicU8::new(0),
+            acyclic_budget: AtomicU8::new(0),
         }
     }
 
@@ -156,6 +164,8 @@ impl<const N: usize, const DATA: usize> PduStorage<N, DATA> {
             frame_idx: &self.frame_idx,
             pdu_idx: &self.pdu_idx,
             tx_waker: &self.tx_waker,
+            reserved_cyclic: &self.reserved_cyclic,
+            acyclic_budget: &self.acyclic_budget,
             _lifetime: PhantomData,
         }
     }
@@ -171,12 +181,36 @@ pub(crate) struct PduStorageRef<'sto> {
     frame_idx: &'sto AtomicU8,
     pub pdu_idx: &'sto AtomicU8,
     pub tx_waker: &'sto AtomicWaker,
+    pub reserved_cyclic: &'sto AtomicU8,
+    pub acyclic_budget: &'sto AtomicU8,
     _lifetime: PhantomData<&'sto ()>,
 }
 
 impl<'sto> PduStorageRef<'sto> {
-    /// Allocate a PDU frame with the given command and data length.
+    /// Allocate an acyclic PDU frame.
     pub(in crate::pdu_loop) fn alloc_frame(&self) -> Result<CreatedFrame<'sto>, Error> {
+        self.alloc_frame_class(FrameClass::Acyclic)
+    }
+
+    /// Allocate a PDU frame of the given class.
+    ///
+    /// Acyclic frames are not allocated if doing so would leave fewer free frames than are
+    /// reserved for cyclic process data.
+    pub(in crate::pdu_loop) fn alloc_frame_class(
+        &self,
+        class: FrameClass,
+    ) -> Result<CreatedFrame<'sto>, Error> {
+        let reserved = match class {
+            FrameClass::Cyclic => 0,
+            FrameClass::Acyclic => usize::from(self.reserved_cyclic.load(Ordering::Relaxed)),
+        };
+
+        if reserved > 0 && self.free_frames() <= reserved {
+            fmt::trace!("Only {} reserved cyclic frames are free", reserved);
+
+            return Err(PduError::SwapState.into());
+        }
+
         // Find next frame that is not currently in use.
         //
         // Escape hatch: we'll only loop through the frame storage array twice to put an upper
@@ -199,7 +233,16 @@ impl<'sto> PduStorageRef<'sto> {
             let frame =
                 CreatedFrame::claim_created(frame, frame_idx, self.pdu_idx, self.frame_data_len);
 
-            if let Ok(f) = frame {
+            if let Ok(mut f) = frame {
+                // Another acyclic allocation may have raced us for the last unreserved frame
+                if reserved > 0 && self.free_frames() < reserved {
+                    f.release();
+
+                    return Err(PduError::SwapState.into());
+                }
+
+                f.set_cyclic(class == FrameClass::Cyclic);
+
                 return Ok(f);
             }
         }
@@ -212,6 +255,13 @@ impl<'sto> PduStorageRef<'sto> {
         Err(PduError::SwapState.into())
     }
 
+    /// The number of frames not currently in use.
+    fn free_frames(&self) -> usize {
+        (0..self.num_frames)
+            .filter(|idx| unsafe { FrameElement::<0>::is_free(self.frame_at_index(*idx)) })
+            .count()
+    }
+
     /// Updates state from SENDING -> RX_BUSY
     pub(in crate::pdu_loop) fn claim_receiving(
         &self,
@@ -321,6 +371,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reserved_cyclic_frames() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage: PduStorage<4, { PduStorage::element_size(8) }> = PduStorage::new();
+        let s = storage.as_ref();
+
+        s.reserved_cyclic.store(2, Ordering::Relaxed);
+
+        let _a = s.alloc_frame().expect("first acyclic");
+        let _b = s.alloc_frame().expect("second acyclic");
+
+        // Only reserved frames are left
+        assert_eq!(s.alloc_frame().unwrap_err(), PduError::SwapState.into());
+
+        let _c = s
+            .alloc_frame_class(FrameClass::Cyclic)
+            .expect("first cyclic");
+        let _d = s
+            .alloc_frame_class(FrameClass::Cyclic)
+            .expect("second cyclic");
+
+        assert!(s.alloc_frame_class(FrameClass::Cyclic).is_err());
+    }
+
     #[test]
     fn no_spare_frames() {
         let _ = env_logger::builder().is_test(true).try_init();
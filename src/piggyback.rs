@@ -0,0 +1,401 @@
+//! A queue of small acyclic PDUs that are sent in the spare space of cyclic process data frames.
+//!
+//! Each queue slot is owned by either the task that queued the PDU or the cyclic exchange that is
+//! sending it, with ownership handed over through the slot's atomic state:
+//!
+//! - `FREE -> WRITING -> QUEUED`: the PDU is written by the requesting task.
+//! - `QUEUED -> IN_FLIGHT`: the PDU is claimed by a cyclic exchange and pushed into a frame.
+//! - `IN_FLIGHT -> RESPONDING -> DONE`: the response is written by the exchange.
+//! - `DONE -> FREE`: the response is read by the requesting task.
+//!
+//! A PDU that is still `QUEUED` can be withdrawn by the requesting task to send it in its own
+//! frame instead. If the requesting task gives up while the PDU is being sent, the slot is marked
+//! `CANCELLED` and freed by the exchange once it is done with it.
+
+use crate::{command::Command, error::Error, fmt};
+use atomic_waker::AtomicWaker;
+use core::{
+    cell::UnsafeCell,
+    future::poll_fn,
+    sync::atomic::{AtomicU8, Ordering},
+    task::Poll,
+};
+use ethercrab_wire::EtherCrabWireWrite;
+
+/// The maximum number of PDUs that can be queued at once.
+pub(crate) const QUEUE_LEN: usize = 8;
+
+/// The maximum data length of a queued PDU in bytes.
+pub(crate) const MAX_DATA: usize = 32;
+
+const FREE: u8 = 0;
+const WRITING: u8 = 1;
+const QUEUED: u8 = 2;
+const IN_FLIGHT: u8 = 3;
+const RESPONDING: u8 = 4;
+const DONE: u8 = 5;
+const CANCELLED: u8 = 6;
+
+#[derive(Debug)]
+struct Slot {
+    state: AtomicU8,
+    command: UnsafeCell<Command>,
+    len: UnsafeCell<u16>,
+    /// Data to send, then the response data once received.
+    data: UnsafeCell<[u8; MAX_DATA]>,
+    working_counter: UnsafeCell<u16>,
+    waker: AtomicWaker,
+}
+
+impl Slot {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        state: AtomicU8::new(FREE),
+        command: UnsafeCell::new(Command::Nop),
+        len: UnsafeCell::new(0),
+        data: UnsafeCell::new([0; MAX_DATA]),
+        working_counter: UnsafeCell::new(0),
+        waker: AtomicWaker::new(),
+    };
+
+    fn swap_state(&self, from: u8, to: u8) -> bool {
+        self.state
+            .compare_exchange(from, to, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// The response to a queued PDU.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PiggybackResponse {
+    data: [u8; MAX_DATA],
+    len: u16,
+    pub working_counter: u16,
+}
+
+impl PiggybackResponse {
+    pub fn data(&self) -> &[u8] {
+        &self.data[0..usize::from(self.len)]
+    }
+
+    pub fn maybe_wkc(self, expected: Option<u16>) -> Result<Self, Error> {
+        match expected {
+            Some(expected) if self.working_counter != expected => Err(Error::WorkingCounter {
+                expected,
+                received: self.working_counter,
+            }),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// A queue of PDUs waiting to be sent in the next cyclic process data frame.
+#[derive(Debug)]
+pub(crate) struct PiggybackQueue {
+    slots: [Slot; QUEUE_LEN],
+}
+
+// SAFETY: Slot data is only accessed by whichever side owns the slot, as determined by its atomic
+// state.
+unsafe impl Sync for PiggybackQueue {}
+
+impl PiggybackQueue {
+    pub const fn new() -> Self {
+        Self {
+            slots: [Slot::EMPTY; QUEUE_LEN],
+        }
+    }
+
+    /// Queue a PDU to be sent in the next cyclic frame.
+    ///
+    /// Returns `None` if the PDU is too long or the queue is full.
+    pub fn enqueue(
+        &self,
+        command: Command,
+        data: impl EtherCrabWireWrite,
+        len_override: Option<u16>,
+    ) -> Option<Ticket<'_>> {
+        let len = len_override.map_or(data.packed_len(), |len| {
+            usize::from(len).max(data.packed_len())
+        });
+
+        if len > MAX_DATA {
+            return None;
+        }
+
+        let (idx, slot) = self
+            .slots
+            .iter()
+            .enumerate()
+            .find(|(_, slot)| slot.swap_state(FREE, WRITING))?;
+
+        // SAFETY: The slot is owned by this task while in the `WRITING` state.
+        unsafe {
+            *slot.command.get() = command;
+            *slot.len.get() = len as u16;
+
+            let buf = &mut *slot.data.get();
+
+            buf.fill(0);
+            data.pack_to_slice_unchecked(buf);
+        }
+
+        slot.state.store(QUEUED, Ordering::Release);
+
+        Some(Ticket {
+            queue: self,
+            idx,
+            finished: false,
+        })
+    }
+
+    /// Claim the next queued PDU to send in a cyclic frame.
+    pub fn claim_next(&self) -> Option<(usize, Command, &[u8])> {
+        self.slots
+            .iter()
+            .enumerate()
+            .find(|(_, slot)| slot.swap_state(QUEUED, IN_FLIGHT))
+            .map(|(idx, slot)| {
+                // SAFETY: The slot is owned by the exchange while in the `IN_FLIGHT` state.
+                let (command, data) = unsafe {
+                    (
+                        *slot.command.get(),
+                        &(*slot.data.get())[0..usize::from(*slot.len.get())],
+                    )
+                };
+
+                (idx, command, data)
+            })
+    }
+
+    /// Put a claimed PDU back in the queue, e.g. because it did not fit in the frame or the frame
+    /// could not be sent.
+    pub fn requeue(&self, idx: usize) {
+        let Some(slot) = self.slots.get(idx) else {
+            return;
+        };
+
+        if !slot.swap_state(IN_FLIGHT, QUEUED) && slot.swap_state(CANCELLED, FREE) {
+            fmt::trace!("Freed cancelled piggyback PDU {}", idx);
+        }
+    }
+
+    /// Store the response to a claimed PDU and wake the task waiting for it.
+    pub fn complete(&self, idx: usize, working_counter: u16, data: &[u8]) {
+        let Some(slot) = self.slots.get(idx) else {
+            return;
+        };
+
+        if !slot.swap_state(IN_FLIGHT, RESPONDING) {
+            // Nobody is waiting for this response anymore
+            slot.swap_state(CANCELLED, FREE);
+
+            return;
+        }
+
+        // SAFETY: The slot is owned by the exchange while in the `RESPONDING` state.
+        unsafe {
+            let buf = &mut *slot.data.get();
+            let len = data.len().min(MAX_DATA);
+
+            buf[0..len].copy_from_slice(&data[0..len]);
+
+            *slot.len.get() = len as u16;
+            *slot.working_counter.get() = working_counter;
+        }
+
+        if !slot.swap_state(RESPONDING, DONE) {
+            // Cancelled while the response was being written
+            slot.state.store(FREE, Ordering::Release);
+
+            return;
+        }
+
+        slot.waker.wake();
+    }
+}
+
+/// A queued PDU, used to wait for its response.
+///
+/// Dropping the ticket removes the PDU from the queue, or discards its response if it has already
+/// been sent.
+#[derive(Debug)]
+pub(crate) struct Ticket<'q> {
+    queue: &'q PiggybackQueue,
+    idx: usize,
+    /// Set once the slot has been freed by this ticket.
+    finished: bool,
+}
+
+impl<'q> Ticket<'q> {
+    fn slot(&self) -> &Slot {
+        &self.queue.slots[self.idx]
+    }
+
+    /// Remove the PDU from the queue if it has not been claimed by a cyclic exchange yet.
+    ///
+    /// Returns `true` if the PDU was withdrawn.
+    pub fn withdraw(&mut self) -> bool {
+        if !self.finished && self.slot().swap_state(QUEUED, FREE) {
+            self.finished = true;
+        }
+
+        self.finished
+    }
+
+    /// Wait for the PDU to be sent and its response received.
+    pub async fn response(&mut self) -> PiggybackResponse {
+        poll_fn(|ctx| {
+            let slot = self.slot();
+
+            slot.waker.register(ctx.waker());
+
+            if slot.state.load(Ordering::Acquire) != DONE {
+                return Poll::Pending;
+            }
+
+            // SAFETY: The response is owned by this ticket while in the `DONE` state.
+            let response = unsafe {
+                PiggybackResponse {
+                    data: *slot.data.get(),
+                    len: *slot.len.get(),
+                    working_counter: *slot.working_counter.get(),
+                }
+            };
+
+            slot.state.store(FREE, Ordering::Release);
+
+            self.finished = true;
+
+            Poll::Ready(response)
+        })
+        .await
+    }
+}
+
+impl<'q> Drop for Ticket<'q> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let slot = self.slot();
+
+        let _ = slot.swap_state(QUEUED, FREE)
+            || slot.swap_state(IN_FLIGHT, CANCELLED)
+            || slot.swap_state(RESPONDING, CANCELLED)
+            || slot.swap_state(DONE, FREE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let queue = PiggybackQueue::new();
+
+        let mut ticket = queue
+            .enqueue(Command::fpwr(0x1000, 0x0120).into(), 0xabcdu16, None)
+            .expect("enqueue");
+
+        let (idx, command, data) = queue.claim_next().expect("claim");
+
+        assert_eq!(command, Command::fpwr(0x1000, 0x0120).into());
+        assert_eq!(data, &[0xcd, 0xab]);
+
+        // Only one PDU was queued
+        assert!(queue.claim_next().is_none());
+
+        queue.complete(idx, 1, &[0x12, 0x34]);
+
+        let response = cassette::block_on(ticket.response());
+
+        assert_eq!(response.data(), &[0x12, 0x34]);
+        assert_eq!(
+            response.maybe_wkc(Some(1)).map(|r| r.working_counter),
+            Ok(1)
+        );
+        assert_eq!(
+            response.maybe_wkc(Some(2)).map(|r| r.working_counter),
+            Err(Error::WorkingCounter {
+                expected: 2,
+                received: 1
+            })
+        );
+
+        drop(ticket);
+
+        // Slot is free again
+        assert!(queue
+            .slots
+            .iter()
+            .all(|slot| slot.state.load(Ordering::Relaxed) == FREE));
+    }
+
+    #[test]
+    fn too_long_or_full() {
+        let queue = PiggybackQueue::new();
+
+        assert!(queue
+            .enqueue(
+                Command::fprd(0x1000, 0).into(),
+                (),
+                Some(MAX_DATA as u16 + 1)
+            )
+            .is_none());
+
+        let tickets = (0..QUEUE_LEN)
+            .map(|_| {
+                queue
+                    .enqueue(Command::fprd(0x1000, 0).into(), (), Some(2))
+                    .expect("enqueue")
+            })
+            .collect::<Vec<_>>();
+
+        assert!(queue
+            .enqueue(Command::fprd(0x1000, 0).into(), (), Some(2))
+            .is_none());
+
+        drop(tickets);
+
+        assert!(queue.claim_next().is_none());
+    }
+
+    #[test]
+    fn withdraw_and_cancel() {
+        let queue = PiggybackQueue::new();
+
+        let mut ticket = queue
+            .enqueue(Command::fprd(0x1000, 0).into(), (), Some(2))
+            .unwrap();
+
+        assert!(ticket.withdraw());
+        assert!(queue.claim_next().is_none());
+
+        let mut ticket = queue
+            .enqueue(Command::fprd(0x1000, 0).into(), (), Some(2))
+            .unwrap();
+
+        let (idx, _, _) = queue.claim_next().unwrap();
+
+        // Already being sent
+        assert!(!ticket.withdraw());
+
+        // Requeued because the frame could not be sent
+        queue.requeue(idx);
+
+        let (idx, _, _) = queue.claim_next().unwrap();
+
+        drop(ticket);
+
+        // The response is discarded and the slot freed
+        queue.complete(idx, 1, &[0, 0]);
+
+        assert!(queue
+            .slots
+            .iter()
+            .all(|slot| slot.state.load(Ordering::Relaxed) == FREE));
+    }
+}
@@ -0,0 +1,203 @@
+//! Locating breaks in a redundant ring network.
+
+use crate::{
+    command::Command, dl_status::DlStatus, error::Error, fmt, MainDevice, RegisterAddress,
+    BASE_SUBDEVICE_ADDRESS,
+};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// The location of a cable break in a ring network.
+///
+/// SubDevices are numbered in network order from the primary interface, i.e. port 0 of each
+/// SubDevice faces the primary interface and port 1 faces the secondary interface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RingBreak {
+    /// The configured address of the last SubDevice reachable from the primary interface, or
+    /// `None` if the break is between the primary interface and the first SubDevice.
+    pub primary_side: Option<u16>,
+
+    /// The configured address of the first SubDevice reachable from the secondary interface, or
+    /// `None` if the break is between the last SubDevice and the secondary interface.
+    pub secondary_side: Option<u16>,
+}
+
+impl<'sto> MainDevice<'sto> {
+    /// Find the location of a break in a ring network by reading the DL status of every SubDevice.
+    ///
+    /// This is intended for use with a redundant driver like
+    /// `ethercrab::std::tx_rx_task_redundant`, where SubDevices on both sides of the break are
+    /// still reachable. The last SubDevice's port 1 must be cabled back to the secondary interface,
+    /// otherwise a break is always reported after it.
+    ///
+    /// Returns `None` if every SubDevice responded and all links are up. If a SubDevice does not
+    /// respond, the break is reported directly before it.
+    ///
+    /// [`init`](MainDevice::init) must have been called with the ring intact so configured
+    /// addresses follow network order.
+    ///
+    /// # Limitations
+    ///
+    /// Only the SubDevices discovered by [`init`](MainDevice::init) are checked, assuming they
+    /// were given the contiguous configured addresses starting at `0x1000` that `init` assigns.
+    /// SubDevices in a [`HotConnectSegment`](crate::HotConnectSegment) have their own address
+    /// range and are not checked, so a break inside or after a hot connect segment may be
+    /// reported at the wrong SubDevice or not at all.
+    pub async fn ring_break(&self) -> Result<Option<RingBreak>, Error> {
+        let num_subdevices = self.num_subdevices() as u16;
+
+        let mut result = None;
+        let mut previous = None;
+
+        self.batch_pdus(
+            (0..num_subdevices).map(|index| {
+                Command::fprd(
+                    BASE_SUBDEVICE_ADDRESS + index,
+                    RegisterAddress::DlStatus.into(),
+                )
+                .into()
+            }),
+            DlStatus::PACKED_LEN as u16,
+            |index, pdu| {
+                let address = BASE_SUBDEVICE_ADDRESS + index as u16;
+
+                let status = if pdu.working_counter == 1 {
+                    Some(DlStatus::unpack_from_slice(&pdu)?)
+                } else {
+                    None
+                };
+
+                if result.is_none() {
+                    result = find_break(
+                        previous,
+                        address,
+                        status,
+                        index + 1 == num_subdevices.into(),
+                    );
+                }
+
+                previous = Some(address);
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        if let Some(location) = result {
+            fmt::warn!(
+                "Ring break between {:?} and {:?}",
+                location.primary_side,
+                location.secondary_side
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+/// Check one SubDevice for a break, given the address of the SubDevice before it.
+fn find_break(
+    previous: Option<u16>,
+    address: u16,
+    status: Option<DlStatus>,
+    is_last: bool,
+) -> Option<RingBreak> {
+    match status {
+        // Either the SubDevice is unreachable or the cable into it is broken
+        None => Some(RingBreak {
+            primary_side: previous,
+            secondary_side: Some(address),
+        }),
+        Some(status) if !status.link(0) => Some(RingBreak {
+            primary_side: previous,
+            secondary_side: Some(address),
+        }),
+        Some(status) if !status.link(1) => Some(RingBreak {
+            primary_side: Some(address),
+            secondary_side: (!is_last).then(|| address + 1),
+        }),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(port0: bool, port1: bool) -> Option<DlStatus> {
+        Some(DlStatus {
+            pdi_operational: true,
+            watchdog_ok: true,
+            extended_link_detection: false,
+            link_port0: port0,
+            link_port1: port1,
+            link_port2: false,
+            link_port3: false,
+            loopback_port0: false,
+            signal_port0: port0,
+            loopback_port1: false,
+            signal_port1: port1,
+            loopback_port2: true,
+            signal_port2: false,
+            loopback_port3: true,
+            signal_port3: false,
+        })
+    }
+
+    #[test]
+    fn intact() {
+        assert_eq!(find_break(None, 0x1000, status(true, true), false), None);
+        assert_eq!(
+            find_break(Some(0x1000), 0x1001, status(true, true), true),
+            None
+        );
+    }
+
+    #[test]
+    fn break_between_subdevices() {
+        assert_eq!(
+            find_break(None, 0x1000, status(true, false), false),
+            Some(RingBreak {
+                primary_side: Some(0x1000),
+                secondary_side: Some(0x1001)
+            })
+        );
+        assert_eq!(
+            find_break(Some(0x1000), 0x1001, status(false, true), false),
+            Some(RingBreak {
+                primary_side: Some(0x1000),
+                secondary_side: Some(0x1001)
+            })
+        );
+    }
+
+    #[test]
+    fn break_at_interfaces() {
+        assert_eq!(
+            find_break(None, 0x1000, status(false, true), false),
+            Some(RingBreak {
+                primary_side: None,
+                secondary_side: Some(0x1000)
+            })
+        );
+        assert_eq!(
+            find_break(Some(0x1000), 0x1001, status(true, false), true),
+            Some(RingBreak {
+                primary_side: Some(0x1001),
+                secondary_side: None
+            })
+        );
+    }
+
+    #[test]
+    fn no_response() {
+        assert_eq!(
+            find_break(Some(0x1000), 0x1001, None, false),
+            Some(RingBreak {
+                primary_side: Some(0x1000),
+                secondary_side: Some(0x1001)
+            })
+        );
+    }
+}
@@ -40,6 +40,25 @@ pub enum RegisterAddress {
     /// Application Layer (AL) status code register.
     AlStatusCode = 0x0134,
 
+    /// Port 0-3 RX error counters, two `u8`s per port.
+    ///
+    /// For each port, the invalid frame counter is followed by the physical layer RX error
+    /// counter. Writing to any register in `0x0300..=0x030B` clears this and the
+    /// [`ForwardedRxErrorCounter`](RegisterAddress::ForwardedRxErrorCounter) registers.
+    RxErrorCounter = 0x0300,
+    /// Port 0-3 forwarded RX error counters, one `u8` per port.
+    ///
+    /// Counts frames that arrived already marked as faulty by a SubDevice earlier in the network.
+    ForwardedRxErrorCounter = 0x0308,
+    /// EtherCAT Processing Unit error counter, `u8`. Cleared by writing to it.
+    ProcessingUnitErrorCounter = 0x030C,
+    /// PDI error counter, `u8`. Cleared by writing to it.
+    PdiErrorCounter = 0x030D,
+    /// Port 0-3 lost link counters, one `u8` per port.
+    ///
+    /// Writing to any of these registers clears all four.
+    LostLinkCounter = 0x0310,
+
     /// Watchdog divider, `u16`.
     ///
     /// See ETG1000.4 section 6.3 Watchdogs.
@@ -193,6 +212,13 @@ pub enum RegisterAddress {
     /// AKA ETG1000.4 Table 61 DC user P1.
     DcSyncActive = 0x0981,
 
+    /// Pulse length of SYNC signals in units of 10ns, `u16`.
+    ///
+    /// A value of `0` selects acknowledged mode, where the SYNC signal stays active until the
+    /// SubDevice application acknowledges it. Most ESCs load this value from EEPROM and make it
+    /// read-only over EtherCAT.
+    DcSyncPulseLength = 0x0982,
+
     /// ETG1000.6 Table 27 - Distributed Clock sync parameter, `u32`.
     ///
     /// AKA ETG1000.4 Table 61 DC user P4.
@@ -365,7 +391,9 @@ impl core::fmt::Display for SupportFlags {
 }
 
 /// SubDevice DC support status.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DcSupport {
     /// No support at all.
     None,
@@ -0,0 +1,473 @@
+//! Read-only discovery of a network that is owned by another MainDevice.
+
+use crate::{
+    al_control::AlControl,
+    al_status_code::AlStatusCode,
+    command::Command,
+    dc,
+    diagnostics::{link_errors, ErrorCounters, LinkErrors},
+    dl_status::DlStatus,
+    eeprom::types::SiiOwner,
+    error::{Error, Item},
+    fmt,
+    register::SupportFlags,
+    subdevice::{ports::Ports, SubDevice, SubDeviceRef},
+    DcSupport, MainDevice, RegisterAddress, SubDeviceIdentity, SubDeviceState, SubDeviceTopology,
+};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// Information held in the EtherCAT Slave Controller (ESC) registers `0x0000` to `0x0009`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EscInfo {
+    /// ESC type, e.g. `0x11` for an ET1100.
+    pub esc_type: u8,
+
+    /// ESC revision.
+    pub revision: u8,
+
+    /// ESC build.
+    pub build: u16,
+
+    /// The number of FMMUs the ESC supports.
+    pub fmmus: u8,
+
+    /// The number of SyncManagers the ESC supports.
+    pub sync_managers: u8,
+
+    /// Process data RAM size in KiB.
+    pub ram_size: u8,
+
+    /// Distributed clock support.
+    pub dc_support: DcSupport,
+}
+
+/// A SubDevice found by [`MainDevice::scan`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScannedSubDevice {
+    /// Position in the network, configured address and port information.
+    ///
+    /// The configured address is the one given to the SubDevice by the MainDevice that owns the
+    /// network, or `0` if it has not been configured. Propagation delays are not computed.
+    pub topology: SubDeviceTopology,
+
+    /// Configured station alias.
+    pub alias_address: u16,
+
+    /// Information about the SubDevice's EtherCAT controller.
+    pub esc: EscInfo,
+
+    /// The current AL state.
+    pub state: SubDeviceState,
+
+    /// The AL status code if the SubDevice's error flag is set.
+    pub status_code: Option<AlStatusCode>,
+
+    /// Whether the SubDevice's application is running and has taken control of its EtherCAT
+    /// controller.
+    pub pdi_operational: bool,
+
+    /// Link and port error counters.
+    pub error_counters: ErrorCounters,
+
+    /// The identity read from the SubDevice's EEPROM.
+    ///
+    /// This is `None` until [`NetworkScan::read_identities`] is called.
+    pub identity: Option<SubDeviceIdentity>,
+}
+
+/// A description of every SubDevice on the network, returned by [`MainDevice::scan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkScan<const MAX_SUBDEVICES: usize> {
+    /// All SubDevices in network order.
+    pub subdevices: heapless::Vec<ScannedSubDevice, MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> NetworkScan<MAX_SUBDEVICES> {
+    /// Attribute the scanned error counters to the cables between SubDevices.
+    ///
+    /// See [`link_errors`] for details.
+    pub fn link_errors(&self) -> heapless::Vec<LinkErrors, MAX_SUBDEVICES> {
+        let subdevices = self
+            .subdevices
+            .iter()
+            .map(|subdevice| (subdevice.topology, subdevice.error_counters))
+            .collect::<heapless::Vec<_, MAX_SUBDEVICES>>();
+
+        link_errors(&subdevices)
+    }
+
+    /// Read the vendor ID, product ID, revision and serial number of each SubDevice from its
+    /// EEPROM.
+    ///
+    /// Unlike [`MainDevice::scan`], this is not strictly read only: reading the EEPROM writes the
+    /// address and command to the SubDevice's SII registers. Process data and AL states are not
+    /// touched, but an EEPROM access by the MainDevice that owns the network at the same time
+    /// may fail or return wrong data.
+    ///
+    /// SubDevices without a configured address, or whose EEPROM is assigned to the SubDevice
+    /// application, are skipped and keep an identity of `None`.
+    pub async fn read_identities(&mut self, maindevice: &MainDevice<'_>) -> Result<(), Error> {
+        for subdevice in self.subdevices.iter_mut() {
+            let configured_address = subdevice.topology.configured_address;
+
+            if configured_address == 0 {
+                continue;
+            }
+
+            let subdevice_ref = SubDeviceRef::new(maindevice, configured_address, ());
+
+            let sii_config = subdevice_ref
+                .read(RegisterAddress::SiiConfig)
+                .receive::<[u8; 2]>(maindevice)
+                .await?;
+
+            if !eeprom_available(sii_config)? {
+                fmt::debug!(
+                    "SubDevice {:#06x} EEPROM is assigned to PDI, skipping identity",
+                    configured_address
+                );
+
+                continue;
+            }
+
+            subdevice.identity = Some(subdevice_ref.eeprom().identity().await?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a SubDevice's EEPROM can be read, given the value of its SII configuration register.
+fn eeprom_available(sii_config: [u8; 2]) -> Result<bool, Error> {
+    // Owner in bit 0, PDI access in progress in bit 8
+    Ok(
+        SiiOwner::unpack_from_slice(&[sii_config[0] & 0x01])? != SiiOwner::Pdi
+            && sii_config[1] & 0x01 == 0,
+    )
+}
+
+/// Rebuild the network tree from the registers read from every SubDevice, in network order.
+///
+/// Each item holds the configured and alias addresses, support flags, DL status and the DC receive
+/// times of ports 0 to 3 latched by the MainDevice that owns the network. Receive times are ignored
+/// for SubDevices without DC support.
+fn reconstruct_topology<'a, const MAX_SUBDEVICES: usize>(
+    registers: impl Iterator<Item = (u16, u16, SupportFlags, &'a DlStatus, [u32; 4])>,
+) -> Result<heapless::Vec<SubDevice, MAX_SUBDEVICES>, Error> {
+    let mut subdevices = registers
+        .enumerate()
+        .map(|(index, (address, alias, flags, dl_status, times))| {
+            let mut ports = Ports::new(
+                dl_status.link_port0,
+                dl_status.link_port3,
+                dl_status.link_port1,
+                dl_status.link_port2,
+            );
+
+            if flags.dc_supported {
+                ports.set_receive_times(times[0], times[3], times[1], times[2]);
+            }
+
+            SubDevice::from_registers(index as u16, address, alias, flags, ports)
+        })
+        .collect::<heapless::Vec<SubDevice, MAX_SUBDEVICES>>();
+
+    dc::assign_topology(&mut subdevices)?;
+
+    Ok(subdevices)
+}
+
+impl<'sto> MainDevice<'sto> {
+    /// Passively discover the SubDevices on a network that may be owned and operated by another
+    /// MainDevice, e.g. a PLC.
+    ///
+    /// Unlike [`init`](MainDevice::init), this method only sends `BRD` and `APRD` commands, so it
+    /// does not change any SubDevice states, addresses or configuration, and does not disturb
+    /// process data exchange. The network is described as found, including AL states, DL status,
+    /// port topology and error counters.
+    ///
+    /// Distributed clock receive times are not latched, as that requires a write. The receive
+    /// times latched by the owning MainDevice are used to find each SubDevice's entry port if
+    /// available.
+    ///
+    /// Use [`NetworkScan::read_identities`] to additionally read identities from SubDevice
+    /// EEPROMs.
+    pub async fn scan<const MAX_SUBDEVICES: usize>(
+        &self,
+    ) -> Result<NetworkScan<MAX_SUBDEVICES>, Error> {
+        let num_subdevices = self.count_subdevices().await?;
+
+        fmt::debug!("Scanning {} SubDevices", num_subdevices);
+
+        if usize::from(num_subdevices) > MAX_SUBDEVICES {
+            return Err(Error::Capacity(Item::SubDevice));
+        }
+
+        let mut esc = heapless::Vec::<([u8; 10], SupportFlags), MAX_SUBDEVICES>::new();
+
+        self.read_all(num_subdevices, RegisterAddress::Type, 10, |pdu| {
+            let raw = <[u8; 10]>::unpack_from_slice(pdu)?;
+            let flags = SupportFlags::unpack_from_slice(&raw[8..10])?;
+
+            // Can't overflow as capacity is checked above
+            let _ = esc.push((raw, flags));
+
+            Ok(())
+        })
+        .await?;
+
+        let mut addresses = heapless::Vec::<[u16; 2], MAX_SUBDEVICES>::new();
+
+        self.read_all(
+            num_subdevices,
+            RegisterAddress::ConfiguredStationAddress,
+            4,
+            |pdu| {
+                let _ = addresses.push(<[u16; 2]>::unpack_from_slice(pdu)?);
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        let mut dl_status = heapless::Vec::<DlStatus, MAX_SUBDEVICES>::new();
+
+        self.read_all(
+            num_subdevices,
+            RegisterAddress::DlStatus,
+            DlStatus::PACKED_LEN,
+            |pdu| {
+                let _ = dl_status.push(DlStatus::unpack_from_slice(pdu)?);
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        let mut receive_times = heapless::Vec::<[u32; 4], MAX_SUBDEVICES>::new();
+
+        // SubDevices without DC support may not respond to reads of the DC registers
+        self.batch_pdus(
+            (0..num_subdevices).map(|position| {
+                Command::aprd(position, RegisterAddress::DcTimePort0.into()).into()
+            }),
+            16,
+            |_, pdu| {
+                let times = if pdu.working_counter == 1 {
+                    <[u32; 4]>::unpack_from_slice(&pdu)?
+                } else {
+                    [0; 4]
+                };
+
+                let _ = receive_times.push(times);
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        // AL status followed by a reserved word, then the AL status code
+        let mut al_status = heapless::Vec::<(AlControl, AlStatusCode), MAX_SUBDEVICES>::new();
+
+        self.read_all(num_subdevices, RegisterAddress::AlStatus, 6, |pdu| {
+            let status = AlControl::unpack_from_slice(pdu)?;
+            let code = AlStatusCode::unpack_from_slice(pdu.get(4..).unwrap_or_default())?;
+
+            let _ = al_status.push((status, code));
+
+            Ok(())
+        })
+        .await?;
+
+        let mut error_counters = heapless::Vec::<ErrorCounters, MAX_SUBDEVICES>::new();
+
+        self.read_all(
+            num_subdevices,
+            RegisterAddress::RxErrorCounter,
+            ErrorCounters::PACKED_LEN,
+            |pdu| {
+                let _ = error_counters.push(ErrorCounters::unpack_from_slice(pdu)?);
+
+                Ok(())
+            },
+        )
+        .await?;
+
+        let subdevices = reconstruct_topology::<MAX_SUBDEVICES>(
+            esc.iter()
+                .zip(addresses.iter())
+                .zip(dl_status.iter().zip(receive_times.iter()))
+                .map(|(((_, flags), [address, alias]), (dl_status, times))| {
+                    (*address, *alias, flags.clone(), dl_status, *times)
+                }),
+        )?;
+
+        let subdevices = subdevices
+            .iter()
+            .zip(esc)
+            .zip(al_status)
+            .zip(dl_status.iter().zip(error_counters))
+            .map(
+                |(((subdevice, (raw, flags)), (status, code)), (dl_status, error_counters))| {
+                    ScannedSubDevice {
+                        topology: subdevice.topology(),
+                        alias_address: subdevice.alias_address,
+                        esc: EscInfo {
+                            esc_type: raw[0],
+                            revision: raw[1],
+                            build: u16::from_le_bytes([raw[2], raw[3]]),
+                            fmmus: raw[4],
+                            sync_managers: raw[5],
+                            ram_size: raw[6],
+                            dc_support: flags.dc_support(),
+                        },
+                        state: status.state,
+                        status_code: status.error.then_some(code),
+                        pdi_operational: dl_status.pdi_operational,
+                        error_counters,
+                        identity: None,
+                    }
+                },
+            )
+            .collect();
+
+        Ok(NetworkScan { subdevices })
+    }
+
+    /// Read the same register from every SubDevice by position, in as few frames as possible.
+    pub(crate) async fn read_all(
+        &self,
+        num_subdevices: u16,
+        register: RegisterAddress,
+        len: usize,
+        mut on_response: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.batch_pdus(
+            (0..num_subdevices).map(|position| Command::aprd(position, register.into()).into()),
+            len as u16,
+            |_, pdu| {
+                if pdu.working_counter != 1 {
+                    return Err(Error::WorkingCounter {
+                        expected: 1,
+                        received: pdu.working_counter,
+                    });
+                }
+
+                on_response(&pdu)
+            },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Topology;
+
+    fn dl_status(port0: bool, port1: bool, port2: bool, port3: bool) -> DlStatus {
+        let links = u8::from(port0) << 4
+            | u8::from(port1) << 5
+            | u8::from(port2) << 6
+            | u8::from(port3) << 7;
+
+        DlStatus::unpack_from_slice(&[links, 0x00]).expect("DL status")
+    }
+
+    fn dc() -> SupportFlags {
+        SupportFlags {
+            dc_supported: true,
+            ..SupportFlags::default()
+        }
+    }
+
+    fn topologies(
+        registers: &[(SupportFlags, DlStatus, [u32; 4])],
+    ) -> heapless::Vec<SubDeviceTopology, 8> {
+        reconstruct_topology::<8>(registers.iter().enumerate().map(
+            |(index, (flags, dl_status, times))| {
+                (0x1000 + index as u16, 0, flags.clone(), dl_status, *times)
+            },
+        ))
+        .expect("topology")
+        .iter()
+        .map(SubDevice::topology)
+        .collect()
+    }
+
+    #[test]
+    fn fork() {
+        // EK1100 with one module on port 3 followed by an EL module, and another SubDevice on port 1
+        let topologies = topologies(&[
+            (dc(), dl_status(true, true, false, true), [100, 900, 0, 200]),
+            (dc(), dl_status(true, true, false, false), [300, 500, 0, 0]),
+            (dc(), dl_status(true, false, false, false), [400, 0, 0, 0]),
+            (dc(), dl_status(true, false, false, false), [1000, 0, 0, 0]),
+        ]);
+
+        let tree = topologies
+            .iter()
+            .map(|t| (t.index, t.parent_index, t.parent_port, t.topology))
+            .collect::<heapless::Vec<_, 8>>();
+
+        assert_eq!(
+            tree,
+            [
+                (0, None, None, Topology::Fork),
+                (1, Some(0), Some(3), Topology::Passthrough),
+                (2, Some(1), Some(1), Topology::LineEnd),
+                (3, Some(0), Some(1), Topology::LineEnd),
+            ]
+        );
+
+        assert!(topologies.iter().all(|t| t.entry_port == 0));
+        assert_eq!(topologies[0].configured_address, 0x1000);
+        assert_eq!(topologies[3].configured_address, 0x1003);
+    }
+
+    #[test]
+    fn entry_port_from_receive_times() {
+        // The second SubDevice is cabled backwards, so sees traffic on port 1 first
+        let topologies = topologies(&[
+            (dc(), dl_status(true, true, false, false), [100, 600, 0, 0]),
+            (dc(), dl_status(true, true, false, false), [500, 200, 0, 0]),
+            (dc(), dl_status(true, false, false, false), [300, 0, 0, 0]),
+        ]);
+
+        assert_eq!(topologies[0].entry_port, 0);
+        assert_eq!(topologies[1].entry_port, 1);
+        assert_eq!(topologies[1].parent_index, Some(0));
+        assert_eq!(topologies[2].parent_index, Some(1));
+    }
+
+    #[test]
+    fn receive_times_ignored_without_dc() {
+        // Whatever is in the DC registers of a SubDevice without DC support is not a receive time
+        let topologies = topologies(&[
+            (dc(), dl_status(true, true, false, false), [100, 600, 0, 0]),
+            (
+                SupportFlags::default(),
+                dl_status(true, true, false, false),
+                [500, 200, 0, 0],
+            ),
+        ]);
+
+        assert_eq!(topologies[1].entry_port, 0);
+        assert_eq!(topologies[1].dc_receive_time, 0);
+        assert!(!topologies[1].dc_supported);
+    }
+
+    #[test]
+    fn eeprom_owner() {
+        assert_eq!(eeprom_available([0x00, 0x00]), Ok(true));
+        // Other bits don't matter
+        assert_eq!(eeprom_available([0x02, 0x02]), Ok(true));
+        // Assigned to PDI
+        assert_eq!(eeprom_available([0x01, 0x00]), Ok(false));
+        // PDI access in progress
+        assert_eq!(eeprom_available([0x00, 0x01]), Ok(false));
+    }
+}
@@ -2,10 +2,16 @@
 
 #[cfg(target_os = "linux")]
 mod io_uring;
+#[cfg(target_os = "linux")]
+mod redundancy;
+#[cfg(target_os = "linux")]
+mod tpacket;
 #[cfg(unix)]
 mod unix;
 #[cfg(target_os = "windows")]
 mod windows;
+#[cfg(target_os = "linux")]
+mod xdp;
 
 use std::{
     sync::Arc,
@@ -20,6 +26,12 @@ pub use unix::{ethercat_now, tx_rx_task};
 // io_uring is Linux-only
 #[cfg(target_os = "linux")]
 pub use io_uring::tx_rx_task_io_uring;
+#[cfg(target_os = "linux")]
+pub use redundancy::{tx_rx_task_redundant, RedundancyConfig, RedundancyStatus, RingState};
+#[cfg(target_os = "linux")]
+pub use tpacket::tx_rx_task_tpacket;
+#[cfg(target_os = "linux")]
+pub use xdp::{tx_rx_task_xdp, XdpConfig};
 
 struct ParkSignal {
     current_thread: Thread,
@@ -0,0 +1,617 @@
+//! A TX/RX driver for cable redundancy, sending every frame out of two network interfaces.
+
+use super::unix::RawSocketDesc;
+use crate::{
+    error::{Error, PduError},
+    ethernet::{EthernetAddress, EthernetFrame, ETHERNET_HEADER_LEN},
+    fmt,
+    pdu_loop::{PduRx, PduTx},
+    LEN_MASK, MASTER_ADDR,
+};
+use async_io::{Async, Timer};
+use core::{future::Future, pin::Pin, task::Poll};
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Source address of frames sent out of the secondary interface.
+const SECONDARY_ADDR: EthernetAddress = EthernetAddress([0x10, 0x10, 0x10, 0x10, 0x10, 0x11]);
+
+/// The U/L bit of the first octet of a MAC address, set by the first SubDevice that processes a
+/// frame.
+const LOCAL_BIT: u8 = 0b10;
+
+/// Offset of the first PDU's index in an Ethernet frame, used to pair up copies of the same frame.
+const FIRST_PDU_INDEX: usize = ETHERNET_HEADER_LEN + 2 + 1;
+
+/// Length of an EtherCAT PDU header.
+const PDU_HEADER_LEN: usize = 10;
+
+/// Configuration for [`tx_rx_task_redundant`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RedundancyConfig {
+    /// How long to wait for the second copy of a frame once the first has arrived.
+    ///
+    /// Both copies normally arrive within a few microseconds of each other, whether the ring is
+    /// intact or broken. This timeout only expires if one copy is lost, e.g. because an interface
+    /// has no link. After a timeout, copies from that interface are only waited for if the other
+    /// copy was not processed by any SubDevice, until the interface returns a frame again.
+    ///
+    /// Defaults to 1ms.
+    pub merge_timeout: Duration,
+}
+
+impl Default for RedundancyConfig {
+    fn default() -> Self {
+        Self {
+            merge_timeout: Duration::from_millis(1),
+        }
+    }
+}
+
+/// The state of a ring network as seen by [`tx_rx_task_redundant`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RingState {
+    /// No frames have been returned yet.
+    Unknown,
+    /// Frames sent from the primary interface are received on the secondary interface.
+    Intact,
+    /// Frames are returned to the interface they were sent from.
+    ///
+    /// [`MainDevice::ring_break`](crate::MainDevice::ring_break) can be used to find where the
+    /// ring is broken.
+    Broken,
+}
+
+impl From<u8> for RingState {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Intact,
+            2 => Self::Broken,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A handle to read the ring state detected by a running [`tx_rx_task_redundant`].
+#[derive(Debug, Clone)]
+pub struct RedundancyStatus {
+    state: Arc<AtomicU8>,
+}
+
+impl RedundancyStatus {
+    /// The ring state seen in the most recently received frame.
+    pub fn ring_state(&self) -> RingState {
+        RingState::from(self.state.load(Ordering::Relaxed))
+    }
+
+    fn update(&self, state: RingState) {
+        let previous = RingState::from(self.state.swap(state as u8, Ordering::Relaxed));
+
+        if previous != state {
+            match state {
+                RingState::Broken => fmt::warn!("Ring is broken"),
+                _ => fmt::info!("Ring is {:?}", state),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Port {
+    Primary = 0,
+    Secondary = 1,
+}
+
+impl Port {
+    const fn source_addr(self) -> EthernetAddress {
+        match self {
+            Port::Primary => MASTER_ADDR,
+            Port::Secondary => SECONDARY_ADDR,
+        }
+    }
+
+    const fn other(self) -> Self {
+        match self {
+            Port::Primary => Port::Secondary,
+            Port::Secondary => Port::Primary,
+        }
+    }
+
+    /// The interface a received frame was originally sent from.
+    fn of_origin(src: EthernetAddress) -> Option<Self> {
+        let mut addr = src;
+
+        addr.0[0] &= !LOCAL_BIT;
+
+        [Port::Primary, Port::Secondary]
+            .into_iter()
+            .find(|port| port.source_addr() == addr)
+    }
+}
+
+/// A frame that has been sent and is waiting for its copies to return.
+#[derive(Default)]
+struct Pending {
+    /// The frame as sent from the primary interface.
+    sent: Vec<u8>,
+    /// The first copy to be received.
+    received: Vec<u8>,
+    /// Whether a copy is still expected from each interface.
+    expected: [bool; 2],
+    /// Interfaces that were not returning frames when this one was sent. Their copies are only
+    /// waited for if the first copy to arrive was not processed by any SubDevice.
+    optional: [bool; 2],
+    /// When to stop waiting for the second copy, set once the first copy arrives.
+    deadline: Option<Instant>,
+}
+
+struct RedundantTxRxFut<'a> {
+    sockets: [Async<RawSocketDesc>; 2],
+    buf: Vec<u8>,
+    secondary_buf: Vec<u8>,
+    tx: PduTx<'a>,
+    rx: PduRx<'a>,
+    /// Pending frames, indexed by the index of their first PDU.
+    pending: Vec<Pending>,
+    /// Interfaces whose last expected copy never arrived.
+    missing: [bool; 2],
+    timer: Timer,
+    config: RedundancyConfig,
+    status: RedundancyStatus,
+}
+
+impl RedundantTxRxFut<'_> {
+    fn send(&mut self, ctx: &mut core::task::Context<'_>, data: &[u8]) -> Result<usize, Error> {
+        let index = *data.get(FIRST_PDU_INDEX).ok_or(Error::Internal)?;
+
+        self.secondary_buf.clear();
+        self.secondary_buf.extend_from_slice(data);
+        self.secondary_buf[6..12].copy_from_slice(SECONDARY_ADDR.as_bytes());
+
+        let mut sent = [false; 2];
+
+        for (port, frame) in [
+            (Port::Primary, data),
+            (Port::Secondary, self.secondary_buf.as_slice()),
+        ] {
+            sent[port as usize] =
+                match Pin::new(&mut self.sockets[port as usize]).poll_write(ctx, frame) {
+                    Poll::Ready(Ok(bytes_written)) if bytes_written == frame.len() => true,
+                    Poll::Ready(Ok(bytes_written)) => {
+                        fmt::error!(
+                            "Only wrote {} of {} bytes to {:?} interface",
+                            bytes_written,
+                            frame.len(),
+                            port
+                        );
+
+                        false
+                    }
+                    Poll::Ready(Err(e)) => {
+                        fmt::error!("Send PDU on {:?} interface failed: {}", port, e);
+
+                        false
+                    }
+                    Poll::Pending => false,
+                };
+        }
+
+        if sent == [false; 2] {
+            return Err(Error::SendFrame);
+        }
+
+        let pending = &mut self.pending[usize::from(index)];
+
+        pending.sent.clear();
+        pending.sent.extend_from_slice(data);
+        pending.expected = sent;
+        pending.deadline = None;
+
+        // If both interfaces have dropped frames before, wait for whichever comes back
+        pending.optional = if self.missing == [true; 2] {
+            [false; 2]
+        } else {
+            self.missing
+        };
+
+        Ok(data.len())
+    }
+
+    fn receive(&mut self, port: Port, len: usize) -> Result<(), Error> {
+        let packet = &self.buf[0..len];
+
+        let Ok(frame) = EthernetFrame::new_checked(packet) else {
+            fmt::trace!("Ignore short frame");
+
+            return Ok(());
+        };
+
+        // Frames sent by this interface are also received by it
+        if frame.src_addr() == port.source_addr() {
+            return Ok(());
+        }
+
+        let Some(origin) = Port::of_origin(frame.src_addr()) else {
+            fmt::trace!("Ignore frame from {}", frame.src_addr());
+
+            return Ok(());
+        };
+
+        self.missing[origin as usize] = false;
+
+        match (origin, port) {
+            (Port::Primary, Port::Secondary) => self.status.update(RingState::Intact),
+            // A frame from the secondary interface passes through an intact ring unprocessed
+            (Port::Secondary, Port::Primary) => (),
+            _ => self.status.update(RingState::Broken),
+        }
+
+        let Some(index) = packet.get(FIRST_PDU_INDEX) else {
+            return Ok(());
+        };
+
+        let pending = &mut self.pending[usize::from(*index)];
+
+        // Either a late copy of a frame that was already delivered, or not a frame we sent
+        if !pending.expected[origin as usize] {
+            return Ok(());
+        }
+
+        pending.expected[origin as usize] = false;
+
+        if pending.deadline.is_none() {
+            let other = origin.other() as usize;
+
+            // The U/L bit is only set if a SubDevice processed the frame
+            let processed = frame.src_addr().0[0] & LOCAL_BIT != 0;
+
+            pending.received.clear();
+            pending.received.extend_from_slice(packet);
+
+            if pending.expected[other] && (!pending.optional[other] || !processed) {
+                pending.deadline = Some(Instant::now() + self.config.merge_timeout);
+
+                return Ok(());
+            }
+        } else if let Err(e) = merge_frames(&pending.sent, &mut pending.received, packet) {
+            fmt::error!("Failed to merge frame copies, using first copy: {}", e);
+        }
+
+        pending.expected = [false; 2];
+        pending.deadline = None;
+
+        deliver(&mut self.rx, &mut pending.received)
+    }
+
+    /// Deliver single copies of frames whose second copy did not arrive in time, returning when
+    /// the timer must next fire.
+    fn expire(&mut self) -> Result<Option<Instant>, Error> {
+        let now = Instant::now();
+        let mut next = None;
+
+        for pending in self.pending.iter_mut() {
+            match pending.deadline {
+                Some(deadline) if deadline <= now => {
+                    for (port, expected) in pending.expected.iter_mut().enumerate() {
+                        if *expected && !self.missing[port] {
+                            fmt::warn!("No frames returned from interface {}", port);
+
+                            self.missing[port] = true;
+                        }
+
+                        *expected = false;
+                    }
+
+                    pending.deadline = None;
+
+                    deliver(&mut self.rx, &mut pending.received)?;
+                }
+                Some(deadline) => {
+                    next = Some(next.map_or(deadline, |next: Instant| next.min(deadline)));
+                }
+                None => (),
+            }
+        }
+
+        Ok(next)
+    }
+}
+
+/// Pass a returned frame to the PDU loop.
+fn deliver(rx: &mut PduRx<'_>, frame: &mut [u8]) -> Result<(), Error> {
+    // An unprocessed copy from the primary interface would otherwise be ignored as our own echo
+    frame[6] |= LOCAL_BIT;
+
+    loop {
+        match rx.receive_frame(frame) {
+            // Wait for frame RX future waker to be registered
+            Err(Error::Pdu(PduError::NoWaker)) => thread::yield_now(),
+            Err(e) => {
+                fmt::error!("Failed to receive frame: {}", e);
+
+                return Err(Error::ReceiveFrame);
+            }
+            Ok(_) => return Ok(()),
+        }
+    }
+}
+
+/// Merge `other` into `frame`, where both are copies of the Ethernet frame `sent` that returned
+/// from opposite sides of a broken ring.
+///
+/// The SubDevices on each side of the break only process one of the copies, so for every PDU,
+/// data changed by either side is kept and the working counters are added together. Bytes changed
+/// by both sides, e.g. by a broadcast read, are ORed together.
+fn merge_frames(sent: &[u8], frame: &mut [u8], other: &[u8]) -> Result<(), Error> {
+    // Skip Ethernet and EtherCAT frame headers
+    let mut pos = ETHERNET_HEADER_LEN + 2;
+
+    loop {
+        let header = sent
+            .get(pos..pos + PDU_HEADER_LEN)
+            .ok_or(PduError::Decode)?;
+
+        let flags = u16::from_le_bytes([header[6], header[7]]);
+
+        let data = pos + PDU_HEADER_LEN..pos + PDU_HEADER_LEN + usize::from(flags & LEN_MASK);
+        let wkc = data.end..data.end + 2;
+
+        if [sent.len(), frame.len(), other.len()]
+            .iter()
+            .any(|len| *len < wkc.end)
+        {
+            return Err(PduError::Decode.into());
+        }
+
+        for i in data {
+            if frame[i] == sent[i] {
+                frame[i] = other[i];
+            } else if other[i] != sent[i] {
+                frame[i] |= other[i];
+            }
+        }
+
+        let sum = u16::from_le_bytes([frame[wkc.start], frame[wkc.start + 1]])
+            .wrapping_add(u16::from_le_bytes([other[wkc.start], other[wkc.start + 1]]));
+
+        frame[wkc.clone()].copy_from_slice(&sum.to_le_bytes());
+
+        // More PDUs follow
+        if flags & (1 << 15) == 0 {
+            break Ok(());
+        }
+
+        pos = wkc.end;
+    }
+}
+
+impl Future for RedundantTxRxFut<'_> {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, ctx: &mut core::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        // Re-register waker to make sure this future is polled again
+        this.tx.replace_waker(ctx.waker());
+
+        while let Some(frame) = this.tx.next_sendable_frame() {
+            if let Err(e) = frame.send_blocking(|data| this.send(ctx, data)) {
+                fmt::error!("Send PDU failed: {}", e);
+
+                return Poll::Ready(Err(e));
+            }
+        }
+
+        for port in [Port::Primary, Port::Secondary] {
+            match Pin::new(&mut this.sockets[port as usize]).poll_read(ctx, &mut this.buf) {
+                Poll::Ready(Ok(n)) => {
+                    // Wake again in case there are more frames to consume
+                    ctx.waker().wake_by_ref();
+
+                    if let Err(e) = this.receive(port, n) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Ready(Err(e)) => {
+                    fmt::error!("Receive PDU on {:?} interface failed: {}", port, e);
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        match this.expire() {
+            Ok(Some(deadline)) => {
+                this.timer.set_at(deadline);
+
+                if Pin::new(&mut this.timer).poll(ctx).is_ready() {
+                    ctx.waker().wake_by_ref();
+                }
+            }
+            Ok(None) => (),
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Spawn a TX and RX task that sends every frame out of two network interfaces connected to either
+/// end of a ring of SubDevices.
+///
+/// The `primary` interface is cabled to port 0 of the first SubDevice, and the `secondary`
+/// interface to port 1 of the last SubDevice. While the ring is intact, frames from the primary
+/// interface travel around the whole ring and the copy sent from the secondary interface passes
+/// through unprocessed. When a cable breaks, the SubDevices on each side of the break process one
+/// copy each, which are merged back together by adding their working counters and keeping any data
+/// changed by either side before they are passed to the PDU loop.
+///
+/// The returned [`RedundancyStatus`] reports whether the ring is currently intact. Use
+/// [`MainDevice::ring_break`](crate::MainDevice::ring_break) to find the location of a break.
+///
+/// Commands that are addressed by position, e.g. during [`MainDevice::init`](crate::MainDevice),
+/// count positions from the break on the secondary side, so the ring must be intact during
+/// initialisation. Distributed clock synchronisation frames may also give wrong results with a
+/// broken ring.
+///
+/// # Testing
+///
+/// The driver can be tested without hardware using two veth pairs, e.g.
+///
+/// ```bash
+/// sudo ip link add ecat0 type veth peer name ring0
+/// sudo ip link add ecat1 type veth peer name ring1
+/// ```
+///
+/// with `ecat0` and `ecat1` passed to this function and a simulated ring forwarding frames between
+/// `ring0` and `ring1`. See `tests/redundancy-veth.rs` in the EtherCrab repository for an example.
+pub fn tx_rx_task_redundant<'sto>(
+    primary: &str,
+    secondary: &str,
+    pdu_tx: PduTx<'sto>,
+    pdu_rx: PduRx<'sto>,
+    config: RedundancyConfig,
+) -> Result<
+    (
+        impl Future<Output = Result<(), Error>> + 'sto,
+        RedundancyStatus,
+    ),
+    io::Error,
+> {
+    let mut primary_socket = RawSocketDesc::new(primary)?;
+    let mut secondary_socket = RawSocketDesc::new(secondary)?;
+
+    let mtu = primary_socket
+        .interface_mtu()?
+        .max(secondary_socket.interface_mtu()?);
+
+    fmt::debug!(
+        "Opening {} and {} with MTU {} for redundancy",
+        primary,
+        secondary,
+        mtu
+    );
+
+    let status = RedundancyStatus {
+        state: Arc::new(AtomicU8::new(RingState::Unknown as u8)),
+    };
+
+    let task = RedundantTxRxFut {
+        sockets: [Async::new(primary_socket)?, Async::new(secondary_socket)?],
+        buf: vec![0; mtu + ETHERNET_HEADER_LEN],
+        secondary_buf: Vec::with_capacity(mtu + ETHERNET_HEADER_LEN),
+        tx: pdu_tx,
+        rx: pdu_rx,
+        pending: (0..=u8::MAX).map(|_| Pending::default()).collect(),
+        missing: [false; 2],
+        timer: Timer::never(),
+        config,
+        status: status.clone(),
+    };
+
+    Ok((task, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Ethernet frame with a single PDU of `data`.
+    fn frame(data: &[u8], wkc: u16, more: Option<&[u8]>) -> Vec<u8> {
+        let mut frame = vec![0u8; ETHERNET_HEADER_LEN + 2];
+
+        let mut pdu = |data: &[u8], wkc: u16, more_follows: bool| {
+            let flags = data.len() as u16 | u16::from(more_follows) << 15;
+
+            frame.extend_from_slice(&[0x07, 0x01, 0, 0, 0, 0]);
+            frame.extend_from_slice(&flags.to_le_bytes());
+            frame.extend_from_slice(&[0, 0]);
+            frame.extend_from_slice(data);
+            frame.extend_from_slice(&wkc.to_le_bytes());
+        };
+
+        pdu(data, wkc, more.is_some());
+
+        if let Some(more) = more {
+            pdu(more, wkc, false);
+        }
+
+        frame
+    }
+
+    #[test]
+    fn merge_broken_ring() {
+        let sent = frame(&[0, 0, 0, 0], 0, None);
+        let mut primary = frame(&[0xaa, 0, 0xbb, 0], 2, None);
+        let secondary = frame(&[0, 0xcc, 0, 0x01], 1, None);
+
+        merge_frames(&sent, &mut primary, &secondary).expect("merge");
+
+        assert_eq!(primary, frame(&[0xaa, 0xcc, 0xbb, 0x01], 3, None));
+    }
+
+    #[test]
+    fn merge_broadcast_read() {
+        let sent = frame(&[0], 0, None);
+        let mut primary = frame(&[0b001], 1, None);
+        let secondary = frame(&[0b110], 2, None);
+
+        merge_frames(&sent, &mut primary, &secondary).expect("merge");
+
+        assert_eq!(primary, frame(&[0b111], 3, None));
+    }
+
+    #[test]
+    fn merge_intact_ring() {
+        let sent = frame(&[1, 2, 3], 0, None);
+        let mut primary = frame(&[4, 5, 6], 3, None);
+        let unprocessed = sent.clone();
+
+        merge_frames(&sent, &mut primary, &unprocessed).expect("merge");
+
+        assert_eq!(primary, frame(&[4, 5, 6], 3, None));
+    }
+
+    #[test]
+    fn merge_multiple_pdus() {
+        let sent = frame(&[0, 0], 0, Some(&[0]));
+        let mut primary = frame(&[0x11, 0], 1, Some(&[0]));
+        let secondary = frame(&[0, 0x22], 1, Some(&[0x33]));
+
+        merge_frames(&sent, &mut primary, &secondary).expect("merge");
+
+        assert_eq!(primary, frame(&[0x11, 0x22], 2, Some(&[0x33])));
+    }
+
+    #[test]
+    fn merge_truncated() {
+        let sent = frame(&[0, 0, 0, 0], 0, None);
+        let mut primary = frame(&[0, 0, 0, 0], 0, None);
+        let short = &sent[0..sent.len() - 3];
+
+        assert_eq!(
+            merge_frames(&sent, &mut primary, short),
+            Err(Error::Pdu(PduError::Decode))
+        );
+    }
+
+    #[test]
+    fn origin() {
+        let mut processed = MASTER_ADDR;
+        processed.0[0] |= LOCAL_BIT;
+
+        assert_eq!(Port::of_origin(processed), Some(Port::Primary));
+        assert_eq!(Port::of_origin(SECONDARY_ADDR), Some(Port::Secondary));
+        assert_eq!(Port::of_origin(EthernetAddress([0xff; 6])), None);
+    }
+}
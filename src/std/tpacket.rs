@@ -0,0 +1,442 @@
+//! TX/RX using a raw socket with `TPACKET_V3` memory mapped receive and transmit rings.
+//!
+//! The kernel writes received frames into blocks of a ring buffer shared with this process, and
+//! hands over a whole block at a time. All frames in a block are processed without any further
+//! syscalls, and without copying them out of the ring first. Frames to send are copied into a
+//! second ring and handed to the kernel with a single syscall for each batch.
+
+use crate::{
+    error::{Error, PduError},
+    fmt,
+    std::unix::{cvt, EventFdSignal, Mmap, RawSocketDesc},
+    PduRx, PduTx,
+};
+use core::{
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+    task::Waker,
+    time::Duration,
+};
+use std::{io, mem, os::fd::AsRawFd, sync::Arc, thread, time::Instant};
+
+// `linux/if_packet.h`, defined here to not depend on a recent `libc`.
+const SOL_PACKET: libc::c_int = 263;
+const PACKET_RX_RING: libc::c_int = 5;
+const PACKET_VERSION: libc::c_int = 10;
+const PACKET_TX_RING: libc::c_int = 13;
+const TPACKET_V3: libc::c_int = 2;
+const TP_STATUS_KERNEL: u32 = 0;
+const TP_STATUS_USER: u32 = 1 << 0;
+const TP_STATUS_AVAILABLE: u32 = 0;
+const TP_STATUS_SEND_REQUEST: u32 = 1 << 0;
+const TP_STATUS_WRONG_FORMAT: u32 = 1 << 2;
+
+/// Size of each ring block. Must be a multiple of the page size and of [`FRAME_SIZE`].
+const BLOCK_SIZE: u32 = 1 << 16;
+
+/// Number of blocks in the receive ring.
+const RX_BLOCK_COUNT: u32 = 16;
+
+/// Number of blocks in the transmit ring.
+const TX_BLOCK_COUNT: u32 = 4;
+
+/// Maximum size of a single frame, including the ring's per-frame header.
+const FRAME_SIZE: u32 = 2048;
+
+const TX_FRAME_COUNT: u32 = BLOCK_SIZE / FRAME_SIZE * TX_BLOCK_COUNT;
+
+/// Time in milliseconds after which the kernel hands over a block that is not yet full. This is the
+/// shortest timeout the kernel supports.
+const BLOCK_TIMEOUT_MS: u32 = 1;
+
+/// `struct tpacket_req3`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct TpacketReq3 {
+    block_size: u32,
+    block_nr: u32,
+    frame_size: u32,
+    frame_nr: u32,
+    retire_blk_tov: u32,
+    sizeof_priv: u32,
+    feature_req_word: u32,
+}
+
+// Field offsets in `struct tpacket_block_desc`, which starts each receive block.
+const BLOCK_STATUS: usize = 8;
+const BLOCK_NUM_PKTS: usize = 12;
+const BLOCK_LEN: usize = 20;
+
+/// Offset of the first frame in a receive block, `BLK_PLUS_PRIV(0)` in the kernel.
+const BLOCK_FIRST_PKT: usize = 48;
+
+// Field offsets in `struct tpacket3_hdr`, which precedes each frame.
+const PKT_NEXT_OFFSET: usize = 0;
+const PKT_SNAPLEN: usize = 12;
+const PKT_LEN: usize = 16;
+const PKT_STATUS: usize = 20;
+const PKT_MAC: usize = 24;
+const PKT_HDR_LEN: usize = 48;
+
+/// Offset of the frame data in a transmit slot, `TPACKET3_HDRLEN - sizeof(struct sockaddr_ll)`.
+const TX_DATA: usize = PKT_HDR_LEN;
+
+/// Create a blocking TX/RX loop using a raw socket with `TPACKET_V3` memory mapped rings.
+///
+/// Frames are received in batches directly from a ring buffer shared with the kernel, without a
+/// `read` syscall or copying them out of the ring first. Frames to send are copied into a transmit
+/// ring, and all frames that are ready at the same time are sent with one syscall. No kernel
+/// configuration or extra privileges are required beyond those needed for a raw socket. The
+/// transmit ring requires Linux 4.11 or newer.
+///
+/// The kernel only wakes this task once a block of received frames is full, or after a 1ms
+/// timeout. As EtherCAT traffic rarely fills a block, this task instead polls the block the kernel
+/// is currently filling while any responses are outstanding, for up to 1ms after the last frame is
+/// sent. Responses are processed as soon as they arrive, at the cost of keeping a CPU core busy
+/// while waiting for them. The `veth` benchmark compares the round trip time of this backend with
+/// [`tx_rx_task`](crate::std::tx_rx_task) and
+/// [`tx_rx_task_io_uring`](crate::std::tx_rx_task_io_uring).
+///
+/// This function is only available on `linux` targets.
+pub fn tx_rx_task_tpacket<'sto>(
+    interface: &str,
+    mut pdu_tx: PduTx<'sto>,
+    mut pdu_rx: PduRx<'sto>,
+) -> Result<(), io::Error> {
+    let mut socket = RawSocketDesc::new(interface)?;
+
+    let mtu = socket.interface_mtu()?;
+
+    fmt::debug!(
+        "Opening {} with MTU {}, blocking, using TPACKET_V3 rings",
+        interface,
+        mtu
+    );
+
+    let rings = Rings::new(&socket)?;
+
+    let signal = Arc::new(EventFdSignal::new()?);
+    let waker = Waker::from(Arc::clone(&signal));
+
+    let mut rx = RxPosition::default();
+    let mut tx_frame = 0;
+
+    // Frames sent that have not had a response yet, and when to stop polling for them.
+    let mut outstanding = 0usize;
+    let mut poll_until = Instant::now();
+
+    loop {
+        pdu_tx.replace_waker(&waker);
+
+        let mut sent = 0;
+
+        while let Some(frame) = pdu_tx.next_sendable_frame() {
+            let idx = frame.index();
+
+            frame
+                .send_blocking(|data| {
+                    fmt::trace!("Send frame {:#04x}, {} bytes", idx, data.len());
+
+                    // The slot is still in use if the kernel hasn't sent the frame from the last
+                    // time round the ring yet.
+                    while !rings.queue(tx_frame, data)? {
+                        rings.flush(&socket)?;
+
+                        thread::yield_now();
+                    }
+
+                    tx_frame = (tx_frame + 1) % TX_FRAME_COUNT;
+
+                    Ok(data.len())
+                })
+                .map_err(io::Error::other)?;
+
+            sent += 1;
+        }
+
+        if sent > 0 {
+            rings.flush(&socket).map_err(io::Error::other)?;
+
+            outstanding += sent;
+            poll_until = Instant::now() + Duration::from_millis(u64::from(BLOCK_TIMEOUT_MS));
+        }
+
+        let mut received = 0;
+
+        while let Some(frame) = rings.next_frame(&mut rx) {
+            loop {
+                match pdu_rx.receive_frame(frame) {
+                    Ok(_) => break,
+                    Err(Error::Pdu(PduError::NoWaker)) => {
+                        fmt::trace!("No waker for received frame, retrying receive");
+
+                        thread::yield_now();
+                    }
+                    Err(e) => return Err(io::Error::other(e)),
+                }
+            }
+
+            received += 1;
+        }
+
+        outstanding = outstanding.saturating_sub(received);
+
+        if sent == 0 && received == 0 {
+            // The kernel doesn't wake this task until the block is retired, so keep checking the
+            // open block while responses are expected.
+            if outstanding > 0 && Instant::now() < poll_until {
+                thread::yield_now();
+
+                continue;
+            }
+
+            outstanding = 0;
+
+            fmt::trace!("Waiting for frames to send or receive");
+
+            // A wakeup between `replace_waker` above and here is kept by the eventfd, so returns
+            // from this call immediately.
+            signal.wait(socket.as_raw_fd())?;
+        }
+    }
+}
+
+/// The next frame to read from the receive ring.
+#[derive(Debug)]
+struct RxPosition {
+    block: u32,
+    /// Offset of the next frame in the block.
+    offset: usize,
+    /// Number of frames already read from the block.
+    read: u32,
+}
+
+impl Default for RxPosition {
+    fn default() -> Self {
+        Self {
+            block: 0,
+            offset: BLOCK_FIRST_PKT,
+            read: 0,
+        }
+    }
+}
+
+/// `TPACKET_V3` receive and transmit rings mapped from a packet socket.
+///
+/// The transmit ring directly follows the receive ring in the mapping.
+struct Rings {
+    map: Mmap,
+}
+
+impl Rings {
+    fn new(socket: &RawSocketDesc) -> io::Result<Self> {
+        set_option(socket, PACKET_VERSION, &TPACKET_V3)?;
+
+        set_option(
+            socket,
+            PACKET_RX_RING,
+            &TpacketReq3 {
+                block_size: BLOCK_SIZE,
+                block_nr: RX_BLOCK_COUNT,
+                frame_size: FRAME_SIZE,
+                frame_nr: BLOCK_SIZE / FRAME_SIZE * RX_BLOCK_COUNT,
+                retire_blk_tov: BLOCK_TIMEOUT_MS,
+                ..TpacketReq3::default()
+            },
+        )?;
+
+        set_option(
+            socket,
+            PACKET_TX_RING,
+            &TpacketReq3 {
+                block_size: BLOCK_SIZE,
+                block_nr: TX_BLOCK_COUNT,
+                frame_size: FRAME_SIZE,
+                frame_nr: TX_FRAME_COUNT,
+                ..TpacketReq3::default()
+            },
+        )?;
+
+        Ok(Self {
+            map: Mmap::new(
+                socket.as_raw_fd(),
+                (BLOCK_SIZE * (RX_BLOCK_COUNT + TX_BLOCK_COUNT)) as usize,
+                0,
+            )?,
+        })
+    }
+
+    fn block(&self, block: u32) -> *mut u8 {
+        // SAFETY: `block` is always less than `RX_BLOCK_COUNT`, so is inside the mapping.
+        unsafe { self.map.as_ptr().add((block * BLOCK_SIZE) as usize) }
+    }
+
+    fn tx_slot(&self, frame: u32) -> *mut u8 {
+        // SAFETY: `frame` is always less than `TX_FRAME_COUNT`, and the frame size divides the
+        // block size, so the slot is inside the transmit ring after the receive ring.
+        unsafe {
+            self.block(RX_BLOCK_COUNT)
+                .add((frame * FRAME_SIZE) as usize)
+        }
+    }
+
+    /// A status field shared with the kernel, which must only be accessed atomically.
+    fn status(&self, ptr: *mut u8) -> &AtomicU32 {
+        // SAFETY: Only called for status fields, which are 4 byte aligned and inside the mapping.
+        unsafe { &*ptr.cast::<AtomicU32>() }
+    }
+
+    fn read<T: Copy>(ptr: *const u8) -> T {
+        // SAFETY: Only called for header fields inside the mapping.
+        unsafe { ptr::read_unaligned(ptr.cast::<T>()) }
+    }
+
+    /// The next received frame, read from the current block whether or not the kernel has
+    /// handed it over yet.
+    fn next_frame(&self, rx: &mut RxPosition) -> Option<&[u8]> {
+        let block = self.block(rx.block);
+
+        // Acquire so every frame in the block is visible once the status is seen
+        let retired = self
+            .status(unsafe { block.add(BLOCK_STATUS) })
+            .load(Ordering::Acquire)
+            & TP_STATUS_USER
+            != 0;
+
+        let pkt = if retired {
+            if rx.read >= Self::read::<u32>(unsafe { block.add(BLOCK_NUM_PKTS) }) {
+                self.release(rx.block);
+
+                *rx = RxPosition {
+                    block: (rx.block + 1) % RX_BLOCK_COUNT,
+                    ..RxPosition::default()
+                };
+
+                return self.next_frame(rx);
+            }
+
+            unsafe { block.add(rx.offset) }
+        } else {
+            if rx.offset + PKT_HDR_LEN > BLOCK_SIZE as usize {
+                return None;
+            }
+
+            let pkt = unsafe { block.add(rx.offset) };
+
+            // The kernel fills in each frame's header after copying the frame into the block, and
+            // the headers were zeroed when the block was last released, so a set status and a
+            // non-zero MAC offset and length mean the frame is complete.
+            let status = self
+                .status(unsafe { pkt.add(PKT_STATUS) })
+                .load(Ordering::Acquire);
+            let mac = Self::read::<u16>(unsafe { pkt.add(PKT_MAC) });
+            let snaplen = Self::read::<u32>(unsafe { pkt.add(PKT_SNAPLEN) });
+
+            if status & TP_STATUS_USER == 0 || mac == 0 || snaplen == 0 {
+                return None;
+            }
+
+            pkt
+        };
+
+        let mac = usize::from(Self::read::<u16>(unsafe { pkt.add(PKT_MAC) }));
+        let snaplen = Self::read::<u32>(unsafe { pkt.add(PKT_SNAPLEN) }) as usize;
+
+        rx.offset += Self::read::<u32>(unsafe { pkt.add(PKT_NEXT_OFFSET) }) as usize;
+        rx.read += 1;
+
+        // SAFETY: The kernel places every frame and its header inside the block.
+        Some(unsafe { core::slice::from_raw_parts(pkt.add(mac), snaplen) })
+    }
+
+    /// Give `block` back to the kernel to be filled again.
+    fn release(&self, block: u32) {
+        let ptr = self.block(block);
+
+        let len = (Self::read::<u32>(unsafe { ptr.add(BLOCK_LEN) }) as usize)
+            .clamp(BLOCK_FIRST_PKT, BLOCK_SIZE as usize);
+
+        // Clear the frame headers so frames can be detected in the block before it is retired
+        // next time round the ring.
+        unsafe {
+            ptr.add(BLOCK_FIRST_PKT)
+                .write_bytes(0, len - BLOCK_FIRST_PKT)
+        };
+
+        self.status(unsafe { ptr.add(BLOCK_STATUS) })
+            .store(TP_STATUS_KERNEL, Ordering::Release);
+    }
+
+    /// Copy `data` into transmit slot `frame` to be sent by the next [`flush`](Rings::flush).
+    ///
+    /// Returns `false` if the kernel has not finished sending the previous frame in the slot.
+    fn queue(&self, frame: u32, data: &[u8]) -> Result<bool, Error> {
+        if data.len() > FRAME_SIZE as usize - TX_DATA {
+            fmt::error!(
+                "Frame of {} bytes does not fit in transmit ring slot",
+                data.len()
+            );
+
+            return Err(Error::SendFrame);
+        }
+
+        let slot = self.tx_slot(frame);
+        let status = self.status(unsafe { slot.add(PKT_STATUS) });
+
+        match status.load(Ordering::Acquire) {
+            TP_STATUS_AVAILABLE => (),
+            s if s & TP_STATUS_WRONG_FORMAT != 0 => {
+                fmt::error!("Kernel rejected frame in transmit ring slot {}", frame);
+
+                // Hand the slot back so the ring doesn't get stuck on it
+                status.store(TP_STATUS_AVAILABLE, Ordering::Release);
+
+                return Err(Error::SendFrame);
+            }
+            _ => return Ok(false),
+        }
+
+        // SAFETY: The slot is available so is owned by this process, and `data` fits after the
+        // header as checked above.
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), slot.add(TX_DATA), data.len());
+            ptr::write_unaligned(slot.add(PKT_LEN).cast::<u32>(), data.len() as u32);
+        }
+
+        // Release so the kernel sees the frame data once it sees the status
+        status.store(TP_STATUS_SEND_REQUEST, Ordering::Release);
+
+        Ok(true)
+    }
+
+    /// Ask the kernel to send all queued frames.
+    fn flush(&self, socket: &RawSocketDesc) -> Result<(), Error> {
+        if unsafe { libc::send(socket.as_raw_fd(), ptr::null(), 0, 0) } != -1 {
+            return Ok(());
+        }
+
+        match io::Error::last_os_error() {
+            // The socket is non-blocking, so frames that can't be sent right now are left queued
+            // for the next flush.
+            e if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            e => {
+                fmt::error!("Failed to send packets: {}", e);
+
+                Err(Error::SendFrame)
+            }
+        }
+    }
+}
+
+fn set_option<T>(socket: &RawSocketDesc, option: libc::c_int, value: &T) -> io::Result<()> {
+    cvt(unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            SOL_PACKET,
+            option,
+            ptr::addr_of!(*value).cast(),
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    })
+    .map(|_| ())
+}
@@ -6,13 +6,15 @@ use crate::{
     ETHERCAT_ETHERTYPE,
 };
 use async_io::IoSafe;
-use core::ptr::addr_of;
+use core::ptr::{self, addr_of, NonNull};
 use std::{
     io, mem,
     os::{
-        fd::{AsFd, BorrowedFd},
+        fd::{AsFd, BorrowedFd, FromRawFd, OwnedFd},
         unix::io::{AsRawFd, RawFd},
     },
+    sync::Arc,
+    task::Wake,
 };
 
 pub struct RawSocketDesc {
@@ -151,3 +153,141 @@ fn ifreq_ioctl(
 
     Ok(ifreq.ifr_data)
 }
+
+pub(in crate::std) fn cvt(res: libc::c_int) -> io::Result<libc::c_int> {
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+/// Wakes a blocking TX/RX loop through an eventfd so it can wait for new frames to send and for
+/// received frames at the same time.
+pub(in crate::std) struct EventFdSignal {
+    fd: OwnedFd,
+}
+
+impl EventFdSignal {
+    pub fn new() -> io::Result<Self> {
+        let fd = cvt(unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) })?;
+
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Block until `fd` is readable or this signal is woken.
+    ///
+    /// A wakeup that happened before this call is kept by the eventfd, so returns immediately.
+    pub fn wait(&self, fd: RawFd) -> io::Result<()> {
+        let mut fds = [
+            libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+
+        match cvt(unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) }) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+            Ok(_) => (),
+        }
+
+        if fds[1].revents & libc::POLLIN != 0 {
+            self.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Reset the eventfd counter after a wakeup.
+    fn clear(&self) {
+        let mut count = 0u64;
+
+        unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                ptr::addr_of_mut!(count).cast(),
+                mem::size_of::<u64>(),
+            )
+        };
+    }
+}
+
+impl Wake for EventFdSignal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let count = 1u64;
+
+        unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                ptr::addr_of!(count).cast(),
+                mem::size_of::<u64>(),
+            )
+        };
+    }
+}
+
+/// A memory mapping, unmapped on drop.
+///
+/// The mapping is anonymous if `fd` is `-1`, otherwise it is shared with the kernel object behind
+/// `fd`, e.g. a socket's ring buffers.
+pub(in crate::std) struct Mmap {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl Mmap {
+    pub fn new(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        let (flags, fd) = if fd == -1 {
+            (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1)
+        } else {
+            (libc::MAP_SHARED | libc::MAP_POPULATE, fd)
+        };
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                offset,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr: NonNull::new(ptr.cast()).ok_or_else(io::Error::last_os_error)?,
+            len,
+        })
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
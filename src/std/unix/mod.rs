@@ -8,7 +8,7 @@ mod linux;
 #[cfg(all(not(target_os = "linux"), unix))]
 use self::bpf::BpfDevice as RawSocketDesc;
 #[cfg(target_os = "linux")]
-pub(in crate::std) use self::linux::RawSocketDesc;
+pub(in crate::std) use self::linux::{cvt, EventFdSignal, Mmap, RawSocketDesc};
 
 use crate::{
     error::{Error, PduError},
@@ -0,0 +1,741 @@
+//! Kernel-bypass TX/RX using an `AF_XDP` socket.
+//!
+//! Frames are sent and received through a UMEM area shared with the kernel. A small XDP program is
+//! attached to the interface which redirects EtherCAT frames arriving on the configured queue into
+//! the socket, and passes all other traffic to the kernel network stack as usual.
+
+use crate::{
+    error::{Error, PduError},
+    fmt,
+    std::unix::{cvt, EventFdSignal, Mmap},
+    PduRx, PduTx, ETHERCAT_ETHERTYPE,
+};
+use core::{
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+    task::Waker,
+};
+use std::{
+    ffi::CString,
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::Arc,
+    thread,
+};
+
+/// Size of each UMEM frame. Must hold a full Ethernet frame.
+const FRAME_SIZE: usize = 2048;
+
+/// Number of entries in each ring, and number of UMEM frames used for each of RX and TX.
+const RING_SIZE: u32 = 256;
+
+// `linux/if_xdp.h`, defined here to not depend on a recent `libc`.
+const SOL_XDP: libc::c_int = 283;
+const XDP_MMAP_OFFSETS: libc::c_int = 1;
+const XDP_RX_RING: libc::c_int = 2;
+const XDP_TX_RING: libc::c_int = 3;
+const XDP_UMEM_REG: libc::c_int = 4;
+const XDP_UMEM_FILL_RING: libc::c_int = 5;
+const XDP_UMEM_COMPLETION_RING: libc::c_int = 6;
+const XDP_COPY: u16 = 1 << 1;
+const XDP_PGOFF_RX_RING: libc::off_t = 0;
+const XDP_PGOFF_TX_RING: libc::off_t = 0x80000000;
+const XDP_UMEM_PGOFF_FILL_RING: libc::off_t = 0x100000000;
+const XDP_UMEM_PGOFF_COMPLETION_RING: libc::off_t = 0x180000000;
+
+// `linux/bpf.h` and `linux/if_link.h`
+const BPF_MAP_CREATE: libc::c_long = 0;
+const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+const BPF_PROG_LOAD: libc::c_long = 5;
+const BPF_LINK_CREATE: libc::c_long = 28;
+const BPF_MAP_TYPE_XSKMAP: u32 = 17;
+const BPF_PROG_TYPE_XDP: u32 = 6;
+const BPF_XDP: u32 = 37;
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+const BPF_FUNC_REDIRECT_MAP: i32 = 51;
+const XDP_PASS: i32 = 2;
+const XDP_FLAGS_SKB_MODE: u32 = 1 << 1;
+
+/// Configuration for [`tx_rx_task_xdp`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct XdpConfig {
+    /// The NIC receive queue to bind to.
+    ///
+    /// EtherCAT frames must arrive on this queue, for example by setting the interface to a single
+    /// queue with `ethtool -L <interface> combined 1`, or by steering EtherType `0x88a4` to it with
+    /// an `ethtool -N` rule.
+    ///
+    /// Defaults to `0`.
+    pub queue_id: u32,
+
+    /// Attach the XDP program in generic (SKB) mode and copy frames into the UMEM instead of using
+    /// zero-copy.
+    ///
+    /// This is slower than native XDP, but works with any network driver. It can be used for
+    /// testing over a veth pair.
+    ///
+    /// Defaults to `false`, which uses native XDP and zero-copy if the driver supports them.
+    pub generic: bool,
+}
+
+/// Create a blocking TX/RX loop using an `AF_XDP` socket.
+///
+/// This bypasses most of the kernel network stack, lowering latency and jitter compared to
+/// [`tx_rx_task_io_uring`](crate::std::tx_rx_task_io_uring). Like that function, it should be
+/// called from its own thread, ideally one pinned to an isolated core with a realtime priority.
+///
+/// An XDP program is attached to `interface` that redirects received EtherCAT frames to this loop.
+/// All other traffic is passed to the kernel as normal. The program is detached when this
+/// function returns.
+///
+/// Requires Linux 5.9 or newer, and the `CAP_NET_ADMIN`, `CAP_NET_RAW` and `CAP_BPF` (or
+/// `CAP_SYS_ADMIN`) capabilities. Any other XDP program attached to the interface must be removed
+/// first.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ethercrab::{
+///     std::{tx_rx_task_xdp, XdpConfig},
+///     MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+/// };
+///
+/// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+///
+/// let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+///
+/// std::thread::spawn(move || tx_rx_task_xdp("eth0", tx, rx, XdpConfig::default()));
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+/// ```
+pub fn tx_rx_task_xdp<'sto>(
+    interface: &str,
+    mut pdu_tx: PduTx<'sto>,
+    mut pdu_rx: PduRx<'sto>,
+    config: XdpConfig,
+) -> Result<(), io::Error> {
+    let ifindex = interface_index(interface)?;
+
+    fmt::debug!(
+        "Opening {} queue {} using AF_XDP, generic mode: {}",
+        interface,
+        config.queue_id,
+        config.generic
+    );
+
+    let mut socket = XdpSocket::new(ifindex, config)?;
+
+    // Attached last so frames are only redirected once the socket is ready to receive them
+    let _program = XdpProgram::attach(ifindex, &socket, config)?;
+
+    let signal = Arc::new(EventFdSignal::new()?);
+    let waker = Waker::from(Arc::clone(&signal));
+
+    loop {
+        pdu_tx.replace_waker(&waker);
+
+        socket.reclaim_tx();
+
+        let mut sent = 0;
+
+        while socket.can_send() {
+            let Some(frame) = pdu_tx.next_sendable_frame() else {
+                break;
+            };
+
+            frame
+                .send_blocking(|data| socket.send(data))
+                .map_err(io::Error::other)?;
+
+            sent += 1;
+        }
+
+        if sent > 0 {
+            socket.kick()?;
+        }
+
+        let mut received = 0;
+
+        while let Some(frame) = socket.receive() {
+            loop {
+                match pdu_rx.receive_frame(frame) {
+                    Ok(_) => break,
+                    Err(Error::Pdu(PduError::NoWaker)) => {
+                        fmt::trace!("No waker for received frame, retrying receive");
+
+                        thread::yield_now();
+                    }
+                    Err(e) => return Err(io::Error::other(e)),
+                }
+            }
+
+            received += 1;
+        }
+
+        socket.release_rx(received);
+
+        // Frames that are still waiting for a TX buffer must not wait for a wakeup
+        if sent == 0 && received == 0 && socket.can_send() {
+            fmt::trace!("Waiting for frames to send or receive");
+
+            // A wakeup between `replace_waker` above and here is kept by the eventfd, so returns
+            // from this call immediately.
+            signal.wait(socket.fd.as_raw_fd())?;
+        }
+    }
+}
+
+fn interface_index(interface: &str) -> io::Result<u32> {
+    let name = CString::new(interface).map_err(io::Error::other)?;
+
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        ifindex => Ok(ifindex),
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct XdpDesc {
+    addr: u64,
+    len: u32,
+    options: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct XdpRingOffset {
+    producer: u64,
+    consumer: u64,
+    desc: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct XdpMmapOffsets {
+    rx: XdpRingOffset,
+    tx: XdpRingOffset,
+    fr: XdpRingOffset,
+    cr: XdpRingOffset,
+}
+
+/// The original `xdp_umem_reg` layout, accepted by every kernel with `AF_XDP` support.
+#[repr(C)]
+struct XdpUmemReg {
+    addr: u64,
+    len: u64,
+    chunk_size: u32,
+    headroom: u32,
+}
+
+#[repr(C)]
+struct SockaddrXdp {
+    sxdp_family: u16,
+    sxdp_flags: u16,
+    sxdp_ifindex: u32,
+    sxdp_queue_id: u32,
+    sxdp_shared_umem_fd: u32,
+}
+
+/// A single-producer, single-consumer ring shared with the kernel.
+struct Ring<T> {
+    map: Mmap,
+    producer: usize,
+    consumer: usize,
+    desc: usize,
+    /// Local copy of the index this side of the ring owns.
+    head: u32,
+    _entry: PhantomData<T>,
+}
+
+impl<T: Copy> Ring<T> {
+    fn new(fd: RawFd, offsets: &XdpRingOffset, page_offset: libc::off_t) -> io::Result<Self> {
+        let len = offsets.desc as usize + RING_SIZE as usize * mem::size_of::<T>();
+
+        Ok(Self {
+            map: Mmap::new(fd, len, page_offset)?,
+            producer: offsets.producer as usize,
+            consumer: offsets.consumer as usize,
+            desc: offsets.desc as usize,
+            head: 0,
+            _entry: PhantomData,
+        })
+    }
+
+    fn index(&self, offset: usize) -> &AtomicU32 {
+        // SAFETY: The kernel places the producer and consumer indices at the given offsets, aligned
+        // to a `u32`, inside the mapping.
+        unsafe { &*self.map.as_ptr().add(offset).cast::<AtomicU32>() }
+    }
+
+    fn entry(&self, index: u32) -> *mut T {
+        // SAFETY: Ring size is a power of two, so the masked index is always inside the mapping.
+        unsafe {
+            self.map
+                .as_ptr()
+                .add(self.desc)
+                .cast::<T>()
+                .add((index & (RING_SIZE - 1)) as usize)
+        }
+    }
+
+    /// The number of entries that can be pushed to a ring produced by userspace.
+    fn free(&self) -> u32 {
+        RING_SIZE
+            - self
+                .head
+                .wrapping_sub(self.index(self.consumer).load(Ordering::Acquire))
+    }
+
+    /// Push an entry to a ring produced by userspace.
+    fn push(&mut self, item: T) -> bool {
+        if self.free() == 0 {
+            return false;
+        }
+
+        unsafe { self.entry(self.head).write(item) };
+
+        self.head = self.head.wrapping_add(1);
+        self.index(self.producer)
+            .store(self.head, Ordering::Release);
+
+        true
+    }
+
+    /// Take an entry from a ring produced by the kernel.
+    ///
+    /// The entry is not handed back to the kernel until [`release`](Ring::release) is called.
+    fn peek(&self, nth: u32) -> Option<T> {
+        let available = self
+            .index(self.producer)
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head);
+
+        (nth < available).then(|| unsafe { self.entry(self.head.wrapping_add(nth)).read() })
+    }
+
+    /// Hand `count` entries of a ring produced by the kernel back to it.
+    fn release(&mut self, count: u32) {
+        self.head = self.head.wrapping_add(count);
+        self.index(self.consumer)
+            .store(self.head, Ordering::Release);
+    }
+}
+
+struct XdpSocket {
+    fd: OwnedFd,
+    umem: Mmap,
+    fill: Ring<u64>,
+    completion: Ring<u64>,
+    rx: Ring<XdpDesc>,
+    tx: Ring<XdpDesc>,
+    /// UMEM addresses of TX frames that are not in use by the kernel.
+    free_tx: Vec<u64>,
+    /// Number of RX ring entries returned by [`receive`](XdpSocket::receive) so far.
+    receiving: u32,
+}
+
+impl XdpSocket {
+    fn new(ifindex: u32, config: XdpConfig) -> io::Result<Self> {
+        let fd =
+            cvt(unsafe { libc::socket(libc::AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) })?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // First half of the UMEM is used for RX, second half for TX
+        let umem = Mmap::new(-1, FRAME_SIZE * RING_SIZE as usize * 2, 0)?;
+
+        let reg = XdpUmemReg {
+            addr: umem.as_ptr() as u64,
+            len: umem.len() as u64,
+            chunk_size: FRAME_SIZE as u32,
+            headroom: 0,
+        };
+
+        set_option(&fd, XDP_UMEM_REG, &reg)?;
+
+        for ring in [
+            XDP_UMEM_FILL_RING,
+            XDP_UMEM_COMPLETION_RING,
+            XDP_RX_RING,
+            XDP_TX_RING,
+        ] {
+            set_option(&fd, ring, &RING_SIZE)?;
+        }
+
+        let mut offsets = XdpMmapOffsets::default();
+        let mut len = mem::size_of::<XdpMmapOffsets>() as libc::socklen_t;
+
+        cvt(unsafe {
+            libc::getsockopt(
+                fd.as_raw_fd(),
+                SOL_XDP,
+                XDP_MMAP_OFFSETS,
+                ptr::addr_of_mut!(offsets).cast(),
+                &mut len,
+            )
+        })?;
+
+        let raw = fd.as_raw_fd();
+
+        let mut socket = Self {
+            fill: Ring::new(raw, &offsets.fr, XDP_UMEM_PGOFF_FILL_RING)?,
+            completion: Ring::new(raw, &offsets.cr, XDP_UMEM_PGOFF_COMPLETION_RING)?,
+            rx: Ring::new(raw, &offsets.rx, XDP_PGOFF_RX_RING)?,
+            tx: Ring::new(raw, &offsets.tx, XDP_PGOFF_TX_RING)?,
+            free_tx: (RING_SIZE..RING_SIZE * 2)
+                .map(|frame| u64::from(frame) * FRAME_SIZE as u64)
+                .collect(),
+            receiving: 0,
+            umem,
+            fd,
+        };
+
+        for frame in 0..RING_SIZE {
+            socket.fill.push(u64::from(frame) * FRAME_SIZE as u64);
+        }
+
+        let sockaddr = SockaddrXdp {
+            sxdp_family: libc::AF_XDP as u16,
+            sxdp_flags: if config.generic { XDP_COPY } else { 0 },
+            sxdp_ifindex: ifindex,
+            sxdp_queue_id: config.queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+
+        cvt(unsafe {
+            libc::bind(
+                socket.fd.as_raw_fd(),
+                ptr::addr_of!(sockaddr).cast(),
+                mem::size_of::<SockaddrXdp>() as libc::socklen_t,
+            )
+        })?;
+
+        Ok(socket)
+    }
+
+    fn frame(&self, addr: u64, len: usize) -> &[u8] {
+        // SAFETY: Addresses and lengths come from our own TX descriptors or kernel RX descriptors,
+        // which always lie inside a single UMEM frame.
+        unsafe { core::slice::from_raw_parts(self.umem.as_ptr().add(addr as usize), len) }
+    }
+
+    /// Move TX frames the kernel has finished sending back to the free list.
+    fn reclaim_tx(&mut self) {
+        let mut count = 0;
+
+        while let Some(addr) = self.completion.peek(count) {
+            self.free_tx.push(addr);
+
+            count += 1;
+        }
+
+        self.completion.release(count);
+    }
+
+    fn can_send(&self) -> bool {
+        !self.free_tx.is_empty()
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<usize, Error> {
+        if data.len() > FRAME_SIZE {
+            return Err(Error::Pdu(PduError::TooLong));
+        }
+
+        let addr = self.free_tx.pop().ok_or(Error::SendFrame)?;
+
+        // SAFETY: The frame is on the free list so is not being used by the kernel, and is large
+        // enough to hold `data`.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.umem.as_ptr().add(addr as usize),
+                data.len(),
+            )
+        };
+
+        let desc = XdpDesc {
+            addr,
+            len: data.len() as u32,
+            options: 0,
+        };
+
+        if !self.tx.push(desc) {
+            self.free_tx.push(addr);
+
+            return Err(Error::SendFrame);
+        }
+
+        Ok(data.len())
+    }
+
+    /// Tell the kernel there are frames in the TX ring.
+    fn kick(&self) -> io::Result<()> {
+        let res = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null(),
+                0,
+            )
+        };
+
+        if res == -1 {
+            let e = io::Error::last_os_error();
+
+            // The kernel is still busy with previous frames, and will pick these up too
+            match e.raw_os_error() {
+                Some(libc::EAGAIN | libc::EBUSY | libc::ENOBUFS) => (),
+                _ => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the next received frame. The frame remains valid until
+    /// [`release_rx`](XdpSocket::release_rx) is called.
+    fn receive(&mut self) -> Option<&[u8]> {
+        let desc = self.rx.peek(self.receiving)?;
+
+        self.receiving += 1;
+
+        Some(self.frame(desc.addr, desc.len as usize))
+    }
+
+    /// Return the UMEM frames of `count` received frames to the fill ring.
+    fn release_rx(&mut self, count: u32) {
+        for nth in 0..count {
+            if let Some(desc) = self.rx.peek(nth) {
+                // Can't fail as there are only as many RX frames as fill ring entries
+                self.fill.push(desc.addr & !(FRAME_SIZE as u64 - 1));
+            }
+        }
+
+        self.rx.release(count);
+        self.receiving = 0;
+    }
+}
+
+fn set_option<T>(fd: &OwnedFd, option: libc::c_int, value: &T) -> io::Result<()> {
+    cvt(unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            SOL_XDP,
+            option,
+            ptr::from_ref(value).cast(),
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    })
+    .map(|_| ())
+}
+
+/// A single eBPF instruction.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct BpfInsn {
+    code: u8,
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+const fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> BpfInsn {
+    #[cfg(target_endian = "little")]
+    let regs = src << 4 | dst;
+    #[cfg(target_endian = "big")]
+    let regs = dst << 4 | src;
+
+    BpfInsn {
+        code,
+        regs,
+        off,
+        imm,
+    }
+}
+
+/// The XDP program and socket map that redirect EtherCAT frames to an [`XdpSocket`].
+///
+/// The program is detached from the interface when the link is dropped.
+struct XdpProgram {
+    _map: OwnedFd,
+    _program: OwnedFd,
+    _link: OwnedFd,
+}
+
+impl XdpProgram {
+    fn attach(ifindex: u32, socket: &XdpSocket, config: XdpConfig) -> io::Result<Self> {
+        #[repr(C)]
+        struct MapCreate {
+            map_type: u32,
+            key_size: u32,
+            value_size: u32,
+            max_entries: u32,
+        }
+
+        let map = bpf_fd(
+            BPF_MAP_CREATE,
+            &MapCreate {
+                map_type: BPF_MAP_TYPE_XSKMAP,
+                key_size: 4,
+                value_size: 4,
+                max_entries: config.queue_id + 1,
+            },
+        )?;
+
+        #[repr(C)]
+        struct MapUpdate {
+            map_fd: u32,
+            _pad: u32,
+            key: u64,
+            value: u64,
+            flags: u64,
+        }
+
+        let value = socket.fd.as_raw_fd() as u32;
+
+        bpf(
+            BPF_MAP_UPDATE_ELEM,
+            &MapUpdate {
+                map_fd: map.as_raw_fd() as u32,
+                _pad: 0,
+                key: ptr::addr_of!(config.queue_id) as u64,
+                value: ptr::addr_of!(value) as u64,
+                flags: 0,
+            },
+        )?;
+
+        // The EtherType as read by a 16 bit load on this machine
+        let ethertype = i32::from(u16::from_ne_bytes(ETHERCAT_ETHERTYPE.to_be_bytes()));
+
+        let program = [
+            // r2 = ctx->data, r3 = ctx->data_end
+            insn(0x61, 2, 1, 0, 0),
+            insn(0x61, 3, 1, 4, 0),
+            // Pass frames too short for an Ethernet header
+            insn(0xbf, 4, 2, 0, 0),
+            insn(0x07, 4, 0, 0, 14),
+            insn(0x2d, 4, 3, 8, 0),
+            // Pass frames with any other EtherType
+            insn(0x69, 4, 2, 12, 0),
+            insn(0x55, 4, 0, 6, ethertype),
+            // return bpf_redirect_map(&map, ctx->rx_queue_index, XDP_PASS)
+            insn(0x61, 2, 1, 16, 0),
+            insn(0x18, 1, BPF_PSEUDO_MAP_FD, 0, map.as_raw_fd()),
+            insn(0x00, 0, 0, 0, 0),
+            insn(0xb7, 3, 0, 0, XDP_PASS),
+            insn(0x85, 0, 0, 0, BPF_FUNC_REDIRECT_MAP),
+            insn(0x95, 0, 0, 0, 0),
+            // return XDP_PASS
+            insn(0xb7, 0, 0, 0, XDP_PASS),
+            insn(0x95, 0, 0, 0, 0),
+        ];
+
+        #[repr(C)]
+        struct ProgLoad {
+            prog_type: u32,
+            insn_cnt: u32,
+            insns: u64,
+            license: u64,
+            log_level: u32,
+            log_size: u32,
+            log_buf: u64,
+            kern_version: u32,
+            prog_flags: u32,
+            prog_name: [u8; 16],
+            prog_ifindex: u32,
+            expected_attach_type: u32,
+        }
+
+        let license = b"Dual MIT/GPL\0";
+        let mut log = vec![0u8; 4096];
+
+        let mut load = ProgLoad {
+            prog_type: BPF_PROG_TYPE_XDP,
+            insn_cnt: program.len() as u32,
+            insns: program.as_ptr() as u64,
+            license: license.as_ptr() as u64,
+            log_level: 0,
+            log_size: 0,
+            log_buf: 0,
+            kern_version: 0,
+            prog_flags: 0,
+            prog_name: *b"ethercrab\0\0\0\0\0\0\0",
+            prog_ifindex: 0,
+            expected_attach_type: BPF_XDP,
+        };
+
+        let program = match bpf_fd(BPF_PROG_LOAD, &load) {
+            Ok(program) => program,
+            Err(e) => {
+                // Load again to get the verifier log
+                load.log_level = 1;
+                load.log_size = log.len() as u32;
+                load.log_buf = log.as_mut_ptr() as u64;
+
+                let _ = bpf(BPF_PROG_LOAD, &load);
+
+                fmt::error!(
+                    "Failed to load XDP program: {}",
+                    String::from_utf8_lossy(&log).trim_end_matches('\0')
+                );
+
+                return Err(e);
+            }
+        };
+
+        #[repr(C)]
+        struct LinkCreate {
+            prog_fd: u32,
+            target_ifindex: u32,
+            attach_type: u32,
+            flags: u32,
+        }
+
+        let link = bpf_fd(
+            BPF_LINK_CREATE,
+            &LinkCreate {
+                prog_fd: program.as_raw_fd() as u32,
+                target_ifindex: ifindex,
+                attach_type: BPF_XDP,
+                flags: if config.generic {
+                    XDP_FLAGS_SKB_MODE
+                } else {
+                    0
+                },
+            },
+        )?;
+
+        Ok(Self {
+            _map: map,
+            _program: program,
+            _link: link,
+        })
+    }
+}
+
+/// Call the `bpf` syscall.
+fn bpf<T>(command: libc::c_long, attr: &T) -> io::Result<libc::c_long> {
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            command,
+            ptr::from_ref(attr),
+            mem::size_of::<T>() as libc::c_uint,
+        )
+    };
+
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+/// Call the `bpf` syscall with a command that creates a new file descriptor.
+fn bpf_fd<T>(command: libc::c_long, attr: &T) -> io::Result<OwnedFd> {
+    bpf(command, attr).map(|fd| unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
@@ -10,11 +10,14 @@ use crate::{
     fmt,
     pdi::{PdiOffset, PdiSegment},
     register::RegisterAddress,
-    subdevice::types::{Mailbox, MailboxConfig},
+    subdevice::{
+        pdo_mapping::{PdoMapping, PdoMappingEntry},
+        types::{Mailbox, MailboxConfig},
+    },
     subdevice_state::SubDeviceState,
     sync_manager_channel::{Enable, Status, SyncManagerChannel, SM_BASE_ADDRESS, SM_TYPE_ADDRESS},
 };
-use core::ops::DerefMut;
+use core::ops::{DerefMut, Range};
 
 /// Configuation from EEPROM methods.
 impl<'a, S> SubDeviceRef<'a, S>
@@ -26,6 +29,12 @@ where
     /// Continue configuration by calling
     /// [`configure_fmmus`](crate::SubDeviceGroup::configure_fmmus).
     pub(crate) async fn configure_mailboxes(&mut self) -> Result<(), Error> {
+        self.configure_mailboxes_and_pre_op()
+            .await
+            .map_err(|e| self.error_context(e))
+    }
+
+    async fn configure_mailboxes_and_pre_op(&mut self) -> Result<(), Error> {
         // Force EEPROM into master mode. Some SubDevices require PDI mode for INIT -> PRE-OP
         // transition. This is mentioned in ETG2010 p. 146 under "Eeprom/@AssignToPd". We'll reset
         // to master mode here, now that the transition is complete.
@@ -45,7 +54,7 @@ where
             self.configured_address
         );
 
-        self.request_subdevice_state(SubDeviceState::PreOp).await?;
+        self.request_state(SubDeviceState::PreOp).await?;
 
         if self.state.config.mailbox.has_coe {
             // TODO: Abstract this no-complete-access check into a method call so we can reuse it.
@@ -103,6 +112,17 @@ where
     ///
     /// PDOs must be configured in the PRE-OP state.
     pub(crate) async fn configure_fmmus(
+        &mut self,
+        global_offset: PdiOffset,
+        group_start_address: u32,
+        direction: PdoDirection,
+    ) -> Result<PdiOffset, Error> {
+        self.configure_process_data(global_offset, group_start_address, direction)
+            .await
+            .map_err(|e| self.error_context(e))
+    }
+
+    async fn configure_process_data(
         &mut self,
         mut global_offset: PdiOffset,
         group_start_address: u32,
@@ -136,7 +156,7 @@ where
             has_coe
         );
 
-        let range = if has_coe {
+        let (range, mapping) = if has_coe {
             self.configure_pdos_coe(&sync_managers, &fmmu_usage, direction, &mut global_offset)
                 .await?
         } else {
@@ -151,6 +171,7 @@ where
                         ..(range.bytes.end - group_start_address as usize),
                     ..range
                 };
+                self.state.config.input_mapping = mapping;
             }
             PdoDirection::MasterWrite => {
                 self.state.config.io.output = PdiSegment {
@@ -158,6 +179,7 @@ where
                         ..(range.bytes.end - group_start_address as usize),
                     ..range
                 };
+                self.state.config.output_mapping = mapping;
             }
         };
 
@@ -173,6 +195,64 @@ where
         Ok(global_offset)
     }
 
+    /// Reconfigure the process data of a SubDevice that has been reset, mapping it to the same
+    /// logical addresses it had before.
+    ///
+    /// `inputs` and `outputs` are relative to the start of the group. The SubDevice's PDI ranges are
+    /// left unchanged if its process data length no longer matches.
+    pub(crate) async fn reconfigure_fmmus(
+        &mut self,
+        group_start_address: u32,
+        inputs: Range<usize>,
+        outputs: Range<usize>,
+        outputs_offset: usize,
+    ) -> Result<(), Error> {
+        let previous = self.state.config.io.clone();
+
+        let result = async {
+            self.configure_fmmus(
+                PdiOffset {
+                    start_address: group_start_address + inputs.start as u32,
+                },
+                group_start_address,
+                PdoDirection::MasterRead,
+            )
+            .await?;
+
+            self.configure_fmmus(
+                PdiOffset {
+                    start_address: group_start_address + outputs.start as u32,
+                },
+                group_start_address,
+                PdoDirection::MasterWrite,
+            )
+            .await?;
+
+            self.state
+                .config
+                .io
+                .check_lengths(&inputs, &outputs)
+                .map_err(Error::from)
+        }
+        .await;
+
+        if let Err(e) = result {
+            fmt::error!(
+                "SubDevice {:#06x} process data changed during reconfiguration: {}",
+                self.configured_address,
+                e
+            );
+
+            self.state.config.io = previous;
+
+            return Err(e);
+        }
+
+        self.state.config.io.offset_outputs(outputs_offset);
+
+        Ok(())
+    }
+
     async fn write_sm_config(
         &self,
         sync_manager_index: u8,
@@ -292,7 +372,7 @@ where
         fmmu_usage: &[FmmuUsage],
         direction: PdoDirection,
         global_offset: &mut PdiOffset,
-    ) -> Result<PdiSegment, Error> {
+    ) -> Result<(PdiSegment, PdoMapping), Error> {
         if !self.state.config.mailbox.has_coe {
             fmt::warn!("Invariant: attempting to configure PDOs from COE with no SOE support");
         }
@@ -310,6 +390,7 @@ where
 
         let start_offset = *global_offset;
         let mut total_bit_len = 0;
+        let mut mapping = PdoMapping::new();
 
         for (sync_manager_index, sm_type) in self
             .state
@@ -399,6 +480,8 @@ where
                     );
 
                     sm_bit_len += u16::from(mapping_bit_len);
+
+                    mapping.push(PdoMappingEntry::new(index, sub_index, mapping_bit_len));
                 }
             }
 
@@ -434,10 +517,13 @@ where
             total_bit_len += sm_bit_len;
         }
 
-        Ok(PdiSegment {
-            bit_len: total_bit_len.into(),
-            bytes: start_offset.up_to(*global_offset),
-        })
+        Ok((
+            PdiSegment {
+                bit_len: total_bit_len.into(),
+                bytes: start_offset.up_to(*global_offset),
+            },
+            mapping,
+        ))
     }
 
     async fn write_fmmu_config(
@@ -499,17 +585,35 @@ where
         fmmu_usage: &[FmmuUsage],
         direction: PdoDirection,
         offset: &mut PdiOffset,
-    ) -> Result<PdiSegment, Error> {
+    ) -> Result<(PdiSegment, PdoMapping), Error> {
+        let (sm_type, fmmu_type) = direction.filter_terms();
+
+        // Only PDOs assigned to a SyncManager in this direction end up in the PDI
+        let assigned = |sync_manager: u8| {
+            sync_managers
+                .get(usize::from(sync_manager))
+                .is_some_and(|sm| sm.usage_type == sm_type)
+        };
+
+        // All entries of all assigned PDOs in EEPROM order
+        let mut entries = PdoMapping::new();
+
         let pdos = match direction {
             PdoDirection::MasterRead => {
-                let read_pdos = self.eeprom().maindevice_read_pdos().await?;
+                let read_pdos = self
+                    .eeprom()
+                    .maindevice_read_pdos(&mut entries, assigned)
+                    .await?;
 
                 fmt::trace!("SubDevice inputs PDOs {:#?}", read_pdos);
 
                 read_pdos
             }
             PdoDirection::MasterWrite => {
-                let write_pdos = self.eeprom().maindevice_write_pdos().await?;
+                let write_pdos = self
+                    .eeprom()
+                    .maindevice_write_pdos(&mut entries, assigned)
+                    .await?;
 
                 fmt::trace!("SubDevice outputs PDOs {:#?}", write_pdos);
 
@@ -521,8 +625,10 @@ where
 
         let start_offset = *offset;
         let mut total_bit_len = 0;
+        let mut mapping = PdoMapping::new();
 
-        let (sm_type, fmmu_type) = direction.filter_terms();
+        // Some entries were discarded, so the mapping for any given SM may be incomplete too.
+        mapping.complete = entries.is_complete();
 
         for (sync_manager_index, sync_manager) in sync_managers
             .iter()
@@ -539,6 +645,25 @@ where
 
             total_bit_len += bit_len;
 
+            // PDOs are mapped into the PDI in SM order, so pick out the entries of each PDO
+            // assigned to this SM.
+            let mut first_entry = 0;
+
+            for pdo in pdos.iter().filter(|pdo| assigned(pdo.sync_manager)) {
+                let num_entries = usize::from(pdo.num_entries);
+
+                if pdo.sync_manager == sync_manager_index {
+                    entries
+                        .entries()
+                        .iter()
+                        .skip(first_entry)
+                        .take(num_entries)
+                        .for_each(|entry| mapping.push(*entry));
+                }
+
+                first_entry += num_entries;
+            }
+
             // Look for FMMU index using FMMU_EX section in EEPROM. If it's empty, default
             // to looking through FMMU usage list and picking out the appropriate kind
             // (Inputs, Outputs)
@@ -577,10 +702,13 @@ where
             .await?;
         }
 
-        Ok(PdiSegment {
-            bit_len: total_bit_len.into(),
-            bytes: start_offset.up_to(*offset),
-        })
+        Ok((
+            PdiSegment {
+                bit_len: total_bit_len.into(),
+                bytes: start_offset.up_to(*offset),
+            },
+            mapping,
+        ))
     }
 }
 
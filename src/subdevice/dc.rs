@@ -1,5 +1,6 @@
 //! Distributed Clock configuration for a single SubDevice.
 
+use crate::error::DistributedClockError;
 use core::{fmt, time::Duration};
 
 /// DC sync configuration for a SubDevice.
@@ -34,3 +35,235 @@ impl fmt::Display for DcSync {
         }
     }
 }
+
+/// Per-SubDevice overrides for DC SYNC signal timing.
+///
+/// These values are applied on top of the group-wide
+/// [`DcConfiguration`](crate::subdevice_group::DcConfiguration) by
+/// [`SubDeviceGroup::configure_dc_sync`](crate::SubDeviceGroup::configure_dc_sync), and usually
+/// come from the `<Dc><OpMode>` section of the SubDevice's ESI file. The default value leaves the
+/// group configuration untouched.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct DcSyncTiming {
+    /// Delay of this SubDevice's first SYNC0 pulse relative to the start of the group cycle.
+    ///
+    /// This is added to the computed DC start time, so the SubDevice's SYNC0 pulses are shifted by
+    /// this amount in every cycle. It must be shorter than the group SYNC0 period.
+    ///
+    /// This corresponds to the `ShiftTimeSync0` ESI value.
+    pub sync0_shift: Duration,
+
+    /// Additional SYNC1 delay, added to the SYNC1 period set with [`DcSync::Sync01`].
+    ///
+    /// Ignored if SYNC1 is not enabled. It must be shorter than the group SYNC0 period.
+    ///
+    /// This corresponds to the `ShiftTimeSync1` ESI value.
+    pub sync1_shift: Duration,
+
+    /// SYNC signal pulse length.
+    ///
+    /// If set, this value is written to
+    /// [`RegisterAddress::DcSyncPulseLength`](crate::RegisterAddress::DcSyncPulseLength) in
+    /// units of 10ns. A pulse length of zero selects acknowledged mode.
+    ///
+    /// Most ESCs load the pulse length from EEPROM and do not allow it to be written over
+    /// EtherCAT, so this should only be set for SubDevices which are documented to accept it.
+    pub pulse_length: Option<Duration>,
+}
+
+impl DcSyncTiming {
+    /// Check that these timings are consistent with the given SYNC mode and group SYNC0 period.
+    pub(crate) fn validate(
+        &self,
+        dc_sync: DcSync,
+        sync0_period: Duration,
+    ) -> Result<(), DistributedClockError> {
+        validate_cycle_time(sync0_period)?;
+
+        if self.sync0_shift >= sync0_period {
+            return Err(DistributedClockError::ShiftTooLong);
+        }
+
+        if let DcSync::Sync01 { .. } = dc_sync {
+            if self.sync1_shift >= sync0_period {
+                return Err(DistributedClockError::ShiftTooLong);
+            }
+
+            self.sync1_cycle_time(dc_sync)?;
+        }
+
+        if self.pulse_length.is_some() {
+            self.pulse_length_register()?;
+        }
+
+        if self
+            .pulse_length
+            .is_some_and(|pulse_length| pulse_length >= sync0_period)
+        {
+            return Err(DistributedClockError::InvalidPulseLength);
+        }
+
+        Ok(())
+    }
+
+    /// Value written to the SYNC1 cycle time register in nanoseconds, or `None` if SYNC1 is
+    /// disabled.
+    pub(crate) fn sync1_cycle_time(
+        &self,
+        dc_sync: DcSync,
+    ) -> Result<Option<u32>, DistributedClockError> {
+        let DcSync::Sync01 { sync1_period } = dc_sync else {
+            return Ok(None);
+        };
+
+        let ns = (sync1_period + self.sync1_shift).as_nanos();
+
+        u32::try_from(ns)
+            .map(Some)
+            .map_err(|_| DistributedClockError::InvalidSync1CycleTime)
+    }
+
+    /// Pulse length register value in units of 10ns, or `None` if the pulse length should not be
+    /// written.
+    pub(crate) fn pulse_length_register(&self) -> Result<Option<u16>, DistributedClockError> {
+        self.pulse_length
+            .map(|pulse_length| {
+                u16::try_from(pulse_length.as_nanos() / 10)
+                    .map_err(|_| DistributedClockError::InvalidPulseLength)
+            })
+            .transpose()
+    }
+}
+
+/// Check that a SYNC0 period is usable as a DC cycle time.
+pub(crate) fn validate_cycle_time(sync0_period: Duration) -> Result<(), DistributedClockError> {
+    if sync0_period.is_zero() || sync0_period.as_nanos() > u128::from(u32::MAX) {
+        return Err(DistributedClockError::InvalidCycleTime);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CYCLE: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn default_timing_is_valid() {
+        assert_eq!(
+            DcSyncTiming::default().validate(DcSync::Sync0, CYCLE),
+            Ok(())
+        );
+        assert_eq!(
+            DcSyncTiming::default().validate(
+                DcSync::Sync01 {
+                    sync1_period: Duration::from_micros(100)
+                },
+                CYCLE
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn bad_cycle_time() {
+        assert_eq!(
+            DcSyncTiming::default().validate(DcSync::Sync0, Duration::ZERO),
+            Err(DistributedClockError::InvalidCycleTime)
+        );
+        assert_eq!(
+            DcSyncTiming::default().validate(DcSync::Sync0, Duration::from_secs(5)),
+            Err(DistributedClockError::InvalidCycleTime)
+        );
+    }
+
+    #[test]
+    fn shift_longer_than_cycle() {
+        let timing = DcSyncTiming {
+            sync0_shift: CYCLE,
+            ..DcSyncTiming::default()
+        };
+
+        assert_eq!(
+            timing.validate(DcSync::Sync0, CYCLE),
+            Err(DistributedClockError::ShiftTooLong)
+        );
+
+        let timing = DcSyncTiming {
+            sync1_shift: CYCLE * 2,
+            ..DcSyncTiming::default()
+        };
+
+        // SYNC1 shift is ignored if SYNC1 is disabled
+        assert_eq!(timing.validate(DcSync::Sync0, CYCLE), Ok(()));
+        assert_eq!(
+            timing.validate(
+                DcSync::Sync01 {
+                    sync1_period: Duration::ZERO
+                },
+                CYCLE
+            ),
+            Err(DistributedClockError::ShiftTooLong)
+        );
+    }
+
+    #[test]
+    fn sync1_cycle_time() {
+        let timing = DcSyncTiming {
+            sync1_shift: Duration::from_micros(20),
+            ..DcSyncTiming::default()
+        };
+
+        assert_eq!(timing.sync1_cycle_time(DcSync::Sync0), Ok(None));
+        assert_eq!(
+            timing.sync1_cycle_time(DcSync::Sync01 {
+                sync1_period: Duration::from_micros(100)
+            }),
+            Ok(Some(120_000))
+        );
+        assert_eq!(
+            timing.validate(
+                DcSync::Sync01 {
+                    sync1_period: Duration::from_secs(10)
+                },
+                CYCLE
+            ),
+            Err(DistributedClockError::InvalidSync1CycleTime)
+        );
+    }
+
+    #[test]
+    fn pulse_length() {
+        let timing = DcSyncTiming {
+            pulse_length: Some(Duration::from_micros(1)),
+            ..DcSyncTiming::default()
+        };
+
+        assert_eq!(timing.pulse_length_register(), Ok(Some(100)));
+        assert_eq!(timing.validate(DcSync::Sync0, CYCLE), Ok(()));
+
+        // Longer than the SYNC0 cycle
+        let timing = DcSyncTiming {
+            pulse_length: Some(Duration::from_micros(500)),
+            ..DcSyncTiming::default()
+        };
+
+        assert_eq!(
+            timing.validate(DcSync::Sync0, Duration::from_micros(250)),
+            Err(DistributedClockError::InvalidPulseLength)
+        );
+
+        // Doesn't fit in register
+        let timing = DcSyncTiming {
+            pulse_length: Some(Duration::from_millis(1)),
+            ..DcSyncTiming::default()
+        };
+
+        assert_eq!(
+            timing.validate(DcSync::Sync0, Duration::from_millis(2)),
+            Err(DistributedClockError::InvalidPulseLength)
+        );
+    }
+}
@@ -7,7 +7,10 @@ use crate::{
     },
     error::{EepromError, Error, Item},
     fmt,
-    subdevice::SubDeviceIdentity,
+    subdevice::{
+        pdo_mapping::{PdoMapping, PdoMappingEntry},
+        SubDeviceIdentity,
+    },
 };
 use core::marker::PhantomData;
 use embedded_io_async::{Read, ReadExactError};
@@ -213,7 +216,17 @@ where
         Ok(mappings)
     }
 
-    async fn pdos(&self, direction: PdoType) -> Result<heapless::Vec<Pdo, 64>, Error> {
+    /// Read all PDOs in the given direction, appending the entries of each PDO assigned to a
+    /// SyncManager to `entries` in EEPROM order.
+    ///
+    /// `assigned` is given the SyncManager index of each PDO. Entries of unassigned PDOs are not
+    /// stored so they don't use up the capacity of `entries`.
+    async fn pdos(
+        &self,
+        direction: PdoType,
+        entries: &mut PdoMapping,
+        assigned: impl Fn(u8) -> bool,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
         let mut pdos = heapless::Vec::new();
 
         fmt::trace!("Get {:?} PDOs", direction);
@@ -234,6 +247,14 @@ where
                 fmt::debug!("--> PDO entry:\n{:#?}", entry);
 
                 pdo.bit_len += u16::from(entry.data_length_bits);
+
+                if assigned(pdo.sync_manager) {
+                    entries.push(PdoMappingEntry::new(
+                        entry.index,
+                        entry.sub_index,
+                        entry.data_length_bits,
+                    ));
+                }
             }
 
             pdos.push(pdo).map_err(|_| {
@@ -247,13 +268,21 @@ where
     }
 
     /// Transmit PDOs (from device's perspective) - inputs
-    pub(crate) async fn maindevice_read_pdos(&self) -> Result<heapless::Vec<Pdo, 64>, Error> {
-        self.pdos(PdoType::Tx).await
+    pub(crate) async fn maindevice_read_pdos(
+        &self,
+        entries: &mut PdoMapping,
+        assigned: impl Fn(u8) -> bool,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
+        self.pdos(PdoType::Tx, entries, assigned).await
     }
 
     /// Receive PDOs (from device's perspective) - outputs
-    pub(crate) async fn maindevice_write_pdos(&self) -> Result<heapless::Vec<Pdo, 64>, Error> {
-        self.pdos(PdoType::Rx).await
+    pub(crate) async fn maindevice_write_pdos(
+        &self,
+        entries: &mut PdoMapping,
+        assigned: impl Fn(u8) -> bool,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
+        self.pdos(PdoType::Rx, entries, assigned).await
     }
 
     /// Find a string in the device EEPROM.
@@ -649,8 +678,16 @@ mod tests {
     async fn subdevice_no_pdos() {
         let e = SubDeviceEeprom::new(EepromFile::new("dumps/eeprom/ek1100.hex"));
 
-        assert_eq!(e.maindevice_read_pdos().await, Ok(heapless::Vec::new()));
-        assert_eq!(e.maindevice_write_pdos().await, Ok(heapless::Vec::new()));
+        assert_eq!(
+            e.maindevice_read_pdos(&mut PdoMapping::new(), |_| true)
+                .await,
+            Ok(heapless::Vec::new())
+        );
+        assert_eq!(
+            e.maindevice_write_pdos(&mut PdoMapping::new(), |_| true)
+                .await,
+            Ok(heapless::Vec::new())
+        );
     }
 
     #[tokio::test]
@@ -709,11 +746,42 @@ mod tests {
             pdo(0x1607, 13, 0x7070),
         ];
 
-        assert_eq!(e.maindevice_read_pdos().await, Ok(heapless::Vec::new()));
+        let mut output_entries = PdoMapping::new();
+
+        assert_eq!(
+            e.maindevice_read_pdos(&mut PdoMapping::new(), |_| true)
+                .await,
+            Ok(heapless::Vec::new())
+        );
         pretty_assertions::assert_eq!(
-            e.maindevice_write_pdos().await,
+            e.maindevice_write_pdos(&mut output_entries, |_| true).await,
             Ok(heapless::Vec::from_slice(&output_pdos).unwrap())
         );
+        #[cfg(feature = "pdo-mapping")]
+        assert_eq!(
+            output_entries.entries(),
+            (0..8)
+                .map(|channel| PdoMappingEntry::new(0x7000 + channel * 0x10, 1, 1))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn unassigned_pdos_not_stored() {
+        let e = SubDeviceEeprom::new(EepromFile::new("dumps/eeprom/el2828.hex"));
+
+        let mut output_entries = PdoMapping::new();
+
+        let pdos = e
+            .maindevice_write_pdos(&mut output_entries, |sync_manager| sync_manager == 1)
+            .await
+            .expect("PDOs");
+
+        // PDOs are still returned so their lengths can be used, but none are assigned to SM1
+        assert_eq!(pdos.len(), 8);
+        assert!(output_entries.entries().is_empty());
+        #[cfg(feature = "pdo-mapping")]
+        assert!(output_entries.is_complete());
     }
 
     // This exercises the "read from a specific address" codepath as opposed to the "find a category
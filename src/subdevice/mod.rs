@@ -1,9 +1,12 @@
 pub(crate) mod configuration;
-mod dc;
+pub(crate) mod dc;
 mod eeprom;
 pub mod pdi;
+pub(crate) mod pdo_mapping;
 pub mod ports;
+mod topology;
 mod types;
+mod watchdog;
 
 use crate::{
     al_control::AlControl,
@@ -15,14 +18,15 @@ use crate::{
     command::Command,
     dl_status::DlStatus,
     eeprom::{device_reader::DeviceEeprom, types::SiiOwner},
-    error::{Error, MailboxError, PduError},
+    error::{Error, MailboxError, PdiLayoutError, PduError},
+    events::Event,
     fmt,
     mailbox::{MailboxHeader, MailboxType},
     maindevice::MainDevice,
     pdu_loop::ReceivedPdu,
     register::{DcSupport, RegisterAddress, SupportFlags},
     subdevice::{ports::Ports, types::SubDeviceConfig},
-    subdevice_state::SubDeviceState,
+    subdevice_state::{StateRequest, SubDeviceState},
     timer_factory::IntoTimeout,
     WrappedRead, WrappedWrite,
 };
@@ -41,7 +45,11 @@ pub use self::pdi::SubDevicePdi;
 pub use self::types::IoRanges;
 pub use self::types::SubDeviceIdentity;
 use self::{eeprom::SubDeviceEeprom, types::Mailbox};
-pub use dc::DcSync;
+pub use dc::{DcSync, DcSyncTiming};
+pub use pdo_mapping::{PdoMapping, PdoMappingEntry};
+pub use ports::Topology;
+pub use topology::{NetworkTopology, SubDevicePort, SubDeviceTopology};
+pub use watchdog::{WatchdogConfig, WatchdogStatus};
 
 /// SubDevice device metadata. See [`SubDeviceRef`] for richer behaviour.
 #[doc(alias = "Slave")]
@@ -77,6 +85,9 @@ pub struct SubDevice {
     /// For the first SubDevice in the network, this will always be `None`.
     pub(crate) parent_index: Option<u16>,
 
+    /// The port number on the parent SubDevice this SubDevice is connected to.
+    pub(crate) parent_port: Option<u8>,
+
     /// Propagation delay in nanoseconds.
     ///
     /// `u32::MAX` gives a maximum propagation delay of ~4.2 seconds for the last SubDevice in the
@@ -88,6 +99,12 @@ pub struct SubDevice {
 
     /// DC config.
     pub(crate) dc_sync: DcSync,
+
+    /// Per-SubDevice DC timing overrides.
+    pub(crate) dc_sync_timing: DcSyncTiming,
+
+    /// Watchdog configuration, overriding the group configuration if set.
+    pub(crate) watchdog: Option<WatchdogConfig>,
 }
 
 // Only required for tests, also doesn't make much sense - consumers of EtherCrab should be
@@ -106,8 +123,11 @@ impl PartialEq for SubDevice {
             && self.dc_receive_time == other.dc_receive_time
             && self.index == other.index
             && self.parent_index == other.parent_index
+            && self.parent_port == other.parent_port
             && self.propagation_delay == other.propagation_delay
             && self.dc_sync == other.dc_sync
+            && self.dc_sync_timing == other.dc_sync_timing
+            && self.watchdog == other.watchdog
         // NOTE: No mailbox_counter
     }
 }
@@ -128,8 +148,11 @@ impl Clone for SubDevice {
             dc_receive_time: self.dc_receive_time,
             index: self.index,
             parent_index: self.parent_index,
+            parent_port: self.parent_port,
             propagation_delay: self.propagation_delay,
             dc_sync: self.dc_sync,
+            dc_sync_timing: self.dc_sync_timing,
+            watchdog: self.watchdog,
             mailbox_counter: AtomicU8::new(self.mailbox_counter.load(Ordering::Acquire)),
         }
     }
@@ -153,7 +176,10 @@ impl SubDevice {
             SubDeviceState::Init
         );
 
-        subdevice_ref.wait_for_state(SubDeviceState::Init).await?;
+        // The reset during init acknowledged any AL errors
+        subdevice_ref
+            .wait_for_state(StateRequest::new(SubDeviceState::Init).acknowledge_error())
+            .await?;
 
         // Make sure master has access to SubDevice EEPROM
         subdevice_ref.set_eeprom_mode(SiiOwner::Master).await?;
@@ -213,21 +239,40 @@ impl SubDevice {
         );
 
         Ok(Self {
+            identity,
+            name,
+            ..Self::from_registers(index, configured_address, alias_address, flags, ports)
+        })
+    }
+
+    /// Create a SubDevice from the information held in its registers, without any EEPROM data or
+    /// configuration.
+    pub(crate) fn from_registers(
+        index: u16,
+        configured_address: u16,
+        alias_address: u16,
+        flags: SupportFlags,
+        ports: Ports,
+    ) -> Self {
+        Self {
             configured_address,
             alias_address,
             config: SubDeviceConfig::default(),
             index,
             parent_index: None,
+            parent_port: None,
             propagation_delay: 0,
             dc_receive_time: 0,
-            identity,
-            name,
+            identity: SubDeviceIdentity::default(),
+            name: heapless::String::new(),
             flags,
             ports,
             dc_sync: DcSync::Disabled,
+            dc_sync_timing: DcSyncTiming::default(),
+            watchdog: None,
             // 0 is a reserved value, so we initialise the cycle at 1. The cycle repeats 1 - 7.
             mailbox_counter: AtomicU8::new(1),
-        })
+        }
     }
 
     /// Get the SubDevice's human readable short name.
@@ -276,6 +321,31 @@ impl SubDevice {
         self.propagation_delay
     }
 
+    /// Get this SubDevice's position in the network tree, port states and DC propagation delay.
+    ///
+    /// Note that before [`MainDevice::init`](crate::MainDevice::init) is called, parent and
+    /// propagation delay information will not be populated.
+    pub fn topology(&self) -> SubDeviceTopology {
+        SubDeviceTopology::new(self)
+    }
+
+    /// Get the objects mapped into this SubDevice's inputs.
+    ///
+    /// The mapping is discovered when the SubDevice's FMMUs are configured, i.e. in
+    /// [`SubDeviceGroup::into_pre_op_pdi`](crate::SubDeviceGroup::into_pre_op_pdi) or
+    /// [`SubDeviceGroup::into_safe_op`](crate::SubDeviceGroup::into_safe_op). Before then, the
+    /// mapping will be empty and not [complete](PdoMapping::is_complete).
+    pub fn input_pdo_mapping(&self) -> &PdoMapping {
+        &self.config.input_mapping
+    }
+
+    /// Get the objects mapped into this SubDevice's outputs.
+    ///
+    /// See [`input_pdo_mapping`](SubDevice::input_pdo_mapping) for more information.
+    pub fn output_pdo_mapping(&self) -> &PdoMapping {
+        &self.config.output_mapping
+    }
+
     /// Distributed Clock (DC) support.
     pub fn dc_support(&self) -> DcSupport {
         self.flags.dc_support()
@@ -344,6 +414,24 @@ where
     pub fn set_dc_sync(&mut self, dc_sync: DcSync) {
         self.state.dc_sync = dc_sync;
     }
+
+    /// Set per-SubDevice DC SYNC0/SYNC1 shift and pulse length overrides.
+    ///
+    /// Like [`set_dc_sync`](SubDeviceRef::set_dc_sync), this only stores the configuration. It is
+    /// validated against the group SYNC0 period and written to the SubDevice by
+    /// [`SubDeviceGroup::configure_dc_sync`](crate::SubDeviceGroup::configure_dc_sync).
+    pub fn set_dc_sync_timing(&mut self, timing: DcSyncTiming) {
+        self.state.dc_sync_timing = timing;
+    }
+
+    /// Set the process data and PDI watchdog configuration for this SubDevice.
+    ///
+    /// This overrides any configuration set for the whole group with
+    /// [`SubDeviceGroup::set_watchdog`](crate::SubDeviceGroup::set_watchdog), and is written to
+    /// the SubDevice during the PRE-OP -> SAFE-OP transition.
+    pub fn set_watchdog(&mut self, config: WatchdogConfig) {
+        self.state.watchdog = Some(config);
+    }
 }
 
 impl<'a, S> SubDeviceRef<'a, S>
@@ -355,6 +443,11 @@ where
         self.state.name.as_str()
     }
 
+    /// Attach this SubDevice's address and name to an error.
+    pub(crate) fn error_context(&self, error: Error) -> Error {
+        error.with_subdevice(self.configured_address, self.name())
+    }
+
     /// Get the long name of the SubDevice.
     ///
     /// Using the EK1100 as an example, [`SubDeviceRef::name`] will return `"EK1100"` wherease this
@@ -363,7 +456,7 @@ where
     /// In the case that a SubDevice does not have a description, this method will return
     /// `Ok(None)`.
     pub async fn description(&self) -> Result<Option<heapless::String<64>>, Error> {
-        SubDevice::description(&self.state, &self.maindevice).await
+        SubDevice::description(&self.state, self.maindevice).await
     }
 
     /// Get additional identifying details for the SubDevice.
@@ -384,6 +477,81 @@ where
         self.state.propagation_delay
     }
 
+    /// Get this SubDevice's position in the network tree, port states and DC propagation delay.
+    ///
+    /// See [`SubDevice::topology`] for more information.
+    pub fn topology(&self) -> SubDeviceTopology {
+        SubDeviceTopology::new(&self.state)
+    }
+
+    /// Get the objects mapped into this SubDevice's inputs.
+    ///
+    /// See [`SubDevice::input_pdo_mapping`] for more information.
+    pub fn input_pdo_mapping(&self) -> &PdoMapping {
+        &self.state.config.input_mapping
+    }
+
+    /// Get the objects mapped into this SubDevice's outputs.
+    ///
+    /// See [`SubDevice::input_pdo_mapping`] for more information.
+    pub fn output_pdo_mapping(&self) -> &PdoMapping {
+        &self.state.config.output_mapping
+    }
+
+    /// Check that this SubDevice's discovered PDO mapping is exactly `inputs` and `outputs`.
+    ///
+    /// Call this once the group has reached PRE-OP with PDI, SAFE-OP or OP to make sure the process data layout
+    /// assumed by the application, e.g. with [`inputs_view`](SubDeviceRef::inputs_view), matches
+    /// what the SubDevice actually sends. This catches changes to default PDOs from firmware
+    /// updates or replaced hardware before any garbage values are read.
+    ///
+    /// The mapping is only retained with the `pdo-mapping` feature. Without it, this method
+    /// returns [`PdoMappingError::Unavailable`](crate::error::PdoMappingError::Unavailable).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::SafeOp, PdoMappingEntry};
+    /// # fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64, SafeOp>) -> Result<(), Error> {
+    /// const SERVO_INPUTS: &[PdoMappingEntry] = &[
+    ///     // Status word
+    ///     PdoMappingEntry::new(0x6041, 0, 16),
+    ///     // Position actual value
+    ///     PdoMappingEntry::new(0x6064, 0, 32),
+    /// ];
+    ///
+    /// const SERVO_OUTPUTS: &[PdoMappingEntry] = &[
+    ///     // Control word
+    ///     PdoMappingEntry::new(0x6040, 0, 16),
+    ///     PdoMappingEntry::padding(16),
+    /// ];
+    ///
+    /// let servo = group.subdevice(maindevice, 0)?;
+    ///
+    /// servo.validate_pdo_mapping(SERVO_INPUTS, SERVO_OUTPUTS)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_pdo_mapping(
+        &self,
+        inputs: &[PdoMappingEntry],
+        outputs: &[PdoMappingEntry],
+    ) -> Result<(), Error> {
+        self.state
+            .config
+            .input_mapping
+            .validate(inputs)
+            .map_err(PdiLayoutError::InputMapping)?;
+
+        self.state
+            .config
+            .output_mapping
+            .validate(outputs)
+            .map_err(PdiLayoutError::OutputMapping)?;
+
+        Ok(())
+    }
+
     /// Distributed Clock (DC) support.
     pub fn dc_support(&self) -> DcSupport {
         self.state.flags.dc_support()
@@ -393,6 +561,66 @@ where
         self.state.dc_sync
     }
 
+    pub(crate) fn dc_sync_timing(&self) -> DcSyncTiming {
+        self.state.dc_sync_timing
+    }
+
+    pub(crate) fn watchdog(&self) -> Option<WatchdogConfig> {
+        self.state.watchdog
+    }
+
+    /// Find this SubDevice by its position in the network and restore its configured station
+    /// address, e.g. after it was power cycled.
+    ///
+    /// Returns `false` if no SubDevice responds at this position, or if the SubDevice there is
+    /// already addressed or has a different identity.
+    pub(crate) async fn restore_address(&self) -> Result<bool, Error> {
+        let position = self.state.index;
+
+        let response = Command::aprd(position, RegisterAddress::ConfiguredStationAddress.into())
+            .ignore_wkc()
+            .receive_slice(self.maindevice, u16::PACKED_LEN as u16)
+            .await?;
+
+        if response.working_counter == 0 {
+            return Ok(false);
+        }
+
+        let address = u16::unpack_from_slice(&response)?;
+
+        if address != 0 {
+            fmt::warn!(
+                "SubDevice at position {} has address {:#06x}, expected {:#06x} or none",
+                position,
+                address,
+                self.configured_address
+            );
+
+            return Ok(false);
+        }
+
+        Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+            .send(self.maindevice, self.configured_address)
+            .await?;
+
+        self.set_eeprom_mode(SiiOwner::Master).await?;
+
+        let identity = self.eeprom().identity().await?;
+
+        if identity != self.state.identity {
+            fmt::warn!(
+                "SubDevice at position {} has identity {}, expected {}",
+                position,
+                identity,
+                self.state.identity
+            );
+
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
     /// Return the current cyclic mailbox counter value, from 0-7.
     ///
     /// Calling this method internally increments the counter, so subequent calls will produce a new
@@ -442,10 +670,7 @@ where
         // Ensure SubDevice OUT (master IN) mailbox is empty. We'll retry this multiple times in
         // case the SubDevice is still busy or bugged or something.
         for i in 0..10 {
-            let sm_status = self
-                .read(mailbox_read_sm_status)
-                .receive::<crate::sync_manager_channel::Status>(self.maindevice)
-                .await?;
+            let sm_status = self.mailbox_sm_status(mailbox_read_sm_status).await?;
 
             // If flag is set, read entire mailbox to clear it
             if sm_status.mailbox_full {
@@ -476,10 +701,7 @@ where
         // Wait for SubDevice IN mailbox to be available to receive data from master
         async {
             loop {
-                let sm_status = self
-                    .read(mailbox_write_sm_status)
-                    .receive::<crate::sync_manager_channel::Status>(self.maindevice)
-                    .await?;
+                let sm_status = self.mailbox_sm_status(mailbox_write_sm_status).await?;
 
                 if !sm_status.mailbox_full {
                     break Ok(());
@@ -503,6 +725,21 @@ where
         Ok((read_mailbox, write_mailbox))
     }
 
+    /// Read the status of a mailbox SyncManager, sending the read in a cyclic frame if enabled with
+    /// [`MainDevice::set_piggyback_mailbox_polling`].
+    async fn mailbox_sm_status(
+        &self,
+        register: u16,
+    ) -> Result<crate::sync_manager_channel::Status, Error> {
+        let read = self.read(register);
+
+        if self.maindevice.piggyback_mailbox_polling() {
+            read.receive_piggyback(self.maindevice).await
+        } else {
+            read.receive(self.maindevice).await
+        }
+    }
+
     /// Wait for a mailbox response
     async fn coe_response(&self, read_mailbox: &Mailbox) -> Result<ReceivedPdu, Error> {
         let mailbox_read_sm = RegisterAddress::sync_manager_status(read_mailbox.sync_manager);
@@ -510,10 +747,7 @@ where
         // Wait for SubDevice OUT mailbox to be ready
         async {
             loop {
-                let sm_status = self
-                    .read(mailbox_read_sm)
-                    .receive::<crate::sync_manager_channel::Status>(self.maindevice)
-                    .await?;
+                let sm_status = self.mailbox_sm_status(mailbox_read_sm).await?;
 
                 if sm_status.mailbox_full {
                     break Ok(());
@@ -627,10 +861,16 @@ where
                 decoded.extra_data
             );
 
-            return Err(Error::Mailbox(MailboxError::Emergency {
+            self.maindevice.publish(Event::Emergency {
+                configured_address: self.configured_address,
+                error_code: decoded.error_code,
+                error_register: decoded.error_register,
+            });
+
+            Err(Error::Mailbox(MailboxError::Emergency {
                 error_code: decoded.error_code,
                 error_register: decoded.error_register,
-            }));
+            }))
         } else if headers.command == CoeCommand::Abort {
             let code = CoeAbortCode::Incompatible;
 
@@ -704,7 +944,10 @@ where
 
         fmt::trace!("CoE download");
 
-        let (_response, _data) = self.send_coe_service(request).await?;
+        let (_response, _data) = self
+            .send_coe_service(request)
+            .await
+            .map_err(|e| self.error_context(e))?;
 
         // TODO: Validate reply?
 
@@ -791,7 +1034,10 @@ where
 
         fmt::trace!("CoE upload {:#06x} {:?}", index, sub_index);
 
-        let (headers, response) = self.send_coe_service(request).await?;
+        let (headers, response) = self
+            .send_coe_service(request)
+            .await
+            .map_err(|e| self.error_context(e))?;
         let data: &[u8] = &response;
 
         // Expedited transfers where the data is 4 bytes or less long, denoted in the SDO header
@@ -821,7 +1067,10 @@ where
 
         fmt::trace!("CoE upload {:#06x} {:?}", index, sub_index);
 
-        let (headers, response) = self.send_coe_service(request).await?;
+        let (headers, response) = self
+            .send_coe_service(request)
+            .await
+            .map_err(|e| self.error_context(e))?;
         let data: &[u8] = &response;
 
         // Expedited transfers where the data is 4 bytes or less long, denoted in the SDO header
@@ -862,7 +1111,10 @@ where
 
                     fmt::trace!("CoE upload segmented");
 
-                    let (headers, data) = self.send_coe_service(request).await?;
+                    let (headers, data) = self
+                        .send_coe_service(request)
+                        .await
+                        .map_err(|e| self.error_context(e))?;
 
                     // The spec defines the data length as n-3, so we'll just go with that magic
                     // number...
@@ -961,10 +1213,50 @@ impl<'a, S> SubDeviceRef<'a, S> {
         futures_lite::future::try_zip(self.state(), code).await
     }
 
-    fn eeprom(&self) -> SubDeviceEeprom<DeviceEeprom> {
+    pub(crate) fn eeprom(&self) -> SubDeviceEeprom<DeviceEeprom> {
         SubDeviceEeprom::new(DeviceEeprom::new(self.maindevice, self.configured_address))
     }
 
+    /// Read the SyncManager watchdog status and the SyncManager and PDI watchdog expiry counters.
+    pub async fn watchdog_status(&self) -> Result<WatchdogStatus, Error> {
+        self.read(RegisterAddress::SyncManagerWatchdogStatus)
+            .receive::<WatchdogStatus>(self.maindevice)
+            .await
+    }
+
+    /// Write a watchdog configuration to the SubDevice.
+    pub(crate) async fn configure_watchdog(&self, config: WatchdogConfig) -> Result<(), Error> {
+        let divider = config.divider()?;
+        let sync_manager = config.sync_manager_ticks()?;
+        let pdi = config.pdi_ticks()?;
+
+        fmt::debug!(
+            "SubDevice {:#06x} watchdog divider {}, SM watchdog {:?}, PDI watchdog {:?}",
+            self.configured_address,
+            divider,
+            sync_manager,
+            pdi
+        );
+
+        self.write(RegisterAddress::WatchdogDivider)
+            .send(self.maindevice, divider)
+            .await?;
+
+        if let Some(sync_manager) = sync_manager {
+            self.write(RegisterAddress::SyncManagerWatchdog)
+                .send(self.maindevice, sync_manager)
+                .await?;
+        }
+
+        if let Some(pdi) = pdi {
+            self.write(RegisterAddress::PdiWatchdog)
+                .send(self.maindevice, pdi)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     /// Read a register.
     ///
     /// Note that while this method is marked safe, raw alterations to SubDevice config or behaviour can
@@ -989,8 +1281,12 @@ impl<'a, S> SubDeviceRef<'a, S> {
             .await
     }
 
-    pub(crate) async fn wait_for_state(&self, desired_state: SubDeviceState) -> Result<(), Error> {
-        async {
+    /// Wait for the SubDevice to reach the requested state.
+    ///
+    /// Unless the request acknowledges errors, this returns early with the SubDevice's AL status
+    /// code if it sets the error flag instead of reaching the requested state.
+    pub(crate) async fn wait_for_state(&self, request: StateRequest) -> Result<(), Error> {
+        let result = async {
             loop {
                 let status = self
                     .read(RegisterAddress::AlStatus)
@@ -998,15 +1294,67 @@ impl<'a, S> SubDeviceRef<'a, S> {
                     .receive::<AlControl>(self.maindevice)
                     .await?;
 
-                if status.state == desired_state {
+                if status.state == request.state {
+                    self.maindevice.publish(Event::StateChange {
+                        configured_address: self.configured_address,
+                        state: status.state,
+                    });
+
                     break Ok(());
                 }
 
+                // An acknowledged error can take a few cycles to clear, so only a rejection of an
+                // unacknowledged request is treated as final.
+                if status.error && !request.acknowledge {
+                    break Err(self.al_error(request.state, status.state).await);
+                }
+
                 self.maindevice.timeouts.loop_tick().await;
             }
         }
         .timeout(self.maindevice.timeouts.state_transition)
-        .await
+        .await;
+
+        match result {
+            Err(Error::Timeout) => {
+                let status = self
+                    .read(RegisterAddress::AlStatus)
+                    .receive::<AlControl>(self.maindevice)
+                    .await?;
+
+                if status.error {
+                    Err(self.al_error(request.state, status.state).await)
+                } else {
+                    Err(Error::Timeout)
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Read the AL status code of a SubDevice that failed to transition to the desired state.
+    async fn al_error(&self, desired_state: SubDeviceState, state: SubDeviceState) -> Error {
+        let code = self
+            .read(RegisterAddress::AlStatusCode)
+            .receive::<AlStatusCode>(self.maindevice)
+            .await
+            .unwrap_or(AlStatusCode::UnspecifiedError);
+
+        fmt::error!(
+            "SubDevice {:#06x} failed to transition from {} to {}: {}",
+            self.configured_address,
+            state,
+            desired_state,
+            code,
+        );
+
+        self.maindevice.publish(Event::AlStatus {
+            configured_address: self.configured_address,
+            state,
+            status_code: code,
+        });
+
+        Error::SubDevice(code)
     }
 
     pub(crate) fn write(&self, register: impl Into<u16>) -> WrappedWrite {
@@ -1017,48 +1365,81 @@ impl<'a, S> SubDeviceRef<'a, S> {
         Command::fprd(self.configured_address, register.into())
     }
 
-    pub(crate) async fn request_subdevice_state_nowait(
+    /// Request a new AL state without waiting for the SubDevice to reach it.
+    ///
+    /// Pass a [`StateRequest`] with [`acknowledge_error`](StateRequest::acknowledge_error) set to
+    /// also clear a pending AL error. Use [`status`](SubDeviceRef::status) to check whether the
+    /// transition succeeded.
+    ///
+    /// This does not change the state of the [`SubDeviceGroup`](crate::SubDeviceGroup) the
+    /// SubDevice belongs to.
+    pub async fn request_state_nowait(
         &self,
-        desired_state: SubDeviceState,
+        request: impl Into<StateRequest>,
     ) -> Result<(), Error> {
+        let request = request.into();
+
         fmt::debug!(
-            "Set state {} for SubDevice address {:#04x}",
-            desired_state,
+            "Set state {} (acknowledge {:?}) for SubDevice address {:#04x}",
+            request.state,
+            request.acknowledge,
             self.configured_address
         );
 
-        // Send state request
-        let response = self
-            .write(RegisterAddress::AlControl)
-            .send_receive::<AlControl>(self.maindevice, AlControl::new(desired_state))
+        self.write(RegisterAddress::AlControl)
+            .send_receive_slice(self.maindevice, AlControl::from(request))
             .await?;
 
-        if response.error {
-            let error = self
-                .read(RegisterAddress::AlStatus)
-                .receive::<AlStatusCode>(self.maindevice)
-                .await?;
-
-            fmt::error!(
-                "Error occurred transitioning SubDevice {:#06x} to {:?}: {}",
-                self.configured_address,
-                desired_state,
-                error,
-            );
-
-            return Err(Error::StateTransition);
-        }
-
         Ok(())
     }
 
-    pub(crate) async fn request_subdevice_state(
-        &self,
-        desired_state: SubDeviceState,
-    ) -> Result<(), Error> {
-        self.request_subdevice_state_nowait(desired_state).await?;
+    /// Request a new AL state and wait for the SubDevice to reach it.
+    ///
+    /// If the SubDevice sets its error flag instead, [`Error::SubDevice`] is returned with the AL
+    /// status code read from the SubDevice. [`Error::Timeout`] is returned if the SubDevice did not
+    /// reach the state within [`Timeouts::state_transition`](crate::Timeouts::state_transition)
+    /// without reporting an error.
+    ///
+    /// This does not change the state of the [`SubDeviceGroup`](crate::SubDeviceGroup) the
+    /// SubDevice belongs to.
+    ///
+    /// # Examples
+    ///
+    /// Acknowledge an AL error and return a SubDevice to SAFE-OP:
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{
+    /// #     error::Error, std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, StateRequest,
+    /// #     SubDeviceState, Timeouts,
+    /// # };
+    /// # static PDU_STORAGE: PduStorage<8, 32> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// let mut group = maindevice
+    ///     .init_single_group::<8, 8>(ethercat_now)
+    ///     .await?
+    ///     .into_op(&maindevice)
+    ///     .await?;
+    ///
+    /// for subdevice in group.iter(&maindevice) {
+    ///     let (state, _code) = subdevice.status().await?;
+    ///
+    ///     if state != SubDeviceState::Op {
+    ///         subdevice
+    ///             .request_state(StateRequest::new(SubDeviceState::SafeOp).acknowledge_error())
+    ///             .await?;
+    ///     }
+    /// }
+    /// # Ok::<(), Error>(())
+    /// # };
+    /// ```
+    pub async fn request_state(&self, request: impl Into<StateRequest>) -> Result<(), Error> {
+        let request = request.into();
+
+        self.request_state_nowait(request).await?;
 
-        self.wait_for_state(desired_state).await
+        self.wait_for_state(request).await
     }
 
     pub(crate) async fn set_eeprom_mode(&self, mode: SiiOwner) -> Result<(), Error> {
@@ -1,6 +1,8 @@
 use super::{SubDevice, SubDeviceRef};
+use crate::error::{Error, PdiLayoutError};
 use atomic_refcell::AtomicRefMut;
 use core::ops::Deref;
+use ethercrab_wire::EtherCrabWireView;
 
 /// Process Data Image (PDI) segments for a given SubDevice.
 ///
@@ -121,4 +123,121 @@ impl<'a, 'group> SubDeviceRef<'a, SubDevicePdi<'group>> {
     pub fn outputs_raw_mut(&mut self) -> &mut [u8] {
         self.state.outputs
     }
+
+    /// Get a typed, read-only view of this SubDevice's inputs.
+    ///
+    /// The layout `T` is usually described with the
+    /// [`EtherCrabWireView`](ethercrab_wire::EtherCrabWireView) derive. An error is returned if
+    /// `T` is not exactly the same length as the SubDevice's input data.
+    ///
+    /// Only the length of the layout is checked here. Use
+    /// [`validate_pdo_mapping`](SubDeviceRef::validate_pdo_mapping) at startup to also check the
+    /// objects mapped by the SubDevice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::Op};
+    /// #[derive(ethercrab_wire::EtherCrabWireView)]
+    /// #[wire(bytes = 3)]
+    /// struct Inputs {
+    ///     #[wire(bits = 1)]
+    ///     ready: bool,
+    ///     #[wire(bits = 1, post_skip = 6)]
+    ///     fault: bool,
+    ///     #[wire(bytes = 2)]
+    ///     position: i16,
+    /// }
+    ///
+    /// # fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let subdevice = group.subdevice(maindevice, 0)?;
+    ///
+    /// let inputs = subdevice.inputs_view::<Inputs>()?;
+    ///
+    /// if inputs.ready() && !inputs.fault() {
+    ///     log::info!("Position {}", inputs.position());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inputs_view<T>(&self) -> Result<T::View<'_>, Error>
+    where
+        T: EtherCrabWireView,
+    {
+        check_len(self.state.inputs, T::VIEW_LEN).map_err(|actual| {
+            PdiLayoutError::InputLength {
+                expected: T::VIEW_LEN,
+                actual,
+            }
+        })?;
+
+        T::view(self.state.inputs).map_err(Error::from)
+    }
+
+    /// Get a typed, read-only view of this SubDevice's outputs.
+    ///
+    /// An error is returned if `T` is not exactly the same length as the SubDevice's output data.
+    pub fn outputs_view<T>(&self) -> Result<T::View<'_>, Error>
+    where
+        T: EtherCrabWireView,
+    {
+        check_len(self.state.outputs, T::VIEW_LEN).map_err(|actual| {
+            PdiLayoutError::OutputLength {
+                expected: T::VIEW_LEN,
+                actual,
+            }
+        })?;
+
+        T::view(self.state.outputs).map_err(Error::from)
+    }
+
+    /// Get a typed, mutable view of this SubDevice's outputs.
+    ///
+    /// An error is returned if `T` is not exactly the same length as the SubDevice's output data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::Op};
+    /// #[derive(ethercrab_wire::EtherCrabWireView)]
+    /// #[wire(bytes = 3)]
+    /// struct Outputs {
+    ///     #[wire(bits = 1, post_skip = 7)]
+    ///     enable: bool,
+    ///     #[wire(bytes = 2)]
+    ///     target_velocity: i16,
+    /// }
+    ///
+    /// # fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let mut subdevice = group.subdevice(maindevice, 0)?;
+    ///
+    /// let mut outputs = subdevice.outputs_view_mut::<Outputs>()?;
+    ///
+    /// outputs.set_enable(true);
+    /// outputs.set_target_velocity(-500);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn outputs_view_mut<T>(&mut self) -> Result<T::ViewMut<'_>, Error>
+    where
+        T: EtherCrabWireView,
+    {
+        check_len(self.state.outputs, T::VIEW_LEN).map_err(|actual| {
+            PdiLayoutError::OutputLength {
+                expected: T::VIEW_LEN,
+                actual,
+            }
+        })?;
+
+        T::view_mut(self.state.outputs).map_err(Error::from)
+    }
+}
+
+/// Returns the actual length of `buf` as an error if it is not exactly `expected` bytes long.
+fn check_len(buf: &[u8], expected: usize) -> Result<(), usize> {
+    if buf.len() == expected {
+        Ok(())
+    } else {
+        Err(buf.len())
+    }
 }
@@ -0,0 +1,219 @@
+//! PDO mappings discovered during SubDevice configuration.
+
+use crate::{error::PdoMappingError, fmt};
+
+/// The maximum number of mapping entries stored for each of a SubDevice's inputs and outputs.
+#[cfg(feature = "pdo-mapping")]
+pub(crate) const MAX_PDO_ENTRIES: usize = 64;
+
+/// Mappings are not retained without the `pdo-mapping` feature, so take no space in each
+/// SubDevice.
+#[cfg(not(feature = "pdo-mapping"))]
+pub(crate) const MAX_PDO_ENTRIES: usize = 0;
+
+/// An object mapped into a SubDevice's process data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PdoMappingEntry {
+    /// Object index, e.g. `0x6041` for a DS402 status word.
+    pub index: u16,
+
+    /// Object sub-index.
+    pub sub_index: u8,
+
+    /// Length of the object in the process data in bits.
+    pub bit_len: u8,
+}
+
+impl PdoMappingEntry {
+    /// Create a new mapping entry.
+    pub const fn new(index: u16, sub_index: u8, bit_len: u8) -> Self {
+        Self {
+            index,
+            sub_index,
+            bit_len,
+        }
+    }
+
+    /// A gap of `bit_len` bits in the process data.
+    ///
+    /// Padding is mapped to object index `0x0000`.
+    pub const fn padding(bit_len: u8) -> Self {
+        Self::new(0x0000, 0, bit_len)
+    }
+
+    /// Returns `true` if this entry is padding and not a real object.
+    pub fn is_padding(&self) -> bool {
+        self.index == 0x0000
+    }
+}
+
+/// The objects mapped into either a SubDevice's inputs or its outputs, in process data order.
+///
+/// The mapping is read from CoE if the SubDevice supports it, or from the SubDevice EEPROM
+/// otherwise, while the group transitions into PRE-OP.
+///
+/// Mappings are only retained if the `pdo-mapping` feature is enabled. Without it, every mapping
+/// is empty and never [complete](PdoMapping::is_complete), saving about 0.5 KiB per SubDevice.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PdoMapping {
+    entries: heapless::Vec<PdoMappingEntry, MAX_PDO_ENTRIES>,
+    /// `false` if the mapping has not been read yet, or it had more than [`MAX_PDO_ENTRIES`]
+    /// entries.
+    pub(crate) complete: bool,
+}
+
+impl PdoMapping {
+    /// Create an empty mapping ready to be populated.
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: heapless::Vec::new(),
+            complete: cfg!(feature = "pdo-mapping"),
+        }
+    }
+
+    pub(crate) fn push(&mut self, entry: PdoMappingEntry) {
+        if self.entries.push(entry).is_err() {
+            // Never complete without the `pdo-mapping` feature, so this is only logged with it
+            if self.complete {
+                fmt::warn!(
+                    "More than {} PDO mapping entries, mapping will not be available",
+                    MAX_PDO_ENTRIES
+                );
+            }
+
+            self.complete = false;
+        }
+    }
+
+    /// Mapped objects in the order they appear in the process data.
+    ///
+    /// This may not hold every entry if [`is_complete`](PdoMapping::is_complete) returns `false`.
+    pub fn entries(&self) -> &[PdoMappingEntry] {
+        &self.entries
+    }
+
+    /// Returns `true` if every mapping entry was discovered and stored.
+    ///
+    /// This is `false` before the SubDevice has been configured, if the SubDevice maps more than 64
+    /// objects in this direction, or if the `pdo-mapping` feature is disabled.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Total length of all mapped objects in bits.
+    pub fn bit_len(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| usize::from(entry.bit_len))
+            .sum()
+    }
+
+    /// Check that this mapping is exactly the same as `expected`, including padding.
+    pub fn validate(&self, expected: &[PdoMappingEntry]) -> Result<(), PdoMappingError> {
+        if !self.complete {
+            return Err(PdoMappingError::Unavailable);
+        }
+
+        for position in 0..expected.len().max(self.entries.len()) {
+            match (expected.get(position), self.entries.get(position)) {
+                (Some(expected), Some(actual)) if expected != actual => {
+                    return Err(PdoMappingError::Mismatch {
+                        position,
+                        expected: *expected,
+                        actual: *actual,
+                    });
+                }
+                (Some(expected), None) => {
+                    return Err(PdoMappingError::Missing {
+                        position,
+                        expected: *expected,
+                    });
+                }
+                (None, Some(actual)) => {
+                    return Err(PdoMappingError::Unexpected {
+                        position,
+                        actual: *actual,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "pdo-mapping"))]
+mod tests {
+    use super::*;
+
+    fn mapping(entries: &[PdoMappingEntry]) -> PdoMapping {
+        let mut mapping = PdoMapping::new();
+
+        entries.iter().for_each(|entry| mapping.push(*entry));
+
+        mapping
+    }
+
+    const STATUS_WORD: PdoMappingEntry = PdoMappingEntry::new(0x6041, 0, 16);
+    const POSITION: PdoMappingEntry = PdoMappingEntry::new(0x6064, 0, 32);
+
+    #[test]
+    fn validate_ok() {
+        let m = mapping(&[STATUS_WORD, PdoMappingEntry::padding(8), POSITION]);
+
+        assert_eq!(m.bit_len(), 56);
+        assert_eq!(
+            m.validate(&[STATUS_WORD, PdoMappingEntry::padding(8), POSITION]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn validate_mismatch() {
+        let m = mapping(&[STATUS_WORD, POSITION]);
+
+        assert_eq!(
+            m.validate(&[STATUS_WORD, PdoMappingEntry::new(0x6064, 0, 16)]),
+            Err(PdoMappingError::Mismatch {
+                position: 1,
+                expected: PdoMappingEntry::new(0x6064, 0, 16),
+                actual: POSITION
+            })
+        );
+        assert_eq!(
+            m.validate(&[STATUS_WORD]),
+            Err(PdoMappingError::Unexpected {
+                position: 1,
+                actual: POSITION
+            })
+        );
+        assert_eq!(
+            m.validate(&[STATUS_WORD, POSITION, STATUS_WORD]),
+            Err(PdoMappingError::Missing {
+                position: 2,
+                expected: STATUS_WORD
+            })
+        );
+    }
+
+    #[test]
+    fn unavailable() {
+        assert_eq!(
+            PdoMapping::default().validate(&[]),
+            Err(PdoMappingError::Unavailable)
+        );
+
+        let mut m = PdoMapping::new();
+
+        for _ in 0..=MAX_PDO_ENTRIES {
+            m.push(STATUS_WORD);
+        }
+
+        assert!(!m.is_complete());
+        assert_eq!(m.entries().len(), MAX_PDO_ENTRIES);
+        assert_eq!(m.validate(m.entries()), Err(PdoMappingError::Unavailable));
+    }
+}
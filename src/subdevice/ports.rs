@@ -26,8 +26,10 @@ impl Port {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The shape of the network at a SubDevice, based on how many of its ports are open.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Topology {
     /// The SubDevice has two open ports, with only upstream and downstream subdevices.
     Passthrough,
@@ -40,6 +42,7 @@ pub enum Topology {
 }
 
 impl Topology {
+    /// Returns `true` if the network branches at this SubDevice.
     pub fn is_junction(&self) -> bool {
         matches!(self, Self::Fork | Self::Cross)
     }
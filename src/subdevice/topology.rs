@@ -0,0 +1,267 @@
+//! Network topology and DC propagation delay information discovered during init.
+
+use super::ports::{Port, Topology};
+use crate::SubDevice;
+
+/// Information about a single port of a SubDevice.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SubDevicePort {
+    /// EtherCAT port number, `0` to `3`.
+    pub number: u8,
+
+    /// Whether the port has an open link.
+    pub active: bool,
+
+    /// DC receive time latched on this port, in nanoseconds.
+    ///
+    /// This is the SubDevice's local time and is only meaningful relative to the other ports of
+    /// the same SubDevice. It will be `0` for SubDevices without DC support.
+    pub dc_receive_time: u32,
+
+    /// The index of the SubDevice connected downstream of this port, if any.
+    pub downstream_to: Option<u16>,
+}
+
+impl From<Port> for SubDevicePort {
+    fn from(port: Port) -> Self {
+        Self {
+            number: port.number,
+            active: port.active,
+            dc_receive_time: port.dc_receive_time,
+            downstream_to: port.downstream_to,
+        }
+    }
+}
+
+/// Position of a SubDevice in the network tree along with its DC propagation delay.
+///
+/// This is computed during [`MainDevice::init`](crate::MainDevice::init) and can be retrieved with
+/// [`SubDevice::topology`] or [`SubDeviceRef::topology`](crate::SubDeviceRef::topology).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SubDeviceTopology {
+    /// Index of the SubDevice in the network, starting at `0`.
+    pub index: u16,
+
+    /// Configured station address.
+    pub configured_address: u16,
+
+    /// Index of the upstream SubDevice this one is connected to, or `None` for the first
+    /// SubDevice in the network.
+    pub parent_index: Option<u16>,
+
+    /// The port number on the parent SubDevice this SubDevice is connected to.
+    pub parent_port: Option<u8>,
+
+    /// The port number on this SubDevice that traffic from the MainDevice enters through.
+    pub entry_port: u8,
+
+    /// Network shape at this SubDevice. Forks and crosses are junctions in the network tree.
+    pub topology: Topology,
+
+    /// All four ports, in EtherCAT processing order `0`, `3`, `1`, `2`.
+    pub ports: [SubDevicePort; 4],
+
+    /// Whether this SubDevice supports Distributed Clocks.
+    ///
+    /// Propagation delays are only computed for DC-capable SubDevices.
+    pub dc_supported: bool,
+
+    /// DC system receive time latched during init, in nanoseconds.
+    pub dc_receive_time: u64,
+
+    /// The time taken for a frame to pass through all open ports of this SubDevice and everything
+    /// connected downstream of it, in nanoseconds.
+    pub loop_propagation_time: Option<u32>,
+
+    /// Propagation delay from the first SubDevice in the network to this one, in nanoseconds.
+    pub propagation_delay: u32,
+}
+
+impl SubDeviceTopology {
+    pub(crate) fn new(subdevice: &SubDevice) -> Self {
+        Self {
+            index: subdevice.index,
+            configured_address: subdevice.configured_address,
+            parent_index: subdevice.parent_index,
+            parent_port: subdevice.parent_port,
+            entry_port: subdevice.ports.entry_port().number,
+            topology: subdevice.ports.topology(),
+            ports: subdevice.ports.0.map(SubDevicePort::from),
+            dc_supported: subdevice.flags.dc_supported,
+            dc_receive_time: subdevice.dc_receive_time,
+            loop_propagation_time: subdevice.ports.total_propagation_time(),
+            propagation_delay: subdevice.propagation_delay,
+        }
+    }
+
+    /// Returns `true` if the network branches at this SubDevice.
+    pub fn is_junction(&self) -> bool {
+        self.topology.is_junction()
+    }
+}
+
+/// The topology of the whole network as computed during init, returned by
+/// [`MainDevice::topology`](crate::MainDevice::topology).
+///
+/// This is a snapshot intended to be logged or archived after commissioning, and is serializable
+/// with the `serde` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NetworkTopology<const MAX_SUBDEVICES: usize> {
+    /// Every SubDevice in the report, in network order.
+    pub subdevices: heapless::Vec<SubDeviceTopology, MAX_SUBDEVICES>,
+
+    /// The total number of SubDevices discovered during init.
+    ///
+    /// This is larger than the length of [`subdevices`](NetworkTopology::subdevices) if not every
+    /// group was included in the report.
+    pub num_subdevices: u16,
+
+    /// Configured address of the SubDevice used as the DC reference clock, if any.
+    pub dc_reference: Option<u16>,
+}
+
+impl<const MAX_SUBDEVICES: usize> NetworkTopology<MAX_SUBDEVICES> {
+    /// The first SubDevice in the network, connected directly to the MainDevice.
+    pub fn root(&self) -> Option<&SubDeviceTopology> {
+        self.subdevices
+            .iter()
+            .find(|subdevice| subdevice.parent_index.is_none())
+    }
+
+    /// The open ports of the first SubDevice in the network, in EtherCAT processing order.
+    ///
+    /// These are the ports the rest of the network hangs off, including the port connected to the
+    /// MainDevice.
+    pub fn root_ports(&self) -> impl Iterator<Item = &SubDevicePort> {
+        self.root()
+            .into_iter()
+            .flat_map(|root| root.ports.iter().filter(|port| port.active))
+    }
+
+    /// The SubDevices connected directly downstream of the SubDevice at `index`.
+    pub fn children(&self, index: u16) -> impl Iterator<Item = &SubDeviceTopology> {
+        self.subdevices
+            .iter()
+            .filter(move |subdevice| subdevice.parent_index == Some(index))
+    }
+
+    /// The SubDevices where the network branches.
+    pub fn junctions(&self) -> impl Iterator<Item = &SubDeviceTopology> {
+        self.subdevices
+            .iter()
+            .filter(|subdevice| subdevice.is_junction())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register::SupportFlags, subdevice::ports::tests::make_ports};
+
+    #[test]
+    fn from_subdevice() {
+        let subdevice = SubDevice {
+            configured_address: 0x1001,
+            index: 1,
+            parent_index: Some(0),
+            parent_port: Some(3),
+            propagation_delay: 145,
+            // EK1100 with children on port 3 and downstream devices on port 1
+            ports: make_ports(true, true, true, false).set_downstreams(
+                None,
+                Some(2),
+                Some(5),
+                None,
+            ),
+            flags: SupportFlags {
+                dc_supported: true,
+                ..SupportFlags::default()
+            },
+            ..SubDevice::default()
+        };
+
+        let topology = subdevice.topology();
+
+        assert_eq!(topology.entry_port, 0);
+        assert_eq!(topology.topology, Topology::Fork);
+        assert!(topology.is_junction());
+        assert_eq!(topology.parent_index, Some(0));
+        assert_eq!(topology.parent_port, Some(3));
+        assert_eq!(topology.loop_propagation_time, Some(200));
+        assert_eq!(topology.propagation_delay, 145);
+        assert_eq!(
+            topology.ports.map(|port| port.downstream_to),
+            [None, Some(2), Some(5), None]
+        );
+        assert_eq!(topology.ports.map(|port| port.number), [0, 3, 1, 2]);
+    }
+
+    #[test]
+    fn network_tree() {
+        // EK1100 (0) with an EL2004 (1) on its E-bus, followed by a second EK1100 (2)
+        let subdevices = [
+            SubDevice {
+                configured_address: 0x1000,
+                index: 0,
+                ports: make_ports(true, true, true, false).set_downstreams(
+                    None,
+                    Some(1),
+                    Some(2),
+                    None,
+                ),
+                ..SubDevice::default()
+            },
+            SubDevice {
+                configured_address: 0x1001,
+                index: 1,
+                parent_index: Some(0),
+                parent_port: Some(3),
+                ports: make_ports(true, false, false, false),
+                ..SubDevice::default()
+            },
+            SubDevice {
+                configured_address: 0x1002,
+                index: 2,
+                parent_index: Some(0),
+                parent_port: Some(1),
+                ports: make_ports(true, false, false, false),
+                ..SubDevice::default()
+            },
+        ];
+
+        let network = NetworkTopology::<4> {
+            subdevices: subdevices.iter().map(SubDevice::topology).collect(),
+            num_subdevices: 3,
+            dc_reference: Some(0x1000),
+        };
+
+        assert_eq!(network.root().map(|root| root.index), Some(0));
+        assert_eq!(
+            network
+                .root_ports()
+                .map(|port| port.number)
+                .collect::<heapless::Vec<_, 4>>(),
+            [0, 3, 1]
+        );
+        assert_eq!(
+            network
+                .children(0)
+                .map(|child| child.configured_address)
+                .collect::<heapless::Vec<_, 4>>(),
+            [0x1001, 0x1002]
+        );
+        assert_eq!(network.children(1).count(), 0);
+        assert_eq!(
+            network
+                .junctions()
+                .map(|junction| junction.index)
+                .collect::<heapless::Vec<_, 4>>(),
+            [0]
+        );
+    }
+}
@@ -1,8 +1,13 @@
 use crate::{
     eeprom::types::{MailboxProtocols, SyncManagerType},
+    error::PdiLayoutError,
     pdi::PdiSegment,
+    subdevice::pdo_mapping::PdoMapping,
+};
+use core::{
+    fmt::{self, Debug},
+    ops::Range,
 };
-use core::fmt::{self, Debug};
 
 /// SubDevice identity information (vendor ID, product ID, etc).
 #[derive(Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireRead)]
@@ -48,6 +53,8 @@ impl Debug for SubDeviceIdentity {
 pub struct SubDeviceConfig {
     pub io: IoRanges,
     pub mailbox: MailboxConfig,
+    pub input_mapping: PdoMapping,
+    pub output_mapping: PdoMapping,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -73,3 +80,36 @@ pub struct IoRanges {
     pub input: PdiSegment,
     pub output: PdiSegment,
 }
+
+impl IoRanges {
+    /// Move the outputs from their logical addresses to where they are stored in the group PDI.
+    pub(crate) fn offset_outputs(&mut self, outputs_offset: usize) {
+        if !self.output.is_empty() {
+            self.output.bytes = (self.output.bytes.start + outputs_offset)
+                ..(self.output.bytes.end + outputs_offset);
+        }
+    }
+
+    /// Check that the inputs and outputs still have the lengths of the given logical ranges.
+    pub(crate) fn check_lengths(
+        &self,
+        inputs: &Range<usize>,
+        outputs: &Range<usize>,
+    ) -> Result<(), PdiLayoutError> {
+        if self.input.len() != inputs.len() {
+            return Err(PdiLayoutError::InputLength {
+                expected: inputs.len(),
+                actual: self.input.len(),
+            });
+        }
+
+        if self.output.len() != outputs.len() {
+            return Err(PdiLayoutError::OutputLength {
+                expected: outputs.len(),
+                actual: self.output.len(),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+//! Process data and PDI watchdog configuration and status.
+
+use crate::error::WatchdogError;
+use core::time::Duration;
+
+/// The ESC watchdog clock runs at 25MHz.
+const BASE_TICK_NS: u128 = 40;
+
+/// Process data and PDI watchdog configuration.
+///
+/// The SyncManager (process data) watchdog is triggered every time the SubDevice receives new
+/// outputs. If no outputs are received before it expires, the SubDevice stops driving its outputs
+/// and usually moves from OP to SAFE-OP with an AL status code of
+/// [`SyncManagerWatchdog`](crate::AlStatusCode::SyncManagerWatchdog). Most SubDevices default to a
+/// timeout of 100ms.
+///
+/// Set for a whole group with
+/// [`SubDeviceGroup::set_watchdog`](crate::SubDeviceGroup::set_watchdog), or for a single
+/// SubDevice with [`SubDeviceRef::set_watchdog`](crate::SubDeviceRef::set_watchdog). The
+/// configuration is written to the SubDevice during the PRE-OP -> SAFE-OP transition.
+///
+/// # Examples
+///
+/// Drop outputs if no process data is received for 5ms:
+///
+/// ```rust
+/// use core::time::Duration;
+/// use ethercrab::WatchdogConfig;
+///
+/// let config = WatchdogConfig::sync_manager(Duration::from_millis(5));
+///
+/// assert_eq!(config.tick, Duration::from_micros(100));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// The length of one watchdog tick.
+    ///
+    /// This is written to
+    /// [`RegisterAddress::WatchdogDivider`](crate::RegisterAddress::WatchdogDivider) and is shared
+    /// by both watchdogs, so changing it also changes the PDI watchdog timeout if
+    /// [`pdi_timeout`](WatchdogConfig::pdi_timeout) is not set.
+    ///
+    /// The tick must be between 80ns and 2.62ms, and is rounded down to a multiple of 40ns. The
+    /// ESC default is 100us.
+    pub tick: Duration,
+
+    /// SyncManager (process data) watchdog timeout.
+    ///
+    /// `None` leaves the SubDevice's current timeout, and a timeout of zero disables the watchdog.
+    /// The timeout is rounded down to a whole number of ticks and must be no more than `65535`
+    /// ticks.
+    pub sync_manager_timeout: Option<Duration>,
+
+    /// PDI watchdog timeout.
+    ///
+    /// The PDI watchdog expires when the SubDevice application stops accessing the ESC. `None`
+    /// leaves the SubDevice's current timeout.
+    pub pdi_timeout: Option<Duration>,
+}
+
+impl WatchdogConfig {
+    /// The ESC default watchdog tick of 100us.
+    pub const DEFAULT_TICK: Duration = Duration::from_micros(100);
+
+    /// Set the SyncManager watchdog timeout using the default 100us tick.
+    pub const fn sync_manager(timeout: Duration) -> Self {
+        Self {
+            tick: Self::DEFAULT_TICK,
+            sync_manager_timeout: Some(timeout),
+            pdi_timeout: None,
+        }
+    }
+
+    /// Value of the watchdog divider register.
+    pub(crate) fn divider(&self) -> Result<u16, WatchdogError> {
+        (self.tick.as_nanos() / BASE_TICK_NS)
+            .checked_sub(2)
+            .and_then(|divider| u16::try_from(divider).ok())
+            .ok_or(WatchdogError::InvalidTick)
+    }
+
+    /// Actual tick length after rounding.
+    fn tick_ns(&self) -> Result<u128, WatchdogError> {
+        self.divider()
+            .map(|divider| (u128::from(divider) + 2) * BASE_TICK_NS)
+    }
+
+    /// Convert a timeout into a number of ticks.
+    fn ticks(&self, timeout: Duration) -> Result<u16, WatchdogError> {
+        let ticks = timeout.as_nanos() / self.tick_ns()?;
+
+        // A non-zero timeout that rounds down to zero would disable the watchdog
+        if ticks == 0 && !timeout.is_zero() {
+            return Err(WatchdogError::InvalidTimeout);
+        }
+
+        u16::try_from(ticks).map_err(|_| WatchdogError::InvalidTimeout)
+    }
+
+    /// Value of the SyncManager watchdog register, or `None` if it should not be written.
+    pub(crate) fn sync_manager_ticks(&self) -> Result<Option<u16>, WatchdogError> {
+        self.sync_manager_timeout
+            .map(|timeout| self.ticks(timeout))
+            .transpose()
+    }
+
+    /// Value of the PDI watchdog register, or `None` if it should not be written.
+    pub(crate) fn pdi_ticks(&self) -> Result<Option<u16>, WatchdogError> {
+        self.pdi_timeout
+            .map(|timeout| self.ticks(timeout))
+            .transpose()
+    }
+}
+
+/// Watchdog status and expiry counters read from a SubDevice.
+///
+/// Read with [`SubDeviceRef::watchdog_status`](crate::SubDeviceRef::watchdog_status) or
+/// [`SubDeviceGroup::watchdog_status`](crate::SubDeviceGroup::watchdog_status).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ethercrab_wire::EtherCrabWireRead)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[wire(bytes = 4)]
+pub struct WatchdogStatus {
+    /// Cleared when the SyncManager watchdog has expired. Set if the watchdog is running or
+    /// disabled.
+    #[wire(bits = 1, post_skip = 15)]
+    sync_manager_active: bool,
+    #[wire(bytes = 1)]
+    sync_manager_expirations: u8,
+    #[wire(bytes = 1)]
+    pdi_expirations: u8,
+}
+
+impl WatchdogStatus {
+    /// Returns `true` if the SyncManager watchdog has expired and outputs are no longer driven.
+    pub fn sync_manager_expired(&self) -> bool {
+        !self.sync_manager_active
+    }
+
+    /// The number of times the SyncManager watchdog has expired.
+    ///
+    /// This counter saturates at `255`.
+    pub fn sync_manager_expirations(&self) -> u8 {
+        self.sync_manager_expirations
+    }
+
+    /// The number of times the PDI watchdog has expired.
+    ///
+    /// This counter saturates at `255`.
+    pub fn pdi_expirations(&self) -> u8 {
+        self.pdi_expirations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethercrab_wire::EtherCrabWireRead;
+
+    #[test]
+    fn default_tick() {
+        let config = WatchdogConfig::sync_manager(Duration::from_millis(100));
+
+        // ESC default values
+        assert_eq!(config.divider(), Ok(2498));
+        assert_eq!(config.sync_manager_ticks(), Ok(Some(1000)));
+        assert_eq!(config.pdi_ticks(), Ok(None));
+    }
+
+    #[test]
+    fn tick_range() {
+        let config = |tick| WatchdogConfig {
+            tick,
+            ..WatchdogConfig::sync_manager(Duration::ZERO)
+        };
+
+        assert_eq!(config(Duration::from_nanos(80)).divider(), Ok(0));
+        assert_eq!(config(Duration::from_nanos(119)).divider(), Ok(0));
+        assert_eq!(
+            config(Duration::from_nanos(40 * 65537)).divider(),
+            Ok(u16::MAX)
+        );
+        assert_eq!(
+            config(Duration::from_nanos(40)).divider(),
+            Err(WatchdogError::InvalidTick)
+        );
+        assert_eq!(
+            config(Duration::from_millis(3)).divider(),
+            Err(WatchdogError::InvalidTick)
+        );
+    }
+
+    #[test]
+    fn timeout_rounding() {
+        let config = WatchdogConfig {
+            tick: Duration::from_micros(10),
+            sync_manager_timeout: Some(Duration::from_micros(2999)),
+            pdi_timeout: Some(Duration::ZERO),
+        };
+
+        assert_eq!(config.sync_manager_ticks(), Ok(Some(299)));
+        // Zero explicitly disables the watchdog
+        assert_eq!(config.pdi_ticks(), Ok(Some(0)));
+
+        // Would otherwise round down to zero and disable the watchdog
+        let config = WatchdogConfig::sync_manager(Duration::from_micros(50));
+
+        assert_eq!(
+            config.sync_manager_ticks(),
+            Err(WatchdogError::InvalidTimeout)
+        );
+
+        let config = WatchdogConfig::sync_manager(Duration::from_secs(7));
+
+        assert_eq!(
+            config.sync_manager_ticks(),
+            Err(WatchdogError::InvalidTimeout)
+        );
+    }
+
+    #[test]
+    fn unpack_status() {
+        assert_eq!(
+            WatchdogStatus::unpack_from_slice(&[0x00, 0x00, 0x03, 0x01]).map(|status| (
+                status.sync_manager_expired(),
+                status.sync_manager_expirations(),
+                status.pdi_expirations()
+            )),
+            Ok((true, 3, 1))
+        );
+        assert_eq!(
+            WatchdogStatus::unpack_from_slice(&[0x01, 0x00, 0x00, 0x00])
+                .map(|status| status.sync_manager_expired()),
+            Ok(false)
+        );
+    }
+}
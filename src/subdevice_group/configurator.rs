@@ -11,6 +11,7 @@ use atomic_refcell::AtomicRefCell;
 struct GroupInnerRef<'a> {
     subdevices: &'a mut [AtomicRefCell<SubDevice>],
     pdi_start: &'a mut PdiOffset,
+    pdi_reserved: &'a mut bool,
 }
 
 // TODO: Prove if this is safe. All this stuff is internal to the crate and short lived so I think
@@ -41,6 +42,7 @@ impl<'a> SubDeviceGroupRef<'a> {
                 GroupInnerRef {
                     subdevices: &mut inner.subdevices,
                     pdi_start: &mut inner.pdi_start,
+                    pdi_reserved: &mut inner.pdi_reserved,
                 }
             },
         }
@@ -58,6 +60,7 @@ impl<'a> SubDeviceGroupRef<'a> {
 
         // Set the starting position in the PDI for this group's segment
         *inner.pdi_start = pdi_position;
+        *inner.pdi_reserved = true;
 
         fmt::debug!(
             "Going to configure group with {} SubDevice(s), starting PDI offset {:#08x}",
@@ -0,0 +1,57 @@
+//! Commands used to exchange a group's process data.
+
+use crate::command::Command;
+
+/// How a [`SubDeviceGroup`](crate::SubDeviceGroup) exchanges its process data every cycle.
+///
+/// The group PDI is always laid out as all inputs followed by all outputs. The default `LRW`
+/// strategy sends the whole PDI in one logical read/write command. The other strategies read the
+/// inputs with `LRD` and write the outputs with `LWR`, which is required by some older ESCs and by
+/// networks shared between multiple MainDevices or segments that do not support `LRW`.
+///
+/// Set with
+/// [`SubDeviceGroup::set_cyclic_command_strategy`](crate::SubDeviceGroup::set_cyclic_command_strategy).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CyclicCommandStrategy {
+    /// Read inputs and write outputs with a single `LRW`.
+    ///
+    /// Each SubDevice adds `1` to the working counter if it has inputs and `2` if it has outputs.
+    #[default]
+    Lrw,
+
+    /// Read inputs with `LRD` and write outputs with `LWR`, with both PDUs in the same frame where
+    /// possible.
+    ///
+    /// Each SubDevice adds `1` to the working counter if it has inputs and `1` if it has outputs.
+    LrdLwr,
+
+    /// Read inputs with `LRD` and write outputs with `LWR`, sending each command in its own frame.
+    ///
+    /// Each SubDevice adds `1` to the working counter if it has inputs and `1` if it has outputs.
+    ///
+    /// Only this group's `LRD` and `LWR` are kept in separate frames. When groups are exchanged
+    /// together with [`MainDevice::tx_rx_groups`](crate::MainDevice::tx_rx_groups), PDUs from other
+    /// groups may still share a frame with either of them.
+    LrdLwrSeparateFrames,
+}
+
+/// A logical command used to send part of the group PDI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdiCommand {
+    Lrw,
+    Lrd,
+    Lwr,
+}
+
+impl PdiCommand {
+    pub fn command(self, address: u32) -> Command {
+        match self {
+            Self::Lrw => Command::lrw(address).into(),
+            Self::Lrd => Command::lrd(address).into(),
+            Self::Lwr => Command::lwr(address).into(),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Distributed Clock synchronisation quality reporting.
+
+use core::time::Duration;
+
+/// Sign bit of the DC system time difference register.
+const DIFFERENCE_LESS_THAN: u32 = 1 << 31;
+
+/// DC synchronisation deviation of a single SubDevice.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DcDeviation {
+    /// Configured station address of the SubDevice.
+    pub configured_address: u16,
+
+    /// Mean difference between the SubDevice's local copy of the system time and the system time
+    /// received from the DC reference, in nanoseconds.
+    ///
+    /// A negative value means the SubDevice's clock is behind the reference.
+    pub difference: i32,
+}
+
+impl DcDeviation {
+    /// Decode the raw value of
+    /// [`RegisterAddress::DcSystemTimeDifference`](crate::RegisterAddress::DcSystemTimeDifference).
+    ///
+    /// The register is not two's complement. Instead, the upper bit is set when the local copy of
+    /// the system time is less than the received system time, and the remaining bits hold the
+    /// magnitude of the difference.
+    pub(crate) fn from_register(configured_address: u16, raw: u32) -> Self {
+        let magnitude = (raw & !DIFFERENCE_LESS_THAN) as i32;
+
+        let difference = if raw & DIFFERENCE_LESS_THAN > 0 {
+            -magnitude
+        } else {
+            magnitude
+        };
+
+        Self {
+            configured_address,
+            difference,
+        }
+    }
+
+    /// Absolute deviation from the reference clock in nanoseconds.
+    pub fn abs(&self) -> u32 {
+        self.difference.unsigned_abs()
+    }
+
+    fn exceeds(&self, threshold: Duration) -> bool {
+        u128::from(self.abs()) > threshold.as_nanos()
+    }
+}
+
+/// Distributed Clock synchronisation status of every DC-capable SubDevice in a group.
+///
+/// Returned by [`SubDeviceGroup::dc_sync_status`](crate::SubDeviceGroup::dc_sync_status).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DcSyncStatus<const MAX_SUBDEVICES: usize> {
+    pub(crate) deviations: heapless::Vec<DcDeviation, MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> DcSyncStatus<MAX_SUBDEVICES> {
+    /// Deviation for each DC-capable SubDevice, in group order.
+    pub fn deviations(&self) -> &[DcDeviation] {
+        &self.deviations
+    }
+
+    /// The SubDevice furthest from the reference clock, or `None` if the group has no DC-capable
+    /// SubDevices.
+    pub fn max_deviation(&self) -> Option<DcDeviation> {
+        self.deviations.iter().copied().max_by_key(DcDeviation::abs)
+    }
+
+    /// The SubDevice closest to the reference clock, or `None` if the group has no DC-capable
+    /// SubDevices.
+    pub fn min_deviation(&self) -> Option<DcDeviation> {
+        self.deviations.iter().copied().min_by_key(DcDeviation::abs)
+    }
+
+    /// Returns `true` if every SubDevice is within `threshold` of the reference clock.
+    pub fn is_synchronised(&self, threshold: Duration) -> bool {
+        self.outside_threshold(threshold).next().is_none()
+    }
+
+    /// Iterate over SubDevices that have drifted further than `threshold` from the reference
+    /// clock.
+    pub fn outside_threshold(&self, threshold: Duration) -> impl Iterator<Item = &DcDeviation> {
+        self.deviations.iter().filter(move |d| d.exceeds(threshold))
+    }
+}
+
+/// A change in group synchronisation reported by [`DcSyncMonitor::update`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DcSyncEvent {
+    /// All SubDevices are now within the threshold.
+    Synchronised {
+        /// Largest absolute deviation in the group in nanoseconds.
+        max_deviation: u32,
+    },
+
+    /// At least one SubDevice has drifted outside the threshold.
+    OutOfSync {
+        /// The SubDevice with the largest deviation.
+        worst: DcDeviation,
+    },
+}
+
+/// Watch repeated [`DcSyncStatus`] readings and report when a group moves into or out of sync.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use ethercrab::{subdevice_group::{DcSyncEvent, DcSyncMonitor}, MainDevice, SubDeviceGroup};
+/// # use core::time::Duration;
+/// # async fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64>) -> Result<(), ethercrab::error::Error> {
+/// let mut monitor = DcSyncMonitor::new(Duration::from_nanos(100));
+///
+/// loop {
+///     let status = group.dc_sync_status(maindevice).await?;
+///
+///     match monitor.update(&status) {
+///         Some(DcSyncEvent::OutOfSync { worst }) => {
+///             log::warn!("SubDevice {:#06x} drifted by {} ns", worst.configured_address, worst.difference);
+///         }
+///         Some(DcSyncEvent::Synchronised { .. }) => log::info!("Clocks in sync"),
+///         None => (),
+///     }
+/// #   break Ok(())
+/// }
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DcSyncMonitor {
+    threshold: Duration,
+    synchronised: Option<bool>,
+}
+
+impl DcSyncMonitor {
+    /// Create a new monitor which considers SubDevices further than `threshold` from the DC
+    /// reference to be out of sync.
+    pub const fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            synchronised: None,
+        }
+    }
+
+    /// The configured threshold.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Whether the group was in sync at the last update, or `None` if no updates have been made.
+    pub fn is_synchronised(&self) -> Option<bool> {
+        self.synchronised
+    }
+
+    /// Update the monitor with a new status reading.
+    ///
+    /// An event is returned on the first update and whenever the group crosses the threshold.
+    /// Repeated readings on the same side of the threshold return `None`.
+    pub fn update<const MAX_SUBDEVICES: usize>(
+        &mut self,
+        status: &DcSyncStatus<MAX_SUBDEVICES>,
+    ) -> Option<DcSyncEvent> {
+        let synchronised = status.is_synchronised(self.threshold);
+
+        if self.synchronised.replace(synchronised) == Some(synchronised) {
+            return None;
+        }
+
+        let worst = status.max_deviation();
+
+        if synchronised {
+            Some(DcSyncEvent::Synchronised {
+                max_deviation: worst.map(|worst| worst.abs()).unwrap_or(0),
+            })
+        } else {
+            // There is at least one SubDevice outside the threshold here, so `worst` is `Some`.
+            worst.map(|worst| DcSyncEvent::OutOfSync { worst })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(diffs: &[i32]) -> DcSyncStatus<16> {
+        DcSyncStatus {
+            deviations: diffs
+                .iter()
+                .enumerate()
+                .map(|(i, difference)| DcDeviation {
+                    configured_address: 0x1000 + i as u16,
+                    difference: *difference,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn decode_register() {
+        assert_eq!(DcDeviation::from_register(0x1000, 1234).difference, 1234);
+        assert_eq!(
+            DcDeviation::from_register(0x1000, 0x8000_0000 | 1234).difference,
+            -1234
+        );
+        assert_eq!(
+            DcDeviation::from_register(0x1000, 0x8000_0000).difference,
+            0
+        );
+        assert_eq!(
+            DcDeviation::from_register(0x1000, 0xffff_ffff).difference,
+            -(i32::MAX)
+        );
+    }
+
+    #[test]
+    fn min_max() {
+        let s = status(&[10, -500, 3, 200]);
+
+        assert_eq!(
+            s.max_deviation(),
+            Some(DcDeviation {
+                configured_address: 0x1001,
+                difference: -500
+            })
+        );
+        assert_eq!(
+            s.min_deviation(),
+            Some(DcDeviation {
+                configured_address: 0x1002,
+                difference: 3
+            })
+        );
+
+        assert_eq!(status(&[]).max_deviation(), None);
+    }
+
+    #[test]
+    fn threshold() {
+        let s = status(&[10, -500, 3, 200]);
+
+        assert!(!s.is_synchronised(Duration::from_nanos(200)));
+        assert!(s.is_synchronised(Duration::from_nanos(500)));
+        assert_eq!(
+            s.outside_threshold(Duration::from_nanos(100))
+                .map(|d| d.configured_address)
+                .collect::<Vec<_>>(),
+            vec![0x1001, 0x1003]
+        );
+    }
+
+    #[test]
+    fn monitor_events() {
+        let mut monitor = DcSyncMonitor::new(Duration::from_nanos(100));
+
+        assert_eq!(monitor.is_synchronised(), None);
+
+        assert_eq!(
+            monitor.update(&status(&[1000, 20])),
+            Some(DcSyncEvent::OutOfSync {
+                worst: DcDeviation {
+                    configured_address: 0x1000,
+                    difference: 1000
+                }
+            })
+        );
+        assert_eq!(monitor.update(&status(&[500, 20])), None);
+        assert_eq!(
+            monitor.update(&status(&[50, -20])),
+            Some(DcSyncEvent::Synchronised { max_deviation: 50 })
+        );
+        assert_eq!(monitor.update(&status(&[10, -20])), None);
+        assert_eq!(monitor.is_synchronised(), Some(true));
+    }
+}
@@ -0,0 +1,86 @@
+//! Reading error counters for a whole group.
+
+use super::SubDeviceGroup;
+use crate::{
+    command::Command,
+    diagnostics::{link_errors, ErrorCounters, LinkErrors},
+    error::{Error, Item},
+    MainDevice, RegisterAddress, SubDeviceTopology,
+};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+{
+    /// Read the error counters of every SubDevice in the group in as few frames as possible.
+    ///
+    /// The returned counters are in the same order as the SubDevices in the group. The entry for a
+    /// SubDevice that did not respond is `None`.
+    pub async fn error_counters(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<heapless::Vec<Option<ErrorCounters>, MAX_SUBDEVICES>, Error> {
+        let commands = self
+            .inner()
+            .subdevices
+            .iter()
+            .map(|subdevice| {
+                Command::fprd(
+                    subdevice.borrow().configured_address(),
+                    RegisterAddress::RxErrorCounter.into(),
+                )
+                .into()
+            })
+            .collect::<heapless::Vec<Command, MAX_SUBDEVICES>>();
+
+        let mut counters = heapless::Vec::new();
+
+        maindevice
+            .batch_pdus(commands, ErrorCounters::PACKED_LEN as u16, |_, pdu| {
+                let subdevice_counters = if pdu.working_counter == 1 {
+                    Some(ErrorCounters::unpack_from_slice(&pdu)?)
+                } else {
+                    None
+                };
+
+                counters
+                    .push(subdevice_counters)
+                    .map_err(|_| Error::Capacity(Item::SubDevice))
+            })
+            .await?;
+
+        Ok(counters)
+    }
+
+    /// Read the error counters of every SubDevice in the group and attribute them to the cables
+    /// between SubDevices.
+    ///
+    /// Only cables with errors are returned, sorted with the most errors first. See
+    /// [`link_errors`] for details.
+    ///
+    /// SubDevices that do not respond are left out, as are the errors counted at their end of the
+    /// cables to their children. Counters of SubDevices outside this group are not read, so the
+    /// cable into the first SubDevice of the group only includes errors counted at the SubDevice's
+    /// end. To diagnose a whole network spread over several groups, collect the
+    /// [`error_counters`] and [`SubDeviceTopology`] of every group and pass them to
+    /// [`link_errors`].
+    ///
+    /// [`error_counters`]: SubDeviceGroup::error_counters
+    pub async fn link_errors(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<heapless::Vec<LinkErrors, MAX_SUBDEVICES>, Error> {
+        let counters = self.error_counters(maindevice).await?;
+
+        let subdevices = self
+            .inner()
+            .subdevices
+            .iter()
+            .map(|subdevice| subdevice.borrow().topology())
+            .zip(counters)
+            .filter_map(|(topology, counters)| Some((topology, counters?)))
+            .collect::<heapless::Vec<(SubDeviceTopology, ErrorCounters), MAX_SUBDEVICES>>();
+
+        Ok(link_errors(&subdevices))
+    }
+}
@@ -0,0 +1,441 @@
+//! Cyclic process data exchange, shared between single groups and multiple groups packed into the
+//! same frames.
+
+use super::{cyclic_command::PdiCommand, TxRxResponse, DC_PDU_SIZE};
+use crate::{
+    command::Command,
+    error::{Error, PduError},
+    events::Event,
+    fmt,
+    pdu_loop::{CreatedFrame, PduResponseHandle, ReceivedPdu},
+    piggyback::{self, PiggybackQueue},
+    MainDevice, RegisterAddress,
+};
+use core::ops::Range;
+use ethercrab_wire::EtherCrabWireRead;
+
+pub(crate) mod sealed {
+    use super::*;
+
+    /// Methods used to drive a group's PDI exchange. Sealed so groups can be passed to
+    /// [`MainDevice::tx_rx_groups`] without exposing internals.
+    pub trait CyclicGroupInner {
+        /// Compute what needs to be sent this cycle.
+        ///
+        /// An empty `LRW` is sent alongside the DC sync PDU if `with_dc` is `true`.
+        fn start_exchange(&self, with_dc: bool) -> PdiCursor;
+
+        /// The logical start address of the group and its PDI buffer.
+        fn pdi_buffer(&self) -> (u32, &[u8]);
+
+        /// Record the working counter of a completed exchange, returning `true` if it differs from
+        /// the previous exchange.
+        fn working_counter_changed(&self, working_counter: u16) -> bool;
+
+        /// Write received inputs back into the group PDI and compute the working counter.
+        fn process_received_pdi_chunk(
+            &self,
+            command: PdiCommand,
+            chunk: Range<usize>,
+            data: &ReceivedPdu<'_>,
+        ) -> Result<TxRxResponse, Error>;
+    }
+}
+
+/// A [`SubDeviceGroup`](crate::SubDeviceGroup) with a configured PDI that can exchange process data
+/// alongside other groups using [`MainDevice::tx_rx_groups`].
+///
+/// This trait is implemented for every group in PRE-OP with PDI, SAFE-OP or OP.
+pub trait CyclicGroup: sealed::CyclicGroupInner {}
+
+/// A part of a group's PDI sent in the current frame.
+#[derive(Debug)]
+pub(crate) struct PdiChunk {
+    command: PdiCommand,
+    /// Logical range relative to the start of the group.
+    range: Range<usize>,
+    handle: PduResponseHandle,
+}
+
+/// Progress of sending a group's PDI over one or more frames.
+#[derive(Debug)]
+pub struct PdiCursor {
+    /// The command, the logical range relative to the start of the group and the offset of the
+    /// data to send in the group PDI buffer.
+    segments: heapless::Vec<(PdiCommand, Range<usize>, usize), 2>,
+    /// Send each segment in its own frame.
+    separate_frames: bool,
+    segment_idx: usize,
+    /// Bytes of the current segment that have already been sent.
+    segment_sent: usize,
+    /// Chunks pushed into the frame currently being sent.
+    pending: heapless::Vec<PdiChunk, 2>,
+}
+
+impl PdiCursor {
+    pub fn new(
+        segments: heapless::Vec<(PdiCommand, Range<usize>, usize), 2>,
+        separate_frames: bool,
+    ) -> Self {
+        Self {
+            segments,
+            separate_frames,
+            segment_idx: 0,
+            segment_sent: 0,
+            pending: heapless::Vec::new(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.segment_idx >= self.segments.len()
+    }
+
+    /// Push as much of the remaining PDI into `frame` as will fit, returning `true` if the frame is
+    /// full.
+    fn push_chunks(
+        &mut self,
+        frame: &mut CreatedFrame<'_>,
+        pdi: &[u8],
+        pdi_start: u32,
+    ) -> Result<bool, Error> {
+        while let Some((command, segment, data_offset)) = self.segments.get(self.segment_idx) {
+            let chunk_start = segment.start + self.segment_sent;
+
+            let data = pdi
+                .get((data_offset + chunk_start)..(data_offset + segment.end))
+                .ok_or(Error::Internal)?;
+
+            let Some((bytes_in_this_chunk, handle)) =
+                frame.push_pdu_slice_rest(command.command(pdi_start + chunk_start as u32), data)?
+            else {
+                return Ok(true);
+            };
+
+            fmt::trace!("Wrote {} byte {:?} chunk", bytes_in_this_chunk, command);
+
+            // Never more than the number of segments
+            let _ = self.pending.push(PdiChunk {
+                command: *command,
+                range: chunk_start..(chunk_start + bytes_in_this_chunk),
+                handle,
+            });
+
+            self.segment_sent += bytes_in_this_chunk;
+
+            // Frame is full. The rest of this segment will be sent in the next frame.
+            if self.segment_sent < segment.len() {
+                return Ok(true);
+            }
+
+            self.segment_idx += 1;
+            self.segment_sent = 0;
+
+            if self.separate_frames {
+                break;
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Acyclic PDUs from the [`PiggybackQueue`] sent in the current frame.
+///
+/// Any PDUs that don't receive a response, e.g. because the frame timed out or the exchange was
+/// cancelled, are put back in the queue when this is dropped.
+struct Piggybacked<'a> {
+    queue: &'a PiggybackQueue,
+    pdus: heapless::Vec<(usize, PduResponseHandle), { piggyback::QUEUE_LEN }>,
+}
+
+impl<'a> Piggybacked<'a> {
+    /// Fill the rest of `frame` with queued PDUs.
+    fn push_pdus(&mut self, frame: &mut CreatedFrame<'_>) -> Result<(), Error> {
+        while let Some((idx, command, data)) = self.queue.claim_next() {
+            match frame.push_pdu(command, data, None) {
+                Ok(handle) => {
+                    // Never more than the queue length
+                    let _ = self.pdus.push((idx, handle));
+                }
+                Err(e) => {
+                    self.queue.requeue(idx);
+
+                    return match e {
+                        // Frame is full, we'll send the rest next cycle
+                        PduError::TooLong => Ok(()),
+                        e => Err(e.into()),
+                    };
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Drop for Piggybacked<'a> {
+    fn drop(&mut self) {
+        for (idx, _handle) in self.pdus.iter() {
+            self.queue.requeue(*idx);
+        }
+    }
+}
+
+/// Exchange the PDI of every group in `groups`, packing PDUs from all groups into as few frames as
+/// possible.
+///
+/// If `dc_ref` is given, an `FRMW` of the DC system time is sent in the first frame and the time is
+/// returned.
+pub(crate) async fn exchange<'sto, G, const N: usize>(
+    maindevice: &'sto MainDevice<'sto>,
+    groups: [&G; N],
+    dc_ref: Option<u16>,
+) -> Result<([TxRxResponse; N], Option<u64>), Error>
+where
+    G: CyclicGroup + ?Sized,
+{
+    let mut cursors = groups.map(|group| group.start_exchange(dc_ref.is_some()));
+    let mut responses = [TxRxResponse::default(); N];
+    let mut time = None;
+
+    while cursors.iter().any(|cursor| !cursor.is_done()) || (dc_ref.is_some() && time.is_none()) {
+        let mut frame = maindevice.pdu_loop.alloc_cyclic_frame()?;
+
+        let dc_handle = match dc_ref {
+            Some(dc_ref) if time.is_none() => {
+                let dc_handle = frame.push_pdu(
+                    Command::frmw(dc_ref, RegisterAddress::DcSystemTime.into()).into(),
+                    0u64,
+                    None,
+                )?;
+
+                // Just double checking
+                debug_assert_eq!(dc_handle.alloc_size, DC_PDU_SIZE);
+
+                Some(dc_handle)
+            }
+            _ => None,
+        };
+
+        let mut frame_full = false;
+
+        for (group, cursor) in groups.iter().zip(cursors.iter_mut()) {
+            let (pdi_start, pdi) = group.pdi_buffer();
+
+            if cursor.push_chunks(&mut frame, pdi, pdi_start)? {
+                frame_full = true;
+
+                break;
+            }
+        }
+
+        if dc_handle.is_none() && cursors.iter().all(|cursor| cursor.pending.is_empty()) {
+            continue;
+        }
+
+        let mut piggybacked = Piggybacked {
+            queue: &maindevice.piggyback,
+            pdus: heapless::Vec::new(),
+        };
+
+        if !frame_full {
+            piggybacked.push_pdus(&mut frame)?;
+        }
+
+        let frame = frame.mark_sendable(
+            &maindevice.pdu_loop,
+            maindevice.timeouts.pdu,
+            maindevice.config.retry_behaviour.retry_count(),
+        );
+
+        maindevice.pdu_loop.wake_sender();
+
+        let received = frame.await?;
+
+        for (idx, handle) in core::mem::take(&mut piggybacked.pdus) {
+            match received.pdu(handle) {
+                Ok(pdu) => maindevice
+                    .piggyback
+                    .complete(idx, pdu.working_counter, &pdu),
+                Err(e) => {
+                    fmt::warn!("Failed to read piggybacked PDU response: {}", e);
+
+                    maindevice.piggyback.requeue(idx);
+                }
+            }
+        }
+
+        if let Some(dc_handle) = dc_handle {
+            time = Some(
+                received
+                    .pdu(dc_handle)
+                    .and_then(|rx| u64::unpack_from_slice(&rx).map_err(Error::from))?,
+            );
+        }
+
+        for ((group, cursor), response) in groups
+            .iter()
+            .zip(cursors.iter_mut())
+            .zip(responses.iter_mut())
+        {
+            for chunk in core::mem::take(&mut cursor.pending) {
+                response.add_chunk(group.process_received_pdi_chunk(
+                    chunk.command,
+                    chunk.range,
+                    &received.pdu(chunk.handle)?,
+                )?);
+            }
+        }
+    }
+
+    for (group, response) in groups.iter().zip(responses.iter()) {
+        if group.working_counter_changed(response.working_counter) && !response.working_counter_ok()
+        {
+            maindevice.publish(Event::ProcessDataWorkingCounter {
+                logical_address: group.pdi_buffer().0,
+                expected: response.expected_working_counter,
+                received: response.working_counter,
+            });
+        }
+    }
+
+    Ok((responses, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PduStorage;
+
+    fn cursor(segments: &[(PdiCommand, Range<usize>, usize)], separate_frames: bool) -> PdiCursor {
+        PdiCursor::new(
+            heapless::Vec::from_slice(segments).unwrap(),
+            separate_frames,
+        )
+    }
+
+    fn pending(cursor: &PdiCursor) -> Vec<(PdiCommand, Range<usize>)> {
+        cursor
+            .pending
+            .iter()
+            .map(|chunk| (chunk.command, chunk.range.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn pack_multiple_groups() {
+        static STORAGE: PduStorage<1, { PduStorage::element_size(64) }> = PduStorage::new();
+        let (_tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        let pdi = [0u8; 16];
+
+        let mut a = cursor(&[(PdiCommand::Lrw, 0..8, 0)], false);
+        let mut b = cursor(
+            &[(PdiCommand::Lrd, 0..2, 0), (PdiCommand::Lwr, 2..4, 0)],
+            false,
+        );
+
+        let mut frame = pdu_loop.alloc_frame().unwrap();
+
+        assert_eq!(a.push_chunks(&mut frame, &pdi, 0x1000), Ok(false));
+        assert_eq!(b.push_chunks(&mut frame, &pdi, 0x2000), Ok(false));
+
+        assert!(a.is_done());
+        assert!(b.is_done());
+        assert_eq!(pending(&a), vec![(PdiCommand::Lrw, 0..8)]);
+        assert_eq!(
+            pending(&b),
+            vec![(PdiCommand::Lrd, 0..2), (PdiCommand::Lwr, 2..4)]
+        );
+    }
+
+    #[test]
+    fn split_across_frames() {
+        // 64 bytes of PDU payload per frame
+        static STORAGE: PduStorage<2, { PduStorage::element_size(64) }> = PduStorage::new();
+        let (_tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        let pdi = [0u8; 100];
+
+        let mut c = cursor(&[(PdiCommand::Lrw, 0..100, 0)], false);
+
+        let mut frame = pdu_loop.alloc_frame().unwrap();
+
+        assert_eq!(c.push_chunks(&mut frame, &pdi, 0), Ok(true));
+        assert!(!c.is_done());
+        assert_eq!(pending(&c), vec![(PdiCommand::Lrw, 0..64)]);
+
+        c.pending.clear();
+
+        let mut frame = pdu_loop.alloc_frame().unwrap();
+
+        assert_eq!(c.push_chunks(&mut frame, &pdi, 0), Ok(false));
+        assert!(c.is_done());
+        assert_eq!(pending(&c), vec![(PdiCommand::Lrw, 64..100)]);
+    }
+
+    #[test]
+    fn separate_frames() {
+        static STORAGE: PduStorage<1, { PduStorage::element_size(64) }> = PduStorage::new();
+        let (_tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        let pdi = [0u8; 8];
+
+        let mut c = cursor(
+            &[(PdiCommand::Lrd, 0..4, 0), (PdiCommand::Lwr, 4..8, 0)],
+            true,
+        );
+
+        let mut frame = pdu_loop.alloc_frame().unwrap();
+
+        // Frame isn't full, but the LWR must go in the next frame
+        assert_eq!(c.push_chunks(&mut frame, &pdi, 0), Ok(false));
+        assert!(!c.is_done());
+        assert_eq!(pending(&c), vec![(PdiCommand::Lrd, 0..4)]);
+    }
+
+    #[test]
+    fn piggyback_fills_spare_space() {
+        static STORAGE: PduStorage<1, { PduStorage::element_size(64) }> = PduStorage::new();
+        let (_tx, _rx, pdu_loop) = STORAGE.try_split().unwrap();
+
+        let queue = PiggybackQueue::new();
+
+        // 12 byte header + 32 bytes of data each, so only one fits alongside the PDI
+        let _a = queue
+            .enqueue(Command::fprd(0x1000, 0).into(), (), Some(32))
+            .unwrap();
+        let _b = queue
+            .enqueue(Command::fprd(0x1001, 0).into(), (), Some(32))
+            .unwrap();
+
+        let pdi = [0u8; 8];
+
+        let mut c = cursor(&[(PdiCommand::Lrw, 0..8, 0)], false);
+
+        let mut frame = pdu_loop.alloc_frame().unwrap();
+
+        assert_eq!(c.push_chunks(&mut frame, &pdi, 0), Ok(false));
+
+        let mut piggybacked = Piggybacked {
+            queue: &queue,
+            pdus: heapless::Vec::new(),
+        };
+
+        assert_eq!(piggybacked.push_pdus(&mut frame), Ok(()));
+        assert_eq!(piggybacked.pdus.len(), 1);
+
+        // The PDU that didn't fit is sent next time
+        let (idx, command, _) = queue.claim_next().unwrap();
+
+        assert_eq!(idx, 1);
+        assert_eq!(command, Command::fprd(0x1001, 0).into());
+
+        queue.requeue(idx);
+
+        // Frame was never sent, so the first PDU is queued again too
+        drop(piggybacked);
+
+        assert_eq!(queue.claim_next().map(|(idx, _, _)| idx), Some(0));
+    }
+}
@@ -0,0 +1,195 @@
+//! Attaching and detaching hot-connect segments.
+
+use super::{NoDc, PreOp, SubDeviceGroup};
+use crate::{
+    command::Command,
+    error::{Error, HotConnectError, Item},
+    fmt,
+    hot_connect::{HotConnectSegment, SegmentScan},
+    subdevice::{SubDevice, SubDeviceRef},
+    MainDevice, RegisterAddress, StateRequest, SubDeviceState,
+};
+use atomic_refcell::AtomicRefCell;
+use core::{cell::UnsafeCell, marker::PhantomData};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC>
+{
+    /// Find the SubDevices in a newly connected [`HotConnectSegment`], configure them and add them
+    /// to this group, leaving them in PRE-OP.
+    ///
+    /// The group must be empty, either because no SubDevices were assigned to it by
+    /// [`MainDevice::init`] or because it was emptied with
+    /// [`disconnect_segment`](SubDeviceGroup::disconnect_segment). The rest of the network is not
+    /// reset, so other groups can keep exchanging process data while this method runs.
+    ///
+    /// The segment is the run of consecutive SubDevices directly after the segment's parent on the
+    /// network that have no configured station address, or that still have an address from a
+    /// previous connection of this segment. The segment must therefore be the first thing the
+    /// parent forwards frames to after itself. [`HotConnectError::NoSubDevices`] is returned if the
+    /// parent reports no link on the segment port, or no such SubDevices follow it.
+    ///
+    /// Once connected, the group can be configured and transitioned into OP like any other group.
+    /// Distributed clock propagation delays are not measured for hot-connected SubDevices.
+    ///
+    /// If the segment is not at the end of the network, the positions of the SubDevices after it
+    /// change. [`check_subdevices`](SubDeviceGroup::check_subdevices) uses these positions to
+    /// readdress power cycled SubDevices, so segments should be placed at the end of a line.
+    ///
+    /// Returns the number of SubDevices added to the group.
+    pub async fn connect_segment(
+        &mut self,
+        maindevice: &MainDevice<'_>,
+        segment: &HotConnectSegment,
+    ) -> Result<usize, Error> {
+        segment.validate(maindevice, MAX_SUBDEVICES)?;
+
+        if !self.inner.get_mut().subdevices.is_empty() {
+            return Err(HotConnectError::GroupNotEmpty.into());
+        }
+
+        let result = self.attach(maindevice, segment).await;
+
+        let inner = self.inner.get_mut();
+
+        // Don't leave a partially configured segment in the group
+        if result.is_err() {
+            inner.subdevices.clear();
+        }
+
+        result.map(|()| inner.subdevices.len())
+    }
+
+    async fn attach(
+        &mut self,
+        maindevice: &MainDevice<'_>,
+        segment: &HotConnectSegment,
+    ) -> Result<(), Error> {
+        let addresses = segment.addresses(MAX_SUBDEVICES);
+
+        if !segment.link(maindevice).await? {
+            fmt::error!(
+                "No link on port {} of segment parent {:#06x}",
+                segment.port,
+                segment.parent
+            );
+
+            return Err(HotConnectError::NoSubDevices.into());
+        }
+
+        let num_subdevices = maindevice.count_subdevices().await?;
+
+        let mut scan = SegmentScan::<MAX_SUBDEVICES>::new(segment);
+
+        maindevice
+            .batch_pdus(
+                (0..num_subdevices).map(|position| {
+                    Command::aprd(position, RegisterAddress::ConfiguredStationAddress.into()).into()
+                }),
+                u16::PACKED_LEN as u16,
+                |position, pdu| scan.push(position as u16, u16::unpack_from_slice(&pdu)?),
+            )
+            .await?;
+
+        let positions = scan.finish()?;
+
+        fmt::debug!(
+            "Connecting segment of {} SubDevice(s) at position {}",
+            positions.len(),
+            positions[0].0
+        );
+
+        let inner = self.inner.get_mut();
+
+        for (configured_address, (position, was_configured)) in addresses.zip(positions) {
+            Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+                .send(maindevice, configured_address)
+                .await?;
+
+            // A segment that was unplugged without being power cycled still holds its previous
+            // configuration.
+            if was_configured {
+                reset_subdevice(maindevice, configured_address).await?;
+            }
+
+            let subdevice = SubDevice::new(maindevice, position, configured_address).await?;
+
+            inner
+                .subdevices
+                .push(AtomicRefCell::new(subdevice))
+                .map_err(|_| Error::Capacity(Item::SubDevice))?;
+        }
+
+        if !inner.pdi_reserved {
+            inner.pdi_start = maindevice.reserve_pdi(MAX_PDI as u16);
+            inner.pdi_reserved = true;
+        }
+
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
+                .configure_mailboxes()
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+{
+    /// Remove all SubDevices from this group after its [`HotConnectSegment`] has been unplugged.
+    ///
+    /// The group keeps its range of logical addresses and settings like the
+    /// [watchdog](SubDeviceGroup::set_watchdog) and [PDI layout](SubDeviceGroup::set_pdi_layout),
+    /// and can be reused with [`connect_segment`](SubDeviceGroup::connect_segment). Any distributed
+    /// clock configuration must be applied again after reconnecting.
+    ///
+    /// No frames are sent. SubDevices that are still on the network are left in their current state
+    /// and are reset when the segment is next connected.
+    pub fn disconnect_segment(self) -> SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, NoDc> {
+        let mut inner = self.inner.into_inner();
+
+        inner.subdevices.clear();
+        inner.pdi_mappings.clear();
+        inner.dc_configuration = None;
+
+        SubDeviceGroup {
+            id: self.id,
+            pdi: UnsafeCell::new([0u8; MAX_PDI]),
+            read_pdi_len: 0,
+            pdi_len: 0,
+            inner: UnsafeCell::new(inner),
+            dc_conf: NoDc,
+            _state: PhantomData,
+        }
+    }
+}
+
+/// Put a SubDevice back into INIT and clear its FMMUs and SyncManagers.
+async fn reset_subdevice(
+    maindevice: &MainDevice<'_>,
+    configured_address: u16,
+) -> Result<(), Error> {
+    SubDeviceRef::new(maindevice, configured_address, ())
+        .request_state_nowait(StateRequest::new(SubDeviceState::Init).acknowledge_error())
+        .await?;
+
+    for fmmu_idx in 0..16 {
+        Command::fpwr(configured_address, RegisterAddress::fmmu(fmmu_idx).into())
+            .send(maindevice, [0u8; 0x10])
+            .await?;
+    }
+
+    for sm_idx in 0..16 {
+        Command::fpwr(
+            configured_address,
+            RegisterAddress::sync_manager(sm_idx).into(),
+        )
+        .send(maindevice, [0u8; 0x8])
+        .await?;
+    }
+
+    Ok(())
+}
@@ -4,32 +4,61 @@
 //! potentially at different tick rates.
 
 mod configurator;
+mod cyclic_command;
+mod dc_sync_status;
+mod diagnostics;
+pub(crate) mod exchange;
 mod group_id;
 mod handle;
+mod hot_connect;
 mod iterator;
+mod pdi_buffer;
+mod recovery;
+mod shutdown;
+mod transition;
+mod working_counter;
 
 use crate::{
     al_control::AlControl,
+    al_status_code::AlStatusCode,
     command::Command,
-    error::{DistributedClockError, Error, Item, PduError},
+    error::{DistributedClockError, Error, Item},
+    events::Event,
     fmt,
     pdi::PdiOffset,
     pdu_loop::{CreatedFrame, ReceivedPdu},
     subdevice::{
-        configuration::PdoDirection, pdi::SubDevicePdi, IoRanges, SubDevice, SubDeviceRef,
+        configuration::PdoDirection, dc, pdi::SubDevicePdi, IoRanges, SubDevice, SubDeviceRef,
+        SubDeviceTopology, WatchdogConfig, WatchdogStatus,
     },
     timer_factory::IntoTimeout,
     DcSync, MainDevice, RegisterAddress, SubDeviceState,
 };
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
 use core::{
-    cell::UnsafeCell, marker::PhantomData, slice, sync::atomic::AtomicUsize, time::Duration,
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, Range},
+    slice,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
 };
+use cyclic_command::PdiCommand;
 use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+use exchange::PdiCursor;
+use working_counter::{PdiMapping, WATCHDOG_ACTIVE};
 
+pub use self::cyclic_command::CyclicCommandStrategy;
+pub use self::dc_sync_status::{DcDeviation, DcSyncEvent, DcSyncMonitor, DcSyncStatus};
+pub use self::exchange::CyclicGroup;
 pub use self::group_id::GroupId;
 pub use self::handle::SubDeviceGroupHandle;
 pub use self::iterator::GroupSubDeviceIterator;
+pub use self::pdi_buffer::{PdiBuffer, PdiPublisher, PdiSubscriber};
+pub use self::recovery::{RecoveryAction, SubDeviceHealth, SubDeviceRecovery};
+pub use self::shutdown::ShutdownConfig;
+pub use self::transition::{TransitionError, TransitionFailure};
+pub use self::working_counter::{SubDeviceDiagnostic, TxRxResponse, WkcDiagnostics};
 pub use configurator::SubDeviceGroupRef;
 
 static GROUP_ID: AtomicUsize = AtomicUsize::new(0);
@@ -79,11 +108,20 @@ pub struct HasDc {
 
 /// Marker trait for `SubDeviceGroup` typestates where all SubDevices have a PDI.
 #[doc(hidden)]
-pub trait HasPdi {}
+pub trait HasPdi {
+    /// The state all SubDevices in the group should be in.
+    const STATE: SubDeviceState;
+}
 
-impl HasPdi for PreOpPdi {}
-impl HasPdi for SafeOp {}
-impl HasPdi for Op {}
+impl HasPdi for PreOpPdi {
+    const STATE: SubDeviceState = SubDeviceState::PreOp;
+}
+impl HasPdi for SafeOp {
+    const STATE: SubDeviceState = SubDeviceState::SafeOp;
+}
+impl HasPdi for Op {
+    const STATE: SubDeviceState = SubDeviceState::Op;
+}
 
 #[doc(hidden)]
 pub trait IsPreOp {}
@@ -95,12 +133,47 @@ impl IsPreOp for PreOpPdi {}
 struct GroupInner<const MAX_SUBDEVICES: usize> {
     subdevices: heapless::Vec<AtomicRefCell<SubDevice>, MAX_SUBDEVICES>,
     pdi_start: PdiOffset,
+    /// Whether `pdi_start` has been assigned a range of logical addresses.
+    pdi_reserved: bool,
+    /// PDI ranges of each SubDevice, populated once FMMUs are configured.
+    pdi_mappings: heapless::Vec<PdiMapping, MAX_SUBDEVICES>,
+    cyclic_command_strategy: CyclicCommandStrategy,
+    pdi_layout: PdiLayout,
+    /// Watchdog configuration for SubDevices without their own configuration.
+    watchdog: Option<WatchdogConfig>,
+    /// DC configuration, kept so it can be reapplied to SubDevices that are reconfigured.
+    dc_configuration: Option<DcConfiguration>,
+    /// The working counter of the last process data exchange plus one, or `0` before the first
+    /// exchange.
+    last_working_counter: AtomicU32,
 }
 
 const CYCLIC_OP_ENABLE: u8 = 0b0000_0001;
 const SYNC0_ACTIVATE: u8 = 0b0000_0010;
 const SYNC1_ACTIVATE: u8 = 0b0000_0100;
 
+/// How SubDevice inputs and outputs are mapped into a group's logical address space.
+///
+/// Set with [`SubDeviceGroup::set_pdi_layout`] before the group is transitioned out of PRE-OP.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PdiLayout {
+    /// All SubDevice inputs followed by all SubDevice outputs (`IIIIOOOO`).
+    ///
+    /// Every cyclic frame carries the sum of the input and output lengths.
+    #[default]
+    Separate,
+
+    /// The inputs and outputs of each SubDevice share the same logical addresses.
+    ///
+    /// Each SubDevice occupies the larger of its input and output lengths, so a cyclic `LRW` is
+    /// roughly half the size of the [`Separate`](PdiLayout::Separate) layout when inputs and
+    /// outputs are balanced. The outputs are kept in their own buffer after the inputs, so the
+    /// group's `MAX_PDI` must be at least twice the overlapped length.
+    Overlapping,
+}
+
 /// Group distributed clock configuration.
 #[derive(Default, Debug, Copy, Clone)]
 pub struct DcConfiguration {
@@ -168,43 +241,94 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
             inner.pdi_start.start_address
         );
 
-        // Configure master read PDI mappings in the first section of the PDI
-        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
-            // We're in PRE-OP at this point
-            pdi_position = SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
-                .configure_fmmus(
-                    pdi_position,
-                    inner.pdi_start.start_address,
-                    PdoDirection::MasterRead,
-                )
-                .await?;
-        }
+        match inner.pdi_layout {
+            PdiLayout::Separate => {
+                // Configure master read PDI mappings in the first section of the PDI
+                for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+                    // We're in PRE-OP at this point
+                    pdi_position =
+                        SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
+                            .configure_fmmus(
+                                pdi_position,
+                                inner.pdi_start.start_address,
+                                PdoDirection::MasterRead,
+                            )
+                            .await?;
+                }
 
-        self.read_pdi_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
+                self.read_pdi_len =
+                    (pdi_position.start_address - inner.pdi_start.start_address) as usize;
 
-        fmt::debug!("SubDevice mailboxes configured and init hooks called");
+                fmt::debug!("SubDevice mailboxes configured and init hooks called");
 
-        // We configured all read PDI mappings as a contiguous block in the previous loop. Now we'll
-        // configure the write mappings in a separate loop. This means we have IIIIOOOO instead of
-        // IOIOIO.
-        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
-            let addr = subdevice.configured_address();
+                // We configured all read PDI mappings as a contiguous block in the previous loop.
+                // Now we'll configure the write mappings in a separate loop. This means we have
+                // IIIIOOOO instead of IOIOIO.
+                for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+                    let addr = subdevice.configured_address();
 
-            let mut subdevice_config = SubDeviceRef::new(maindevice, addr, subdevice);
+                    let mut subdevice_config = SubDeviceRef::new(maindevice, addr, subdevice);
 
-            // Still in PRE-OP
-            pdi_position = subdevice_config
-                .configure_fmmus(
-                    pdi_position,
-                    inner.pdi_start.start_address,
-                    PdoDirection::MasterWrite,
-                )
-                .await?;
+                    // Still in PRE-OP
+                    pdi_position = subdevice_config
+                        .configure_fmmus(
+                            pdi_position,
+                            inner.pdi_start.start_address,
+                            PdoDirection::MasterWrite,
+                        )
+                        .await?;
+                }
+            }
+            PdiLayout::Overlapping => {
+                // Map the inputs and outputs of each SubDevice to the same logical start address.
+                // The SubDevice reads its outputs from the frame before writing its inputs into
+                // the same bytes.
+                for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+                    let addr = subdevice.configured_address();
+
+                    let mut subdevice_config = SubDeviceRef::new(maindevice, addr, subdevice);
+
+                    let inputs_end = subdevice_config
+                        .configure_fmmus(
+                            pdi_position,
+                            inner.pdi_start.start_address,
+                            PdoDirection::MasterRead,
+                        )
+                        .await?;
+
+                    let outputs_end = subdevice_config
+                        .configure_fmmus(
+                            pdi_position,
+                            inner.pdi_start.start_address,
+                            PdoDirection::MasterWrite,
+                        )
+                        .await?;
+
+                    pdi_position = if inputs_end.start_address > outputs_end.start_address {
+                        inputs_end
+                    } else {
+                        outputs_end
+                    };
+                }
+
+                self.read_pdi_len =
+                    (pdi_position.start_address - inner.pdi_start.start_address) as usize;
+            }
         }
 
         fmt::debug!("SubDevice FMMUs configured for group. Able to move to SAFE-OP");
 
-        self.pdi_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
+        let logical_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
+
+        let (pdi_mappings, pdi_len) = Self::map_pdi(
+            inner.pdi_layout,
+            inner.subdevices.iter_mut().map(AtomicRefCell::get_mut),
+            self.read_pdi_len,
+            logical_len,
+        );
+
+        inner.pdi_mappings = pdi_mappings;
+        self.pdi_len = pdi_len;
 
         fmt::debug!(
             "Group PDI length: start {:#010x}, {} total bytes ({} input bytes)",
@@ -223,6 +347,14 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
         Ok(())
     }
 
+    /// Set how SubDevice inputs and outputs are mapped into the group PDI.
+    ///
+    /// Defaults to [`PdiLayout::Separate`]. The layout is applied when the group's FMMUs are
+    /// configured during the transition out of PRE-OP.
+    pub fn set_pdi_layout(&mut self, layout: PdiLayout) {
+        self.inner.get_mut().pdi_layout = layout;
+    }
+
     /// Borrow an individual SubDevice.
     ///
     /// Each SubDevice in the group is wrapped in an `AtomicRefCell`, meaning it may only have a
@@ -269,7 +401,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         let self_ = self.into_safe_op(maindevice).await?;
 
         self_.into_op(maindevice).await
@@ -301,21 +434,19 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_safe_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         let self_ = self.into_pre_op_pdi(maindevice).await?;
 
-        // We're done configuring FMMUs, etc, now we can request all SubDevices in this group go into
-        // SAFE-OP
-        self_
-            .transition_to(maindevice, SubDeviceState::SafeOp)
-            .await
+        self_.into_safe_op(maindevice).await
     }
 
     /// Transition all SubDevices in the group from PRE-OP to INIT.
     pub async fn into_init(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         self.transition_to(maindevice, SubDeviceState::Init).await
     }
 
@@ -335,11 +466,19 @@ where
 {
     /// Configure Distributed Clock SYNC0 for all SubDevices in this group.
     ///
+    /// Per-SubDevice SYNC0/SYNC1 shifts and pulse lengths set with
+    /// [`SubDeviceRef::set_dc_sync_timing`](crate::SubDeviceRef::set_dc_sync_timing) are applied
+    /// on top of the group configuration.
+    ///
     /// # Errors
     ///
     /// This method will return with a
     /// [`Error::DistributedClock(DistributedClockError::NoReference)`](Error::DistributedClock)
     /// error if no DC reference SubDevice is present on the network.
+    ///
+    /// If the group or any SubDevice timings are inconsistent with
+    /// [`sync0_period`](DcConfiguration::sync0_period), for example a shift longer than the cycle
+    /// time, an [`Error::DistributedClock`] error is returned before any SubDevice is configured.
     pub async fn configure_dc_sync(
         self,
         maindevice: &MainDevice<'_>,
@@ -360,7 +499,7 @@ where
         } = dc_conf;
 
         // Coerce generics into concrete `PreOp` type as we don't need the PDI to configure the DC.
-        let self_ = SubDeviceGroup {
+        let mut self_ = SubDeviceGroup {
             id: self.id,
             pdi: self.pdi,
             read_pdi_len: self.read_pdi_len,
@@ -370,77 +509,49 @@ where
             _state: PhantomData::<PreOp>,
         };
 
-        // Only configure DC for those devices that want and support it
-        let dc_devices = GroupSubDeviceIterator::new(maindevice, &self_).filter(|subdevice| {
-            subdevice.dc_support().any() && !matches!(subdevice.dc_sync(), DcSync::Disabled)
-        });
-
-        for subdevice in dc_devices {
-            fmt::debug!(
-                "--> Configuring SubDevice {:#06x} {} DC mode {}",
-                subdevice.configured_address(),
-                subdevice.name(),
-                subdevice.dc_sync()
-            );
-
-            // Disable cyclic op, ignore WKC
-            subdevice
-                .write(RegisterAddress::DcSyncActive)
-                .ignore_wkc()
-                .send(maindevice, 0u8)
-                .await?;
-
-            // Write access to EtherCAT
-            subdevice
-                .write(RegisterAddress::DcCyclicUnitControl)
-                .send(maindevice, 0u8)
-                .await?;
-
-            let device_time: u64 = subdevice
-                .read(RegisterAddress::DcSystemTime)
-                .ignore_wkc()
-                .receive(maindevice)
-                .await?;
-
-            fmt::debug!("--> Device time {} ns", device_time);
-
-            let sync0_period = sync0_period.as_nanos() as u64;
-
-            let first_pulse_delay = start_delay.as_nanos() as u64;
+        dc::validate_cycle_time(sync0_period)?;
 
-            // Round first pulse time to a whole number of cycles
-            let start_time = (device_time + first_pulse_delay) / sync0_period * sync0_period;
+        if sync0_shift >= sync0_period {
+            fmt::error!(
+                "Group SYNC0 shift {} ns must be shorter than SYNC0 period {} ns",
+                sync0_shift.as_nanos(),
+                sync0_period.as_nanos()
+            );
 
-            fmt::debug!("--> Computed DC sync start time: {}", start_time);
+            return Err(DistributedClockError::ShiftTooLong.into());
+        }
 
-            subdevice
-                .write(RegisterAddress::DcSyncStartTime)
-                .send(maindevice, start_time)
-                .await?;
+        // Only configure DC for those devices that want and support it
+        let dc_devices = || {
+            GroupSubDeviceIterator::new(maindevice, &self_).filter(|subdevice| {
+                subdevice.dc_support().any() && !matches!(subdevice.dc_sync(), DcSync::Disabled)
+            })
+        };
 
-            // Cycle time in nanoseconds
+        // Check every SubDevice before writing anything so a bad config doesn't leave the group
+        // half configured.
+        for subdevice in dc_devices() {
             subdevice
-                .write(RegisterAddress::DcSync0CycleTime)
-                .send(maindevice, sync0_period)
-                .await?;
-
-            let flags = if let DcSync::Sync01 { sync1_period } = subdevice.dc_sync() {
-                subdevice
-                    .write(RegisterAddress::DcSync1CycleTime)
-                    .send(maindevice, sync1_period.as_nanos() as u64)
-                    .await?;
-
-                SYNC1_ACTIVATE | SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
-            } else {
-                SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
-            };
+                .dc_sync_timing()
+                .validate(subdevice.dc_sync(), sync0_period)
+                .map_err(|e| {
+                    fmt::error!(
+                        "SubDevice {:#06x} {} has invalid DC timing: {}",
+                        subdevice.configured_address(),
+                        subdevice.name(),
+                        e
+                    );
+
+                    e
+                })?;
+        }
 
-            subdevice
-                .write(RegisterAddress::DcSyncActive)
-                .send(maindevice, flags)
-                .await?;
+        for subdevice in dc_devices() {
+            configure_subdevice_dc_sync(maindevice, &subdevice, start_delay, sync0_period).await?;
         }
 
+        self_.inner.get_mut().dc_configuration = Some(dc_conf);
+
         Ok(SubDeviceGroup {
             id: self_.id,
             pdi: self_.pdi,
@@ -461,13 +572,37 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, DC>
 {
     /// Transition the SubDevice group from PRE-OP to SAFE-OP.
+    ///
+    /// Any watchdog configuration set with [`set_watchdog`](SubDeviceGroup::set_watchdog) or
+    /// [`SubDeviceRef::set_watchdog`] is written to the SubDevices before they are requested to
+    /// go into SAFE-OP.
     pub async fn into_safe_op(
-        self,
+        mut self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
+        self.configure_watchdogs(maindevice).await?;
+
+        // We're done configuring FMMUs, etc, now we can request all SubDevices in this group go into
+        // SAFE-OP
         self.transition_to(maindevice, SubDeviceState::SafeOp).await
     }
 
+    async fn configure_watchdogs(&mut self, maindevice: &MainDevice<'_>) -> Result<(), Error> {
+        let inner = self.inner.get_mut();
+
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            let subdevice =
+                SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice);
+
+            if let Some(config) = subdevice.watchdog().or(inner.watchdog) {
+                subdevice.configure_watchdog(config).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Transition all SubDevices in the group from PRE-OP to SAFE-OP, then to OP.
     ///
     /// This is a convenience method that calls [`into_safe_op`](SubDeviceGroup::into_safe_op) then
@@ -475,7 +610,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         let self_ = self.into_safe_op(maindevice).await?;
 
         self_.transition_to(maindevice, SubDeviceState::Op).await
@@ -492,7 +628,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn request_into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         let self_ = self.into_safe_op(maindevice).await?;
 
         self_.request_into_op(maindevice).await
@@ -502,7 +639,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_init(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         self.transition_to(maindevice, SubDeviceState::Init).await
     }
 }
@@ -514,7 +652,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         self.transition_to(maindevice, SubDeviceState::Op).await
     }
 
@@ -522,7 +661,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_pre_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         self.transition_to(maindevice, SubDeviceState::PreOp).await
     }
 
@@ -537,7 +677,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn request_into_op(
         mut self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         for subdevice in self
             .inner
             .get_mut()
@@ -546,7 +687,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
             .map(|subdevice| subdevice.get_mut())
         {
             SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
-                .request_subdevice_state_nowait(SubDeviceState::Op)
+                .request_state_nowait(SubDeviceState::Op)
                 .await?;
         }
 
@@ -569,7 +710,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_safe_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         self.transition_to(maindevice, SubDeviceState::SafeOp).await
     }
 
@@ -593,7 +735,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> Default
 {
     fn default() -> Self {
         Self {
-            id: GroupId(GROUP_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed)),
+            id: GroupId(GROUP_ID.fetch_add(1, Ordering::Relaxed)),
             pdi: UnsafeCell::new([0u8; MAX_PDI]),
             read_pdi_len: Default::default(),
             pdi_len: Default::default(),
@@ -604,6 +746,94 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> Default
     }
 }
 
+/// Write the SYNC0/SYNC1 configuration of a single SubDevice and start cyclic operation.
+async fn configure_subdevice_dc_sync<S>(
+    maindevice: &MainDevice<'_>,
+    subdevice: &SubDeviceRef<'_, S>,
+    start_delay: Duration,
+    sync0_period: Duration,
+) -> Result<(), Error>
+where
+    S: Deref<Target = SubDevice>,
+{
+    let timing = subdevice.dc_sync_timing();
+
+    fmt::debug!(
+        "--> Configuring SubDevice {:#06x} {} DC mode {}",
+        subdevice.configured_address(),
+        subdevice.name(),
+        subdevice.dc_sync()
+    );
+
+    // Disable cyclic op, ignore WKC
+    subdevice
+        .write(RegisterAddress::DcSyncActive)
+        .ignore_wkc()
+        .send(maindevice, 0u8)
+        .await?;
+
+    // Write access to EtherCAT
+    subdevice
+        .write(RegisterAddress::DcCyclicUnitControl)
+        .send(maindevice, 0u8)
+        .await?;
+
+    let device_time: u64 = subdevice
+        .read(RegisterAddress::DcSystemTime)
+        .ignore_wkc()
+        .receive(maindevice)
+        .await?;
+
+    fmt::debug!("--> Device time {} ns", device_time);
+
+    let sync0_period = sync0_period.as_nanos() as u64;
+
+    let first_pulse_delay = start_delay.as_nanos() as u64;
+
+    // Round first pulse time to a whole number of cycles, then shift this SubDevice's pulse
+    // within the cycle if required.
+    let start_time = (device_time + first_pulse_delay) / sync0_period * sync0_period
+        + timing.sync0_shift.as_nanos() as u64;
+
+    fmt::debug!("--> Computed DC sync start time: {}", start_time);
+
+    subdevice
+        .write(RegisterAddress::DcSyncStartTime)
+        .send(maindevice, start_time)
+        .await?;
+
+    // Cycle time in nanoseconds
+    subdevice
+        .write(RegisterAddress::DcSync0CycleTime)
+        .send(maindevice, sync0_period)
+        .await?;
+
+    if let Some(pulse_length) = timing.pulse_length_register()? {
+        subdevice
+            .write(RegisterAddress::DcSyncPulseLength)
+            .send(maindevice, pulse_length)
+            .await?;
+    }
+
+    let flags = if let Some(sync1_cycle_time) = timing.sync1_cycle_time(subdevice.dc_sync())? {
+        subdevice
+            .write(RegisterAddress::DcSync1CycleTime)
+            .send(maindevice, u64::from(sync1_cycle_time))
+            .await?;
+
+        SYNC1_ACTIVATE | SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
+    } else {
+        SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
+    };
+
+    subdevice
+        .write(RegisterAddress::DcSyncActive)
+        .send(maindevice, flags)
+        .await?;
+
+    Ok(())
+}
+
 /// Returned when a SubDevice's input or output PDI segment is empty.
 static EMPTY_PDI_SLICE: &[u8] = &[];
 
@@ -624,127 +854,181 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
         self.inner().subdevices.is_empty()
     }
 
-    #[allow(clippy::mut_from_ref)]
-    fn pdi_mut(&self) -> &mut [u8] {
-        let all_buf = unsafe { &mut *self.pdi.get() };
+    /// Get the topology of every SubDevice in this group, in network order.
+    ///
+    /// Pass this to [`MainDevice::topology`] to build a report of the whole network.
+    pub fn topology(&self) -> impl Iterator<Item = SubDeviceTopology> + '_ {
+        self.inner()
+            .subdevices
+            .iter()
+            .map(|subdevice| subdevice.borrow().topology())
+    }
 
-        &mut all_buf[0..self.pdi_len]
+    /// Get the commands used to exchange process data with this group.
+    pub fn cyclic_command_strategy(&self) -> CyclicCommandStrategy {
+        self.inner().cyclic_command_strategy
     }
 
-    fn pdi(&self) -> &[u8] {
-        let all_buf = unsafe { &*self.pdi.get() };
+    /// Get how SubDevice inputs and outputs are mapped into the group PDI.
+    pub fn pdi_layout(&self) -> PdiLayout {
+        self.inner().pdi_layout
+    }
 
-        &all_buf[0..self.pdi_len]
+    /// Set the commands used to exchange process data with this group.
+    ///
+    /// Defaults to [`CyclicCommandStrategy::Lrw`]. The strategy is kept when the group changes
+    /// state, so it can be set once before the group is transitioned into OP.
+    pub fn set_cyclic_command_strategy(&mut self, strategy: CyclicCommandStrategy) {
+        self.inner.get_mut().cyclic_command_strategy = strategy;
     }
 
-    /// Check if all SubDevices in the group are the given desired state.
-    async fn is_state(
+    /// Set the process data and PDI watchdog configuration for every SubDevice in this group.
+    ///
+    /// SubDevices with their own configuration set with [`SubDeviceRef::set_watchdog`] ignore the
+    /// group configuration. If neither is set, the SubDevice's watchdog registers are left
+    /// untouched.
+    ///
+    /// The configuration is written to the SubDevices during the PRE-OP -> SAFE-OP transition, so
+    /// it has no effect until the group next goes through that transition.
+    pub fn set_watchdog(&mut self, config: WatchdogConfig) {
+        self.inner.get_mut().watchdog = Some(config);
+    }
+
+    /// Read the DC system time difference of every DC-capable SubDevice in the group.
+    ///
+    /// The registers are read using as few frames as possible, so this method can be called
+    /// periodically alongside the process data cycle to monitor clock synchronisation. See
+    /// [`DcSyncMonitor`] to detect when SubDevices drift out of sync.
+    ///
+    /// # Errors
+    ///
+    /// An [`Error::WorkingCounter`] error is returned if any SubDevice fails to respond, and an
+    /// [`Error::Borrow`] error is returned if a SubDevice in the group is currently mutably
+    /// borrowed.
+    pub async fn dc_sync_status(
         &self,
         maindevice: &MainDevice<'_>,
-        desired_state: SubDeviceState,
-    ) -> Result<bool, Error> {
-        fmt::trace!("Check group state");
+    ) -> Result<DcSyncStatus<MAX_SUBDEVICES>, Error> {
+        let mut addresses = heapless::Vec::<u16, MAX_SUBDEVICES>::new();
 
-        let mut subdevices = self.inner().subdevices.iter().peekable();
+        for subdevice in self.inner().subdevices.iter() {
+            let subdevice = subdevice.try_borrow().map_err(|_| Error::Borrow)?;
 
-        let mut frame_idx = 0;
-
-        let mut total_checks = 0;
+            if subdevice.dc_support().any() {
+                // Vecs are the same capacity so this will never fail
+                let _ = addresses.push(subdevice.configured_address());
+            }
+        }
 
-        // Send as many frames as required to check statuses of all subdevices
-        loop {
-            let mut frame = maindevice.pdu_loop.alloc_frame()?;
+        let mut deviations = heapless::Vec::new();
 
-            let mut num_in_this_frame = 0;
+        maindevice
+            .batch_pdus(
+                addresses.iter().map(|address| {
+                    Command::fprd(*address, RegisterAddress::DcSystemTimeDifference.into()).into()
+                }),
+                u32::PACKED_LEN as u16,
+                |idx, pdu| {
+                    let raw = u32::unpack_from_slice(&pdu.wkc(1)?)?;
 
-            // Fill frame with status requests
-            while let Some(sd) = subdevices.peek() {
-                match frame.push_pdu(
-                    Command::fprd(
-                        sd.borrow().configured_address(),
-                        RegisterAddress::AlStatus.into(),
-                    )
-                    .into(),
-                    (),
-                    Some(AlControl::PACKED_LEN as u16),
-                ) {
-                    Ok(_) => {
-                        total_checks += 1;
-
-                        // We peeked at the current item, so now we need to remove it from the
-                        // iterator because it was successfully inserted into the frame.
-                        let _ = subdevices.next();
-                    }
-                    // Frame is full, we'll do more next time round
-                    Err(PduError::TooLong) => {
-                        fmt::trace!(
-                            "--> Pushed {} checks into frame {}",
-                            num_in_this_frame,
-                            frame_idx
-                        );
-
-                        break;
-                    }
-                    // Bail on a legitimate failure
-                    Err(e) => return Err(e.into()),
-                }
+                    let _ = deviations.push(DcDeviation::from_register(addresses[idx], raw));
 
-                num_in_this_frame += 1;
+                    Ok(())
+                },
+            )
+            .await?;
 
-                // A status check datagram is 14 bytes, meaning we can fit at most just over 100
-                // checks per normal EtherCAT frame. This leaves spare PDU indices available for
-                // other purposes, however if the user is using jumbo frames or something, we should
-                // always leave some indices free for e.g. other threads.
-                if num_in_this_frame > 128 {
-                    break;
-                }
-            }
+        Ok(DcSyncStatus { deviations })
+    }
 
-            // Nothing to send, we've checked all SDs
-            if num_in_this_frame == 0 {
-                fmt::trace!("--> No more state checks, pushed {}", total_checks);
+    /// Position of the first output byte in the group PDI buffer, relative to its logical address.
+    fn outputs_offset(layout: PdiLayout, read_pdi_len: usize) -> usize {
+        match layout {
+            PdiLayout::Separate => 0,
+            PdiLayout::Overlapping => read_pdi_len,
+        }
+    }
 
-                break;
-            }
+    /// Record the logical PDI ranges of each SubDevice once its FMMUs are configured, and move its
+    /// outputs to where they are stored in the group PDI buffer.
+    ///
+    /// `logical_len` is the number of logical bytes mapped by the group's FMMUs. Returns the PDI
+    /// mappings and the length of the group PDI buffer.
+    fn map_pdi<'a>(
+        layout: PdiLayout,
+        subdevices: impl Iterator<Item = &'a mut SubDevice>,
+        read_pdi_len: usize,
+        logical_len: usize,
+    ) -> (heapless::Vec<PdiMapping, MAX_SUBDEVICES>, usize) {
+        // With overlapping inputs and outputs, the received inputs overwrite the sent outputs, so
+        // outputs are stored in their own section of the buffer after the inputs. They don't take
+        // up any more logical address space.
+        let outputs_offset = Self::outputs_offset(layout, read_pdi_len);
+
+        let mappings = subdevices
+            .map(|subdevice| {
+                let io = &mut subdevice.config.io;
+
+                // Working counter calculations use logical addresses, not PDI buffer positions
+                let mapping = PdiMapping {
+                    configured_address: subdevice.configured_address,
+                    inputs: io.input.bytes.clone(),
+                    outputs: io.output.bytes.clone(),
+                };
 
-            let frame = frame.mark_sendable(
-                &maindevice.pdu_loop,
-                maindevice.timeouts.pdu,
-                maindevice.config.retry_behaviour.retry_count(),
-            );
+                io.offset_outputs(outputs_offset);
 
-            maindevice.pdu_loop.wake_sender();
+                mapping
+            })
+            .collect();
 
-            let received = frame.await?;
+        (mappings, logical_len + outputs_offset)
+    }
 
-            for pdu in received.into_iter() {
-                let pdu = pdu?;
+    #[allow(clippy::mut_from_ref)]
+    fn pdi_mut(&self) -> &mut [u8] {
+        let all_buf = unsafe { &mut *self.pdi.get() };
 
-                let result = AlControl::unpack_from_slice(&pdu)?;
+        &mut all_buf[0..self.pdi_len]
+    }
 
-                if result.state != desired_state {
-                    return Ok(false);
-                }
-            }
+    fn pdi(&self) -> &[u8] {
+        let all_buf = unsafe { &*self.pdi.get() };
 
-            frame_idx += 1;
-        }
+        &all_buf[0..self.pdi_len]
+    }
 
-        // Just sanity checking myself
-        debug_assert_eq!(total_checks, self.len());
+    /// Check if all SubDevices in the group are the given desired state.
+    async fn is_state(
+        &self,
+        maindevice: &MainDevice<'_>,
+        desired_state: SubDeviceState,
+    ) -> Result<bool, Error> {
+        fmt::trace!("Check group state");
 
-        Ok(true)
+        Ok(self
+            .not_in_state(maindevice, desired_state)
+            .await?
+            .is_empty())
     }
 
     /// Wait for all SubDevices in this group to transition to the given state.
+    ///
+    /// If the transition times out, the returned error lists every SubDevice that did not reach
+    /// the desired state.
     async fn wait_for_state(
         &self,
         maindevice: &MainDevice<'_>,
         desired_state: SubDeviceState,
-    ) -> Result<(), Error> {
-        async {
+    ) -> Result<(), TransitionError<MAX_SUBDEVICES>> {
+        // The SubDevices that were not in the desired state the last time the group was checked
+        let mut failed = heapless::Vec::new();
+
+        let result = async {
             loop {
-                if self.is_state(maindevice, desired_state).await? {
+                failed = self.not_in_state(maindevice, desired_state).await?;
+
+                if failed.is_empty() {
                     break Ok(());
                 }
 
@@ -752,7 +1036,14 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
             }
         }
         .timeout(maindevice.timeouts.state_transition)
-        .await
+        .await;
+
+        match result {
+            Err(Error::Timeout) => Err(self
+                .transition_error(maindevice, desired_state, failed)
+                .await),
+            result => result.map_err(TransitionError::from),
+        }
     }
 
     /// Transition to a new state.
@@ -760,7 +1051,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
         mut self,
         maindevice: &MainDevice<'_>,
         desired_state: SubDeviceState,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, TO, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, TO, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
         // We're done configuring FMMUs, etc, now we can request all SubDevices in this group go into
         // SAFE-OP
         for subdevice in self
@@ -771,7 +1063,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
             .map(AtomicRefCell::get_mut)
         {
             SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
-                .request_subdevice_state_nowait(desired_state)
+                .request_state_nowait(desired_state)
                 .await?;
         }
 
@@ -781,6 +1073,13 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
 
         fmt::debug!("--> Group reached state {}", desired_state);
 
+        for subdevice in self.inner.get_mut().subdevices.iter_mut() {
+            maindevice.publish(Event::StateChange {
+                configured_address: subdevice.get_mut().configured_address(),
+                state: desired_state,
+            });
+        }
+
         Ok(SubDeviceGroup {
             id: self.id,
             pdi: self.pdi,
@@ -885,18 +1184,121 @@ where
         GroupSubDeviceIterator::new(maindevice, self)
     }
 
+    /// Get the position of a SubDevice's inputs and outputs in the group PDI.
+    ///
+    /// The returned `(inputs, outputs)` byte ranges can be used to find a SubDevice's process data
+    /// in images published with [`publish_pdi`](SubDeviceGroup::publish_pdi), or to build output
+    /// images for [`apply_staged_outputs`](SubDeviceGroup::apply_staged_outputs).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of range, or the SubDevice is currently mutably
+    /// borrowed.
+    pub fn pdi_ranges(&self, index: usize) -> Result<(Range<usize>, Range<usize>), Error> {
+        let subdevice = self
+            .inner()
+            .subdevices
+            .get(index)
+            .ok_or(Error::NotFound {
+                item: Item::SubDevice,
+                index: Some(index),
+            })?
+            .try_borrow()
+            .map_err(|_| Error::Borrow)?;
+
+        let io = subdevice.io_segments();
+
+        Ok((io.input.bytes.clone(), io.output.bytes.clone()))
+    }
+
+    /// Publish a copy of the whole group PDI.
+    ///
+    /// Call this after [`tx_rx`](SubDeviceGroup::tx_rx) to give other threads or tasks a
+    /// consistent snapshot of the group's inputs through the matching
+    /// [`PdiSubscriber`]. Publishing never blocks, and the subscriber will never see a partially
+    /// updated image.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::{Op, PdiBuffer}};
+    /// static INPUTS: PdiBuffer<64> = PdiBuffer::new();
+    ///
+    /// # async fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let (mut publisher, mut subscriber) = INPUTS.try_split().expect("can only split once");
+    ///
+    /// let (inputs, _outputs) = group.pdi_ranges(1)?;
+    ///
+    /// std::thread::spawn(move || loop {
+    ///     let image = subscriber.read();
+    ///
+    ///     if let Some(inputs) = image.get(inputs.clone()) {
+    ///         println!("SubDevice 1 inputs {:?}", inputs);
+    ///     }
+    ///
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    /// });
+    ///
+    /// loop {
+    ///     group.tx_rx(maindevice).await?;
+    ///
+    ///     group.publish_pdi(&mut publisher);
+    /// }
+    /// # }
+    /// ```
+    pub fn publish_pdi(&self, publisher: &mut PdiPublisher<'_, MAX_PDI>) {
+        publisher.publish_with(self.pdi_len, |image| image.copy_from_slice(self.pdi()));
+    }
+
+    /// Copy the outputs of the most recently staged image into the group PDI.
+    ///
+    /// Images are staged by another thread or task with [`PdiPublisher::publish`] and must be laid
+    /// out the same as the group PDI, e.g. by using [`pdi_ranges`](SubDeviceGroup::pdi_ranges).
+    /// Only the output bytes of the image are copied. Call this before
+    /// [`tx_rx`](SubDeviceGroup::tx_rx) so complete output images are sent each cycle.
+    ///
+    /// Returns `true` if new outputs were applied, or `false` if nothing new was staged since the
+    /// last call or the staged image is too short to hold the group's outputs.
+    pub fn apply_staged_outputs(&self, subscriber: &mut PdiSubscriber<'_, MAX_PDI>) -> bool {
+        if !subscriber.update() {
+            return false;
+        }
+
+        let outputs = self.read_pdi_len..self.pdi_len;
+
+        let Some(staged) = subscriber.current().get(outputs.clone()) else {
+            fmt::warn!(
+                "Staged output image of {} bytes is too short, expected {}",
+                subscriber.current().len(),
+                self.pdi_len
+            );
+
+            return false;
+        };
+
+        self.pdi_mut()[outputs].copy_from_slice(staged);
+
+        true
+    }
+
     /// Drive the SubDevice group's inputs and outputs.
     ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicCommandStrategy`].
     ///
-    /// This method returns the working counter on success.
+    /// This method returns the received and expected working counter on success. To find out
+    /// which SubDevices are responsible for a working counter mismatch, see
+    /// [`tx_rx_diagnostic`](SubDeviceGroup::tx_rx_diagnostic).
     ///
     /// # Errors
     ///
     /// This method will return with an error if the PDU could not be sent over the network, or the
     /// response times out.
-    pub async fn tx_rx<'sto>(&self, maindevice: &'sto MainDevice<'sto>) -> Result<u16, Error> {
+    pub async fn tx_rx<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+    ) -> Result<TxRxResponse, Error> {
         fmt::trace!(
             "Group TX/RX, start address {:#010x}, data len {}, of which read bytes: {}",
             self.inner().pdi_start.start_address,
@@ -904,55 +1306,222 @@ where
             self.read_pdi_len
         );
 
-        let mut remaining = self.pdi();
-        let mut total_bytes_sent = 0;
-        let mut lrw_wkc_sum = 0;
-
-        while !remaining.is_empty() {
-            let mut frame = maindevice.pdu_loop.alloc_frame()?;
+        exchange::exchange(maindevice, [self], None)
+            .await
+            .map(|([response], _time)| response)
+    }
 
-            let start_addr = self.inner().pdi_start.start_address + total_bytes_sent as u32;
+    /// Drive the SubDevice group's inputs and outputs, and find the cause of any working counter
+    /// mismatch.
+    ///
+    /// This method behaves like [`tx_rx`](SubDeviceGroup::tx_rx) while the working counter is
+    /// correct. If it is not, [`working_counter_diagnostics`](SubDeviceGroup::working_counter_diagnostics)
+    /// is called to read the status of every SubDevice in the group and any faulty SubDevices are
+    /// logged at `warn` level.
+    ///
+    /// Diagnostics require extra network round trips, so this method is best suited to groups in OP
+    /// where the working counter is expected to be correct.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{error::Error, MainDevice, SubDeviceGroup, subdevice_group::Op};
+    /// # async fn case(maindevice: &MainDevice<'_>, group: &SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let (response, diagnostics) = group.tx_rx_diagnostic(maindevice).await?;
+    ///
+    /// if let Some(diagnostics) = diagnostics {
+    ///     for subdevice in diagnostics.faulty() {
+    ///         log::error!(
+    ///             "SubDevice {:#06x} is in {} with status {}",
+    ///             subdevice.configured_address,
+    ///             subdevice.state,
+    ///             subdevice.status_code
+    ///         );
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tx_rx_diagnostic<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+    ) -> Result<(TxRxResponse, Option<WkcDiagnostics<MAX_SUBDEVICES>>), Error> {
+        let response = self.tx_rx(maindevice).await?;
 
-            let Some((bytes_in_this_chunk, pdu_handle)) =
-                frame.push_pdu_slice_rest(Command::lrw(start_addr).into(), remaining)?
-            else {
-                continue;
-            };
+        if response.working_counter_ok() {
+            return Ok((response, None));
+        }
 
-            remaining = &remaining[bytes_in_this_chunk..];
+        fmt::warn!(
+            "Group working counter {}, expected {}",
+            response.working_counter,
+            response.expected_working_counter
+        );
 
-            let frame = frame.mark_sendable(
-                &maindevice.pdu_loop,
-                maindevice.timeouts.pdu,
-                maindevice.config.retry_behaviour.retry_count(),
+        let diagnostics = self.working_counter_diagnostics(maindevice).await?;
+
+        for subdevice in diagnostics.faulty() {
+            fmt::warn!(
+                "--> SubDevice {:#06x} responding {:?}, state {}, error {:?}, status code {}, watchdog expired {:?}",
+                subdevice.configured_address,
+                subdevice.responding,
+                subdevice.state,
+                subdevice.error,
+                subdevice.status_code,
+                subdevice.watchdog_expired
             );
+        }
 
-            maindevice.pdu_loop.wake_sender();
+        Ok((response, Some(diagnostics)))
+    }
 
-            let received = frame.await?;
+    /// Read the AL status and process data watchdog status of every SubDevice in the group.
+    ///
+    /// This can be used to find which SubDevices are responsible for a working counter mismatch
+    /// reported by [`tx_rx`](SubDeviceGroup::tx_rx). Reads are packed into as few frames as
+    /// possible. SubDevices that do not respond are reported as such in the returned diagnostics
+    /// instead of causing an error.
+    ///
+    /// The PDI is not sent or received by this method.
+    pub async fn working_counter_diagnostics(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<WkcDiagnostics<MAX_SUBDEVICES>, Error> {
+        let mappings = &self.inner().pdi_mappings;
 
-            let wkc = self.process_received_pdi_chunk(
-                total_bytes_sent,
-                bytes_in_this_chunk,
-                &received.pdu(pdu_handle)?,
-            )?;
+        let mut subdevices = mappings
+            .iter()
+            .map(|mapping| SubDeviceDiagnostic::new(mapping.configured_address))
+            .collect::<heapless::Vec<_, MAX_SUBDEVICES>>();
 
-            total_bytes_sent += bytes_in_this_chunk;
-            lrw_wkc_sum += wkc;
-        }
+        // Read AL status and AL status code in one PDU
+        let status_code_offset = usize::from(
+            u16::from(RegisterAddress::AlStatusCode) - u16::from(RegisterAddress::AlStatus),
+        );
+
+        maindevice
+            .batch_pdus(
+                mappings.iter().map(|mapping| {
+                    Command::fprd(mapping.configured_address, RegisterAddress::AlStatus.into())
+                        .into()
+                }),
+                (status_code_offset + AlStatusCode::PACKED_LEN) as u16,
+                |idx, pdu| {
+                    let Some(subdevice) = subdevices.get_mut(idx) else {
+                        return Ok(());
+                    };
+
+                    if pdu.working_counter != 1 {
+                        return Ok(());
+                    }
+
+                    let status = AlControl::unpack_from_slice(&pdu)?;
+
+                    subdevice.responding = true;
+                    subdevice.state = status.state;
+                    subdevice.error = status.error;
+                    subdevice.status_code = AlStatusCode::unpack_from_slice(
+                        pdu.get(status_code_offset..).ok_or(Error::Internal)?,
+                    )?;
 
-        Ok(lrw_wkc_sum)
+                    Ok(())
+                },
+            )
+            .await?;
+
+        // The process data watchdog is only meaningful for SubDevices with outputs
+        let with_outputs = mappings
+            .iter()
+            .enumerate()
+            .filter(|(_, mapping)| mapping.has_outputs())
+            .collect::<heapless::Vec<_, MAX_SUBDEVICES>>();
+
+        maindevice
+            .batch_pdus(
+                with_outputs.iter().map(|(_, mapping)| {
+                    Command::fprd(
+                        mapping.configured_address,
+                        RegisterAddress::SyncManagerWatchdogStatus.into(),
+                    )
+                    .into()
+                }),
+                u8::PACKED_LEN as u16,
+                |idx, pdu| {
+                    let Some(subdevice) = with_outputs
+                        .get(idx)
+                        .and_then(|(subdevice_idx, _)| subdevices.get_mut(*subdevice_idx))
+                    else {
+                        return Ok(());
+                    };
+
+                    if pdu.working_counter == 1 {
+                        subdevice.watchdog_expired =
+                            u8::unpack_from_slice(&pdu)? & WATCHDOG_ACTIVE == 0;
+                    }
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        Ok(WkcDiagnostics {
+            expected_state: S::STATE,
+            subdevices,
+        })
+    }
+
+    /// Read the watchdog status and expiry counters of every SubDevice in the group.
+    ///
+    /// Reads are packed into as few frames as possible. The returned statuses are in group order,
+    /// with `None` for any SubDevice that did not respond.
+    ///
+    /// The PDI is not sent or received by this method.
+    pub async fn watchdog_status(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<heapless::Vec<Option<WatchdogStatus>, MAX_SUBDEVICES>, Error> {
+        let mappings = &self.inner().pdi_mappings;
+
+        let mut statuses = mappings
+            .iter()
+            .map(|_| None)
+            .collect::<heapless::Vec<_, MAX_SUBDEVICES>>();
+
+        maindevice
+            .batch_pdus(
+                mappings.iter().map(|mapping| {
+                    Command::fprd(
+                        mapping.configured_address,
+                        RegisterAddress::SyncManagerWatchdogStatus.into(),
+                    )
+                    .into()
+                }),
+                WatchdogStatus::PACKED_LEN as u16,
+                |idx, pdu| {
+                    if let Some(status) = statuses.get_mut(idx) {
+                        if pdu.working_counter == 1 {
+                            *status = Some(WatchdogStatus::unpack_from_slice(&pdu)?);
+                        }
+                    }
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        Ok(statuses)
     }
 
     /// Drive the SubDevice group's inputs and outputs and synchronise EtherCAT system time with
     /// `FRMW`.
     ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicCommandStrategy`].
     ///
-    /// This method returns the working counter and the current EtherCAT system time in nanoseconds
-    /// on success. If the PDI must be sent in multiple chunks, the returned working counter is the
-    /// sum of all returned working counter values.
+    /// This method returns the received and expected working counter and the current EtherCAT
+    /// system time in nanoseconds on success. If the PDI must be sent in multiple chunks, the
+    /// returned working counter is the sum of all returned working counter values.
     ///
     /// # Errors
     ///
@@ -961,7 +1530,7 @@ where
     pub async fn tx_rx_sync_system_time<'sto>(
         &self,
         maindevice: &'sto MainDevice<'sto>,
-    ) -> Result<(u16, Option<u64>), Error> {
+    ) -> Result<(TxRxResponse, Option<u64>), Error> {
         fmt::trace!(
             "Group TX/RX with DC sync, start address {:#010x}, data len {}, of which read bytes: {}",
             self.inner().pdi_start.start_address,
@@ -970,93 +1539,87 @@ where
         );
 
         if let Some(dc_ref) = maindevice.dc_ref_address() {
-            let mut remaining = self.pdi();
-            let mut total_bytes_sent = 0;
-            let mut time = 0;
-            let mut lrw_wkc_sum = 0;
-            let mut time_read = false;
-
-            loop {
-                let mut frame = maindevice.pdu_loop.alloc_frame()?;
-
-                let dc_handle = if !time_read {
-                    let dc_handle = frame.push_pdu(
-                        Command::frmw(dc_ref, RegisterAddress::DcSystemTime.into()).into(),
-                        0u64,
-                        None,
-                    )?;
-
-                    // Just double checking
-                    debug_assert_eq!(dc_handle.alloc_size, DC_PDU_SIZE);
-
-                    Some(dc_handle)
-                } else {
-                    None
-                };
-
-                let start_addr = self.inner().pdi_start.start_address + total_bytes_sent as u32;
-
-                let Some((bytes_in_this_chunk, pdu_handle)) =
-                    frame.push_pdu_slice_rest(Command::lrw(start_addr).into(), remaining)?
-                else {
-                    continue;
-                };
-
-                fmt::trace!("Wrote {} byte chunk", bytes_in_this_chunk);
-
-                remaining = &remaining[bytes_in_this_chunk..];
-
-                let frame = frame.mark_sendable(
-                    &maindevice.pdu_loop,
-                    maindevice.timeouts.pdu,
-                    maindevice.config.retry_behaviour.retry_count(),
-                );
-
-                maindevice.pdu_loop.wake_sender();
+            exchange::exchange(maindevice, [self], Some(dc_ref))
+                .await
+                .map(|([response], time)| (response, Some(time.unwrap_or(0))))
+        } else {
+            self.tx_rx(maindevice)
+                .await
+                .map(|response| (response, None))
+        }
+    }
+}
 
-                let received = frame.await?;
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC> exchange::sealed::CyclicGroupInner
+    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+where
+    S: HasPdi,
+{
+    fn start_exchange(&self, with_dc: bool) -> PdiCursor {
+        let strategy = self.inner().cyclic_command_strategy;
+        let layout = self.inner().pdi_layout;
+
+        // Logical ranges of the inputs and outputs relative to the start of the group, and where the
+        // sent outputs are stored in the PDI buffer relative to their logical address.
+        let outputs_offset = Self::outputs_offset(layout, self.read_pdi_len);
+        let inputs = 0..self.read_pdi_len;
+        let outputs = match layout {
+            // PDI is organised as IIIIOOOO, so the inputs and outputs can be sent as separate
+            // contiguous commands.
+            PdiLayout::Separate => self.read_pdi_len..self.pdi_len,
+            PdiLayout::Overlapping => 0..self.read_pdi_len,
+        };
 
-                if let Some(dc_handle) = dc_handle {
-                    time = received
-                        .pdu(dc_handle)
-                        .and_then(|rx| u64::unpack_from_slice(&rx).map_err(Error::from))?;
+        // Each item is the command, the logical range to send and the offset of the data to send
+        // in the PDI buffer.
+        let segments: heapless::Vec<(PdiCommand, Range<usize>, usize), 2> = match strategy {
+            // An empty LRW is still sent alongside the DC sync PDU
+            CyclicCommandStrategy::Lrw if self.pdi_len > 0 || with_dc => {
+                heapless::Vec::from_iter([(
+                    PdiCommand::Lrw,
+                    0..inputs.end.max(outputs.end),
+                    outputs_offset,
+                )])
+            }
+            CyclicCommandStrategy::Lrw => heapless::Vec::new(),
+            CyclicCommandStrategy::LrdLwr | CyclicCommandStrategy::LrdLwrSeparateFrames => [
+                (PdiCommand::Lrd, inputs, 0),
+                (PdiCommand::Lwr, outputs, outputs_offset),
+            ]
+            .into_iter()
+            .filter(|(_, range, _)| !range.is_empty())
+            .collect(),
+        };
 
-                    time_read = true;
-                }
+        PdiCursor::new(
+            segments,
+            strategy == CyclicCommandStrategy::LrdLwrSeparateFrames,
+        )
+    }
 
-                let wkc = self.process_received_pdi_chunk(
-                    total_bytes_sent,
-                    bytes_in_this_chunk,
-                    &received.pdu(pdu_handle)?,
-                )?;
+    fn pdi_buffer(&self) -> (u32, &[u8]) {
+        (self.inner().pdi_start.start_address, self.pdi())
+    }
 
-                total_bytes_sent += bytes_in_this_chunk;
-                lrw_wkc_sum += wkc;
+    fn working_counter_changed(&self, working_counter: u16) -> bool {
+        let stored = u32::from(working_counter) + 1;
 
-                // NOTE: Not using a while loop as we want to always send the DC sync PDU even if
-                // the PDI is empty.
-                if remaining.is_empty() {
-                    break Ok((lrw_wkc_sum, Some(time)));
-                }
-            }
-        } else {
-            self.tx_rx(maindevice).await.map(|wkc| (wkc, None))
-        }
+        self.inner()
+            .last_working_counter
+            .swap(stored, Ordering::Relaxed)
+            != stored
     }
 
     fn process_received_pdi_chunk(
         &self,
-        total_bytes_sent: usize,
-        bytes_in_this_chunk: usize,
+        command: PdiCommand,
+        chunk: Range<usize>,
         data: &ReceivedPdu<'_>,
-    ) -> Result<u16, Error> {
-        let wkc = data.working_counter;
-
-        // If we've read the inputs chunk, write it back into the PDI (PDI is organised as
-        // IIIIOOOO)
-        if bytes_in_this_chunk > 0 && total_bytes_sent < self.read_pdi_len {
-            let inputs_range =
-                total_bytes_sent..(total_bytes_sent + self.read_pdi_len.min(bytes_in_this_chunk));
+    ) -> Result<TxRxResponse, Error> {
+        // If we've read any of the inputs, write them back into the PDI. Inputs are always at the
+        // start of the PDI buffer, at the same offset as their logical address.
+        if command != PdiCommand::Lwr && chunk.start < self.read_pdi_len {
+            let inputs_range = chunk.start..chunk.end.min(self.read_pdi_len);
 
             self.pdi_mut()
                 .get_mut(inputs_range.clone())
@@ -1064,10 +1627,25 @@ where
                 .copy_from_slice(data.get(0..inputs_range.len()).ok_or(Error::Internal)?);
         }
 
-        Ok(wkc)
+        Ok(TxRxResponse {
+            working_counter: data.working_counter,
+            expected_working_counter: self
+                .inner()
+                .pdi_mappings
+                .iter()
+                .map(|mapping| mapping.expected_working_counter(command, &chunk))
+                .sum(),
+        })
     }
 }
 
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC> CyclicGroup
+    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+where
+    S: HasPdi,
+{
+}
+
 // Methods for when the group has a PDI AND has Distributed Clocks configured
 impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S>
     SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, HasDc>
@@ -1078,10 +1656,12 @@ where
     /// and return cycle timing information.
     ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicCommandStrategy`].
     ///
-    /// This method returns the working counter and a [`CycleInfo`], containing values that can be
-    /// used to synchronise the MainDevice to the network SYNC0 event.
+    /// This method returns the received and expected working counter and a [`CycleInfo`],
+    /// containing values that can be used to synchronise the MainDevice to the network SYNC0
+    /// event.
     ///
     /// # Errors
     ///
@@ -1182,7 +1762,7 @@ where
     pub async fn tx_rx_dc<'sto>(
         &self,
         maindevice: &'sto MainDevice<'sto>,
-    ) -> Result<(u16, CycleInfo), Error> {
+    ) -> Result<(TxRxResponse, CycleInfo), Error> {
         fmt::trace!(
             "Group TX/RX with DC sync, start address {:#010x}, data len {}, of which read bytes: {}",
             self.inner().pdi_start.start_address,
@@ -1190,74 +1770,10 @@ where
             self.read_pdi_len
         );
 
-        let mut remaining = self.pdi();
-        let mut total_bytes_sent = 0;
-        let mut time = 0;
-        let mut lrw_wkc_sum = 0;
-        let mut time_read = false;
-
-        loop {
-            let mut frame = maindevice.pdu_loop.alloc_frame()?;
-
-            let dc_handle = if !time_read {
-                let dc_handle = frame.push_pdu(
-                    Command::frmw(self.dc_conf.reference, RegisterAddress::DcSystemTime.into())
-                        .into(),
-                    0u64,
-                    None,
-                )?;
-
-                // Just double checking
-                debug_assert_eq!(dc_handle.alloc_size, DC_PDU_SIZE);
-
-                Some(dc_handle)
-            } else {
-                None
-            };
-
-            let start_addr = self.inner().pdi_start.start_address + total_bytes_sent as u32;
-
-            let Some((bytes_in_this_chunk, pdu_handle)) =
-                frame.push_pdu_slice_rest(Command::lrw(start_addr).into(), remaining)?
-            else {
-                continue;
-            };
-
-            remaining = &remaining[bytes_in_this_chunk..];
-
-            let frame = frame.mark_sendable(
-                &maindevice.pdu_loop,
-                maindevice.timeouts.pdu,
-                maindevice.config.retry_behaviour.retry_count(),
-            );
-
-            maindevice.pdu_loop.wake_sender();
-
-            let received = frame.await?;
-
-            if let Some(dc_handle) = dc_handle {
-                time = received
-                    .pdu(dc_handle)
-                    .and_then(|rx| u64::unpack_from_slice(&rx).map_err(Error::from))?;
+        let ([response], time) =
+            exchange::exchange(maindevice, [self], Some(self.dc_conf.reference)).await?;
 
-                time_read = true;
-            }
-
-            let wkc = self.process_received_pdi_chunk(
-                total_bytes_sent,
-                bytes_in_this_chunk,
-                &received.pdu(pdu_handle)?,
-            )?;
-
-            total_bytes_sent += bytes_in_this_chunk;
-            lrw_wkc_sum += wkc;
-
-            // NOTE: Not using a while loop as we want to always send the DC sync PDU even if the
-            // PDI is empty.
-            if remaining.is_empty() {
-                break;
-            }
-        }
+        let time = time.unwrap_or(0);
 
         // Nanoseconds from the start of the cycle. This works because the first SYNC0 pulse
         // time is rounded to a whole number of `sync0_period`-length cycles.
@@ -1267,7 +1783,7 @@ where
             (self.dc_conf.sync0_period - cycle_start_offset) + self.dc_conf.sync0_shift;
 
         Ok((
-            lrw_wkc_sum,
+            response,
             CycleInfo {
                 dc_system_time: time,
                 cycle_start_offset: Duration::from_nanos(cycle_start_offset),
@@ -1276,3 +1792,174 @@ where
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdi::PdiSegment;
+
+    type Group = SubDeviceGroup<4, 64>;
+
+    fn subdevice(
+        configured_address: u16,
+        inputs: Range<usize>,
+        outputs: Range<usize>,
+    ) -> SubDevice {
+        let mut subdevice = SubDevice {
+            configured_address,
+            ..SubDevice::default()
+        };
+
+        subdevice.config.io = IoRanges {
+            input: PdiSegment {
+                bit_len: inputs.len() * 8,
+                bytes: inputs,
+            },
+            output: PdiSegment {
+                bit_len: outputs.len() * 8,
+                bytes: outputs,
+            },
+        };
+
+        subdevice
+    }
+
+    fn expected_working_counter(
+        mappings: &[PdiMapping],
+        command: PdiCommand,
+        chunk: Range<usize>,
+    ) -> u16 {
+        mappings
+            .iter()
+            .map(|mapping| mapping.expected_working_counter(command, &chunk))
+            .sum()
+    }
+
+    #[test]
+    fn separate_layout() {
+        // IIIIII OOOOOO
+        let mut subdevices = [
+            subdevice(0x1000, 0..4, 6..8),
+            subdevice(0x1001, 4..6, 8..12),
+        ];
+
+        let (mappings, pdi_len) = Group::map_pdi(PdiLayout::Separate, subdevices.iter_mut(), 6, 12);
+
+        assert_eq!(pdi_len, 12);
+        assert_eq!(
+            mappings,
+            [
+                PdiMapping {
+                    configured_address: 0x1000,
+                    inputs: 0..4,
+                    outputs: 6..8
+                },
+                PdiMapping {
+                    configured_address: 0x1001,
+                    inputs: 4..6,
+                    outputs: 8..12
+                }
+            ]
+        );
+
+        // Buffer positions are the same as logical positions
+        assert_eq!(subdevices[0].config.io.output.bytes, 6..8);
+        assert_eq!(subdevices[1].config.io.output.bytes, 8..12);
+
+        assert_eq!(
+            expected_working_counter(&mappings, PdiCommand::Lrw, 0..12),
+            6
+        );
+    }
+
+    #[test]
+    fn overlapping_layout() {
+        // Inputs and outputs of each SubDevice start at the same logical address, as configured by
+        // `configure_fmmus`: the next SubDevice starts after the longer of the two.
+        let mut subdevices = [
+            subdevice(0x1000, 0..4, 0..2),
+            subdevice(0x1001, 4..6, 4..8),
+            // Outputs only
+            subdevice(0x1002, 8..8, 8..9),
+        ];
+
+        let logical_len = 9;
+        let read_pdi_len = logical_len;
+
+        assert_eq!(
+            Group::outputs_offset(PdiLayout::Overlapping, read_pdi_len),
+            9
+        );
+
+        let (mappings, pdi_len) = Group::map_pdi(
+            PdiLayout::Overlapping,
+            subdevices.iter_mut(),
+            read_pdi_len,
+            logical_len,
+        );
+
+        // Only the logical length is mapped, but the buffer holds inputs then outputs
+        assert_eq!(pdi_len, 18);
+
+        // Working counters are calculated from logical addresses
+        assert_eq!(
+            mappings,
+            [
+                PdiMapping {
+                    configured_address: 0x1000,
+                    inputs: 0..4,
+                    outputs: 0..2
+                },
+                PdiMapping {
+                    configured_address: 0x1001,
+                    inputs: 4..6,
+                    outputs: 4..8
+                },
+                PdiMapping {
+                    configured_address: 0x1002,
+                    inputs: 8..8,
+                    outputs: 8..9
+                }
+            ]
+        );
+
+        // Outputs are stored after the inputs in the buffer
+        assert_eq!(subdevices[0].config.io.input.bytes, 0..4);
+        assert_eq!(subdevices[0].config.io.output.bytes, 9..11);
+        assert_eq!(subdevices[1].config.io.input.bytes, 4..6);
+        assert_eq!(subdevices[1].config.io.output.bytes, 13..17);
+        assert_eq!(subdevices[2].config.io.input.bytes, 8..8);
+        assert_eq!(subdevices[2].config.io.output.bytes, 17..18);
+
+        // A single LRW covers the whole logical range
+        assert_eq!(
+            expected_working_counter(&mappings, PdiCommand::Lrw, 0..read_pdi_len),
+            3 + 3 + 2
+        );
+        assert_eq!(
+            expected_working_counter(&mappings, PdiCommand::Lrd, 0..read_pdi_len),
+            2
+        );
+        assert_eq!(
+            expected_working_counter(&mappings, PdiCommand::Lwr, 0..read_pdi_len),
+            3
+        );
+    }
+
+    #[test]
+    fn overlapping_layout_inputs_only() {
+        let mut subdevices = [subdevice(0x1000, 0..2, 2..2)];
+
+        let (mappings, pdi_len) =
+            Group::map_pdi(PdiLayout::Overlapping, subdevices.iter_mut(), 2, 2);
+
+        assert_eq!(pdi_len, 4);
+
+        // Empty outputs aren't moved
+        assert_eq!(subdevices[0].config.io.output.bytes, 2..2);
+        assert_eq!(
+            expected_working_counter(&mappings, PdiCommand::Lrw, 0..2),
+            1
+        );
+    }
+}
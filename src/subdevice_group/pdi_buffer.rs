@@ -0,0 +1,283 @@
+//! Lock-free triple buffer used to share complete PDI images between tasks.
+
+use crate::error::Error;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+/// Set in [`PdiBuffer::state`] when the middle buffer holds an image the subscriber has not seen.
+const NEW_DATA: u8 = 0b100;
+
+/// Mask of the middle buffer index in [`PdiBuffer::state`].
+const INDEX_MASK: u8 = 0b011;
+
+struct Image<const N: usize> {
+    len: usize,
+    data: [u8; N],
+}
+
+/// A triple buffer holding complete copies of a process data image.
+///
+/// The buffer is split into a [`PdiPublisher`] and a [`PdiSubscriber`] which may be used from
+/// different threads or tasks. The publisher never waits for the subscriber and the subscriber
+/// never waits for the publisher, and the subscriber only ever sees complete images.
+///
+/// Two buffers are normally used with a [`SubDeviceGroup`](crate::SubDeviceGroup):
+///
+/// - One for inputs, published by the cyclic task with
+///   [`SubDeviceGroup::publish_pdi`](crate::SubDeviceGroup::publish_pdi) after each `tx_rx` and
+///   read by e.g. an HMI or logging task.
+/// - One for outputs, published by another task and applied by the cyclic task with
+///   [`SubDeviceGroup::apply_staged_outputs`](crate::SubDeviceGroup::apply_staged_outputs) before
+///   each `tx_rx`.
+///
+/// `N` should be the same as the group's `MAX_PDI`.
+///
+/// # Examples
+///
+/// ```rust
+/// use ethercrab::subdevice_group::PdiBuffer;
+///
+/// static INPUTS: PdiBuffer<64> = PdiBuffer::new();
+///
+/// let (mut publisher, mut subscriber) = INPUTS.try_split().expect("can only split once");
+///
+/// assert!(subscriber.read().is_empty());
+///
+/// publisher.publish(&[1, 2, 3]).expect("too long");
+/// publisher.publish(&[4, 5, 6]).expect("too long");
+///
+/// // Only the most recent image is kept
+/// assert_eq!(subscriber.read(), &[4, 5, 6]);
+/// ```
+pub struct PdiBuffer<const N: usize> {
+    images: [UnsafeCell<Image<N>>; 3],
+    /// Index of the middle buffer, along with a flag set when it holds new data.
+    state: AtomicU8,
+    is_split: AtomicBool,
+}
+
+// SAFETY: Each image is only ever accessed by the publisher or subscriber that owns its index.
+// Ownership is transferred by atomically swapping indices through `state`.
+unsafe impl<const N: usize> Sync for PdiBuffer<N> {}
+
+impl<const N: usize> PdiBuffer<N> {
+    /// Create a new, empty buffer.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            images: [
+                UnsafeCell::new(Image {
+                    len: 0,
+                    data: [0; N],
+                }),
+                UnsafeCell::new(Image {
+                    len: 0,
+                    data: [0; N],
+                }),
+                UnsafeCell::new(Image {
+                    len: 0,
+                    data: [0; N],
+                }),
+            ],
+            state: AtomicU8::new(1),
+            is_split: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the publishing and subscribing halves of this buffer.
+    ///
+    /// # Errors
+    ///
+    /// To make sure there is only ever one publisher and one subscriber, `try_split` will return
+    /// an error if called more than once on any given `PdiBuffer`.
+    #[allow(clippy::result_unit_err)]
+    pub fn try_split(&self) -> Result<(PdiPublisher<'_, N>, PdiSubscriber<'_, N>), ()> {
+        self.is_split
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .map_err(|_| ())?;
+
+        Ok((
+            PdiPublisher {
+                buffer: self,
+                back: 0,
+            },
+            PdiSubscriber {
+                buffer: self,
+                front: 2,
+            },
+        ))
+    }
+}
+
+/// The half of a [`PdiBuffer`] that publishes new images.
+pub struct PdiPublisher<'buf, const N: usize> {
+    buffer: &'buf PdiBuffer<N>,
+    /// Index of the image only this publisher may write to.
+    back: u8,
+}
+
+impl<'buf, const N: usize> core::fmt::Debug for PdiPublisher<'buf, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PdiPublisher")
+            .field("back", &self.back)
+            .finish()
+    }
+}
+
+impl<'buf, const N: usize> PdiPublisher<'buf, N> {
+    /// Publish a copy of `data`, replacing any image the subscriber has not read yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiTooLong`] if `data` is longer than the buffer.
+    pub fn publish(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > N {
+            return Err(Error::PdiTooLong {
+                max_length: N,
+                desired_length: data.len(),
+            });
+        }
+
+        self.publish_with(data.len(), |image| image.copy_from_slice(data));
+
+        Ok(())
+    }
+
+    /// Publish an image of `len` bytes written by `write`. `len` must not be more than `N`.
+    pub(crate) fn publish_with(&mut self, len: usize, write: impl FnOnce(&mut [u8])) {
+        // SAFETY: The back image is owned by this publisher until it is swapped below.
+        let image = unsafe { &mut *self.buffer.images[usize::from(self.back)].get() };
+
+        image.len = len;
+        write(&mut image.data[0..len]);
+
+        let prev = self
+            .buffer
+            .state
+            .swap(self.back | NEW_DATA, Ordering::AcqRel);
+
+        self.back = prev & INDEX_MASK;
+    }
+}
+
+/// The half of a [`PdiBuffer`] that reads the most recently published image.
+pub struct PdiSubscriber<'buf, const N: usize> {
+    buffer: &'buf PdiBuffer<N>,
+    /// Index of the image only this subscriber may read from.
+    front: u8,
+}
+
+impl<'buf, const N: usize> core::fmt::Debug for PdiSubscriber<'buf, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PdiSubscriber")
+            .field("front", &self.front)
+            .finish()
+    }
+}
+
+impl<'buf, const N: usize> PdiSubscriber<'buf, N> {
+    /// Fetch the most recently published image, if there is one that hasn't been seen yet.
+    ///
+    /// Returns `true` if a new image was fetched.
+    pub fn update(&mut self) -> bool {
+        if self.buffer.state.load(Ordering::Relaxed) & NEW_DATA == 0 {
+            return false;
+        }
+
+        let prev = self.buffer.state.swap(self.front, Ordering::AcqRel);
+
+        self.front = prev & INDEX_MASK;
+
+        true
+    }
+
+    /// Get the current image without fetching a newer one.
+    ///
+    /// This is empty until the first image has been fetched with
+    /// [`update`](PdiSubscriber::update) or [`read`](PdiSubscriber::read).
+    pub fn current(&self) -> &[u8] {
+        // SAFETY: The front image is owned by this subscriber until it is swapped in `update`,
+        // which requires a mutable reference.
+        let image = unsafe { &*self.buffer.images[usize::from(self.front)].get() };
+
+        &image.data[0..image.len]
+    }
+
+    /// Fetch the most recently published image and return it.
+    ///
+    /// If nothing new has been published since the last call, the previous image is returned.
+    pub fn read(&mut self) -> &[u8] {
+        self.update();
+
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn latest_image_wins() {
+        let buffer = PdiBuffer::<8>::new();
+
+        let (mut publisher, mut subscriber) = buffer.try_split().unwrap();
+
+        assert!(buffer.try_split().is_err());
+
+        assert!(!subscriber.update());
+        assert!(subscriber.current().is_empty());
+
+        publisher.publish(&[1, 2]).unwrap();
+
+        assert_eq!(subscriber.read(), &[1, 2]);
+        // Nothing new, so the same image is kept
+        assert!(!subscriber.update());
+        assert_eq!(subscriber.read(), &[1, 2]);
+
+        publisher.publish(&[3]).unwrap();
+        publisher.publish(&[4, 5, 6]).unwrap();
+
+        assert!(subscriber.update());
+        assert_eq!(subscriber.current(), &[4, 5, 6]);
+
+        assert_eq!(
+            publisher.publish(&[0; 9]),
+            Err(Error::PdiTooLong {
+                max_length: 8,
+                desired_length: 9
+            })
+        );
+    }
+
+    #[test]
+    fn no_torn_images() {
+        static BUFFER: PdiBuffer<256> = PdiBuffer::new();
+
+        let (mut publisher, mut subscriber) = BUFFER.try_split().unwrap();
+
+        let publisher = thread::spawn(move || {
+            for i in 0..=u8::MAX {
+                publisher.publish(&[i; 256]).unwrap();
+            }
+        });
+
+        let mut last = 0;
+
+        while !publisher.is_finished() || last < u8::MAX {
+            let image = subscriber.read();
+
+            if let Some(first) = image.first() {
+                assert!(image.iter().all(|b| b == first), "torn image");
+                assert!(*first >= last, "images must be received in order");
+
+                last = *first;
+            }
+        }
+
+        publisher.join().unwrap();
+    }
+}
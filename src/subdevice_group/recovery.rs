@@ -0,0 +1,716 @@
+//! Recovery of individual SubDevices that have dropped out of OP while the rest of the group keeps
+//! exchanging process data.
+
+use super::{configure_subdevice_dc_sync, DcConfiguration, Op, PdiMapping, SubDeviceGroup};
+use crate::{
+    al_control::AlControl,
+    al_status_code::AlStatusCode,
+    command::Command,
+    dc,
+    error::{Error, Item},
+    events::Event,
+    fmt,
+    subdevice::{SubDevice, SubDeviceRef, WatchdogConfig},
+    DcSupport, DcSync, MainDevice, RegisterAddress, StateRequest, SubDeviceState,
+};
+use atomic_refcell::AtomicRefMut;
+use core::ops::{Deref, DerefMut};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// What [`SubDeviceGroup::check_subdevices`] did to bring a SubDevice back into OP.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RecoveryAction {
+    /// The SubDevice was in SAFE-OP and has been requested to go back into OP.
+    RequestedOp,
+
+    /// The SubDevice fell back to INIT, PRE-OP or BOOT, or lost its configuration after being power
+    /// cycled. It must be reconfigured with [`SubDeviceGroup::reconfigure_subdevice`].
+    Reconfigure,
+
+    /// The SubDevice did not respond.
+    Lost,
+}
+
+/// A SubDevice found outside OP by [`SubDeviceGroup::check_subdevices`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SubDeviceHealth {
+    /// The index of the SubDevice in the group.
+    pub index: usize,
+
+    /// The configured station address of the SubDevice.
+    pub configured_address: u16,
+
+    /// The state the SubDevice was found in, or `None` if it did not respond.
+    pub state: Option<SubDeviceState>,
+
+    /// The AL status code of the error that was acknowledged, if the SubDevice had its error flag
+    /// set.
+    pub al_status_code: Option<AlStatusCode>,
+
+    /// Whether the SubDevice had lost its configured station address, e.g. because it was power
+    /// cycled, and was readdressed.
+    pub readdressed: bool,
+
+    /// What was done to recover the SubDevice.
+    pub action: RecoveryAction,
+}
+
+impl SubDeviceHealth {
+    /// The health of a SubDevice that did not respond at its configured address.
+    ///
+    /// A SubDevice that was found at its position in the network and readdressed is back in INIT
+    /// after being power cycled, so must be reconfigured.
+    fn readdressed(index: usize, configured_address: u16, readdressed: bool) -> Self {
+        Self {
+            index,
+            configured_address,
+            state: readdressed.then_some(SubDeviceState::Init),
+            al_status_code: None,
+            readdressed,
+            action: if readdressed {
+                RecoveryAction::Reconfigure
+            } else {
+                RecoveryAction::Lost
+            },
+        }
+    }
+}
+
+/// How [`SubDeviceGroup::check_subdevices`] handles a single SubDevice.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Check {
+    /// The SubDevice is in OP without an error.
+    Healthy,
+    /// The SubDevice responded, but is outside OP or has its error flag set.
+    Acknowledge(AlControl),
+    /// The SubDevice did not respond at its configured address.
+    Readdress,
+}
+
+impl Check {
+    /// `status` is `None` if the SubDevice did not respond.
+    fn new(status: Option<AlControl>) -> Self {
+        match status {
+            Some(AlControl {
+                state: SubDeviceState::Op,
+                error: false,
+                ..
+            }) => Self::Healthy,
+            Some(status) => Self::Acknowledge(status),
+            None => Self::Readdress,
+        }
+    }
+}
+
+/// The AL control writes that bring a responding SubDevice back towards OP, in order.
+fn recovery_requests(status: AlControl) -> (heapless::Vec<AlControl, 2>, RecoveryAction) {
+    let mut requests = heapless::Vec::new();
+
+    let action = match status.state {
+        SubDeviceState::SafeOp | SubDeviceState::Op => {
+            if status.error {
+                // Acknowledge the error in the current state
+                let _ = requests.push(AlControl::from(
+                    StateRequest::new(status.state).acknowledge_error(),
+                ));
+            }
+
+            let _ = requests.push(AlControl::new(SubDeviceState::Op));
+
+            RecoveryAction::RequestedOp
+        }
+        _ => {
+            if status.error {
+                let _ = requests.push(AlControl::reset());
+            }
+
+            RecoveryAction::Reconfigure
+        }
+    };
+
+    (requests, action)
+}
+
+/// The group DC configuration to write to a recovered SubDevice, if it uses DC sync.
+fn dc_sync_configuration(
+    dc_configuration: Option<DcConfiguration>,
+    dc_support: DcSupport,
+    dc_sync: DcSync,
+) -> Option<DcConfiguration> {
+    dc_configuration.filter(|_| dc_support.any() && !matches!(dc_sync, DcSync::Disabled))
+}
+
+/// A SubDevice being reconfigured by [`SubDeviceGroup::reconfigure_subdevice`].
+///
+/// The SubDevice is in PRE-OP. Any configuration normally done in PRE-OP, e.g. SDO writes to set up
+/// PDO mappings, should be repeated through this handle before calling
+/// [`into_op`](SubDeviceRecovery::into_op).
+#[derive(Debug)]
+pub struct SubDeviceRecovery<'group, 'maindevice> {
+    subdevice: SubDeviceRef<'maindevice, AtomicRefMut<'group, SubDevice>>,
+    mapping: PdiMapping,
+    group_start_address: u32,
+    outputs_offset: usize,
+    watchdog: Option<WatchdogConfig>,
+    dc_configuration: Option<DcConfiguration>,
+}
+
+impl<'group, 'maindevice> SubDeviceRecovery<'group, 'maindevice> {
+    /// Restore the SubDevice's process data configuration at its original position in the group
+    /// PDI, then transition it through SAFE-OP into OP.
+    ///
+    /// The group's watchdog and DC configuration are written to the SubDevice again. The rest of
+    /// the group must keep exchanging process data while this method runs, as most SubDevices
+    /// require valid outputs before entering OP.
+    ///
+    /// # Errors
+    ///
+    /// An [`Error::PdiLayout`] is returned if the SubDevice's process data length no longer
+    /// matches the space it occupies in the group PDI.
+    pub async fn into_op(mut self) -> Result<(), Error> {
+        let maindevice = self.subdevice.maindevice;
+
+        self.subdevice
+            .reconfigure_fmmus(
+                self.group_start_address,
+                self.mapping.inputs.clone(),
+                self.mapping.outputs.clone(),
+                self.outputs_offset,
+            )
+            .await?;
+
+        if let Some(config) = self.subdevice.watchdog().or(self.watchdog) {
+            self.subdevice.configure_watchdog(config).await?;
+        }
+
+        if let Some(DcConfiguration {
+            start_delay,
+            sync0_period,
+            ..
+        }) = dc_sync_configuration(
+            self.dc_configuration,
+            self.subdevice.dc_support(),
+            self.subdevice.dc_sync(),
+        ) {
+            configure_subdevice_dc_sync(maindevice, &self.subdevice, start_delay, sync0_period)
+                .await?;
+        }
+
+        self.subdevice.request_state(SubDeviceState::SafeOp).await?;
+
+        self.subdevice.request_state(SubDeviceState::Op).await?;
+
+        fmt::info!(
+            "SubDevice {:#06x} recovered into OP",
+            self.subdevice.configured_address()
+        );
+
+        Ok(())
+    }
+}
+
+impl<'group, 'maindevice> Deref for SubDeviceRecovery<'group, 'maindevice> {
+    type Target = SubDeviceRef<'maindevice, AtomicRefMut<'group, SubDevice>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.subdevice
+    }
+}
+
+impl<'group, 'maindevice> DerefMut for SubDeviceRecovery<'group, 'maindevice> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.subdevice
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>
+{
+    /// Find SubDevices that are no longer in OP and try to bring them back.
+    ///
+    /// This is intended to be called periodically from a task running alongside the process data
+    /// loop, e.g. when [`tx_rx`](SubDeviceGroup::tx_rx) reports a working counter mismatch. For
+    /// each SubDevice outside OP:
+    ///
+    /// - Any AL error is acknowledged.
+    /// - A SubDevice in SAFE-OP is requested to go back into OP.
+    /// - A SubDevice that has lost its configured station address is readdressed using its position
+    ///   in the network and, if it has the same identity as before, its DC system time offset is
+    ///   restored.
+    /// - A SubDevice in INIT, PRE-OP or BOOT must be reconfigured with
+    ///   [`reconfigure_subdevice`](SubDeviceGroup::reconfigure_subdevice).
+    ///
+    /// The returned list is empty if all SubDevices in the group are in OP.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     error::Error, std::ethercat_now, subdevice_group::RecoveryAction, MainDevice,
+    ///     MainDeviceConfig, PduStorage, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<8, 64>(ethercat_now)
+    ///     .await?
+    ///     .into_op(&maindevice)
+    ///     .await?;
+    ///
+    /// // Run alongside a task calling `group.tx_rx(&maindevice)`
+    /// for health in group.check_subdevices(&maindevice).await? {
+    ///     if health.action == RecoveryAction::Reconfigure {
+    ///         let subdevice = group.reconfigure_subdevice(&maindevice, health.index).await?;
+    ///
+    ///         // Repeat any PRE-OP configuration here, e.g.
+    ///         subdevice.sdo_write(0x1c12, 0, 0u8).await?;
+    ///
+    ///         subdevice.into_op().await?;
+    ///     }
+    /// }
+    /// # Ok::<(), Error>(())
+    /// # };
+    /// ```
+    pub async fn check_subdevices(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<heapless::Vec<SubDeviceHealth, MAX_SUBDEVICES>, Error> {
+        let mappings = &self.inner().pdi_mappings;
+
+        let mut states = mappings
+            .iter()
+            .map(|_| None)
+            .collect::<heapless::Vec<Option<AlControl>, MAX_SUBDEVICES>>();
+
+        maindevice
+            .batch_pdus(
+                mappings.iter().map(|mapping| {
+                    Command::fprd(mapping.configured_address, RegisterAddress::AlStatus.into())
+                        .into()
+                }),
+                AlControl::PACKED_LEN as u16,
+                |idx, pdu| {
+                    if let Some(state) = states.get_mut(idx) {
+                        if pdu.working_counter == 1 {
+                            *state = Some(AlControl::unpack_from_slice(&pdu)?);
+                        }
+                    }
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        let mut report = heapless::Vec::new();
+
+        for (index, (mapping, status)) in mappings.iter().zip(states).enumerate() {
+            let configured_address = mapping.configured_address;
+
+            let health = match Check::new(status) {
+                Check::Healthy => continue,
+                Check::Acknowledge(status) => {
+                    Self::acknowledge(maindevice, index, configured_address, status).await?
+                }
+                Check::Readdress => self.readdress(maindevice, index).await?,
+            };
+
+            fmt::warn!(
+                "SubDevice {:#06x} is in state {:?}, action {:?}",
+                configured_address,
+                health.state,
+                health.action
+            );
+
+            let state = health.state.unwrap_or(SubDeviceState::None);
+
+            maindevice.publish(Event::StateChange {
+                configured_address,
+                state,
+            });
+
+            if let Some(status_code) = health.al_status_code {
+                maindevice.publish(Event::AlStatus {
+                    configured_address,
+                    state,
+                    status_code,
+                });
+            }
+
+            // Never more than the number of SubDevices in the group
+            let _ = report.push(health);
+        }
+
+        Ok(report)
+    }
+
+    /// Acknowledge any error on a responding SubDevice and request OP if possible.
+    async fn acknowledge(
+        maindevice: &MainDevice<'_>,
+        index: usize,
+        configured_address: u16,
+        status: AlControl,
+    ) -> Result<SubDeviceHealth, Error> {
+        let al_status_code = if status.error {
+            Some(
+                Command::fprd(configured_address, RegisterAddress::AlStatusCode.into())
+                    .receive::<AlStatusCode>(maindevice)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        let (requests, action) = recovery_requests(status);
+
+        for request in requests {
+            Command::fpwr(configured_address, RegisterAddress::AlControl.into())
+                .send(maindevice, request)
+                .await?;
+        }
+
+        Ok(SubDeviceHealth {
+            index,
+            configured_address,
+            state: Some(status.state),
+            al_status_code,
+            readdressed: false,
+            action,
+        })
+    }
+
+    /// Look for a SubDevice that did not respond at its position in the network, and restore its
+    /// address if it was power cycled.
+    async fn readdress(
+        &self,
+        maindevice: &MainDevice<'_>,
+        index: usize,
+    ) -> Result<SubDeviceHealth, Error> {
+        let subdevice = self.borrow_subdevice(index)?;
+
+        let readdressed =
+            SubDeviceRef::new(maindevice, subdevice.configured_address(), &*subdevice)
+                .restore_address()
+                .await?;
+
+        if readdressed {
+            if let Some(reference) = maindevice
+                .dc_ref_address()
+                .filter(|_| subdevice.dc_support().any())
+            {
+                dc::restore_dc_parameters(maindevice, &subdevice, reference).await?;
+            }
+        }
+
+        Ok(SubDeviceHealth::readdressed(
+            index,
+            subdevice.configured_address(),
+            readdressed,
+        ))
+    }
+
+    /// Reset a single SubDevice to INIT and configure its mailboxes, leaving it in PRE-OP.
+    ///
+    /// The rest of the group is not affected and can keep exchanging process data. Any further
+    /// PRE-OP configuration can be done through the returned handle, after which
+    /// [`SubDeviceRecovery::into_op`] restores the SubDevice's process data configuration and
+    /// brings it back into OP.
+    ///
+    /// The SubDevice at `index` must not be borrowed elsewhere, e.g. with
+    /// [`subdevice`](SubDeviceGroup::subdevice), until the returned handle is dropped.
+    pub async fn reconfigure_subdevice<'group, 'maindevice>(
+        &'group self,
+        maindevice: &'maindevice MainDevice<'maindevice>,
+        index: usize,
+    ) -> Result<SubDeviceRecovery<'group, 'maindevice>, Error> {
+        let inner = self.inner();
+
+        let mapping = inner
+            .pdi_mappings
+            .get(index)
+            .ok_or(Error::NotFound {
+                item: Item::SubDevice,
+                index: Some(index),
+            })?
+            .clone();
+
+        let subdevice = self.borrow_subdevice(index)?;
+
+        let mut subdevice =
+            SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice);
+
+        fmt::debug!(
+            "Reconfiguring SubDevice {:#06x} {}",
+            subdevice.configured_address(),
+            subdevice.name()
+        );
+
+        subdevice
+            .request_state(StateRequest::new(SubDeviceState::Init).acknowledge_error())
+            .await?;
+
+        // Also transitions the SubDevice into PRE-OP
+        subdevice.configure_mailboxes().await?;
+
+        Ok(SubDeviceRecovery {
+            subdevice,
+            mapping,
+            group_start_address: inner.pdi_start.start_address,
+            outputs_offset: Self::outputs_offset(inner.pdi_layout, self.read_pdi_len),
+            watchdog: inner.watchdog,
+            dc_configuration: inner.dc_configuration,
+        })
+    }
+
+    fn borrow_subdevice(&self, index: usize) -> Result<AtomicRefMut<'_, SubDevice>, Error> {
+        self.inner()
+            .subdevices
+            .get(index)
+            .ok_or(Error::NotFound {
+                item: Item::SubDevice,
+                index: Some(index),
+            })?
+            .try_borrow_mut()
+            .map_err(|_e| {
+                fmt::error!("SubDevice index {} already borrowed", index);
+
+                Error::Borrow
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::PdiLayoutError,
+        pdi::PdiSegment,
+        subdevice::IoRanges,
+        subdevice_group::{PdiLayout, PreOp},
+    };
+    use core::{ops::Range, time::Duration};
+
+    fn status(state: SubDeviceState, error: bool) -> Option<AlControl> {
+        Some(AlControl {
+            state,
+            error,
+            id_request: false,
+        })
+    }
+
+    #[test]
+    fn check_by_state() {
+        assert_eq!(
+            Check::new(status(SubDeviceState::Op, false)),
+            Check::Healthy
+        );
+        assert_eq!(
+            Check::new(status(SubDeviceState::Op, true)),
+            Check::Acknowledge(status(SubDeviceState::Op, true).unwrap())
+        );
+        assert_eq!(
+            Check::new(status(SubDeviceState::SafeOp, false)),
+            Check::Acknowledge(status(SubDeviceState::SafeOp, false).unwrap())
+        );
+        assert_eq!(
+            Check::new(status(SubDeviceState::Init, false)),
+            Check::Acknowledge(status(SubDeviceState::Init, false).unwrap())
+        );
+        assert_eq!(Check::new(None), Check::Readdress);
+    }
+
+    #[test]
+    fn safe_op_requests_op() {
+        let (requests, action) = recovery_requests(status(SubDeviceState::SafeOp, false).unwrap());
+
+        assert_eq!(action, RecoveryAction::RequestedOp);
+        assert_eq!(requests, [AlControl::new(SubDeviceState::Op)]);
+    }
+
+    #[test]
+    fn safe_op_error_acknowledged_before_op() {
+        let (requests, action) = recovery_requests(status(SubDeviceState::SafeOp, true).unwrap());
+
+        assert_eq!(action, RecoveryAction::RequestedOp);
+        assert_eq!(
+            requests,
+            [
+                AlControl {
+                    state: SubDeviceState::SafeOp,
+                    error: true,
+                    id_request: false
+                },
+                AlControl::new(SubDeviceState::Op)
+            ]
+        );
+    }
+
+    #[test]
+    fn op_error_acknowledged_in_op() {
+        let (requests, action) = recovery_requests(status(SubDeviceState::Op, true).unwrap());
+
+        assert_eq!(action, RecoveryAction::RequestedOp);
+        assert_eq!(
+            requests,
+            [
+                AlControl {
+                    state: SubDeviceState::Op,
+                    error: true,
+                    id_request: false
+                },
+                AlControl::new(SubDeviceState::Op)
+            ]
+        );
+    }
+
+    #[test]
+    fn lower_states_need_reconfiguration() {
+        for state in [
+            SubDeviceState::Init,
+            SubDeviceState::PreOp,
+            SubDeviceState::Bootstrap,
+        ] {
+            let (requests, action) = recovery_requests(status(state, false).unwrap());
+
+            assert_eq!(action, RecoveryAction::Reconfigure, "{:?}", state);
+            assert!(requests.is_empty(), "{:?}", state);
+
+            let (requests, action) = recovery_requests(status(state, true).unwrap());
+
+            assert_eq!(action, RecoveryAction::Reconfigure, "{:?}", state);
+            assert_eq!(requests, [AlControl::reset()], "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn readdress_outcome() {
+        assert_eq!(
+            SubDeviceHealth::readdressed(2, 0x1002, true),
+            SubDeviceHealth {
+                index: 2,
+                configured_address: 0x1002,
+                state: Some(SubDeviceState::Init),
+                al_status_code: None,
+                readdressed: true,
+                action: RecoveryAction::Reconfigure,
+            }
+        );
+
+        assert_eq!(
+            SubDeviceHealth::readdressed(2, 0x1002, false),
+            SubDeviceHealth {
+                index: 2,
+                configured_address: 0x1002,
+                state: None,
+                al_status_code: None,
+                readdressed: false,
+                action: RecoveryAction::Lost,
+            }
+        );
+    }
+
+    #[test]
+    fn dc_sync_only_restored_when_used() {
+        let config = DcConfiguration {
+            start_delay: Duration::from_millis(100),
+            sync0_period: Duration::from_millis(1),
+            sync0_shift: Duration::ZERO,
+        };
+
+        assert_eq!(
+            dc_sync_configuration(Some(config), DcSupport::Bits64, DcSync::Sync0)
+                .map(|config| config.sync0_period),
+            Some(Duration::from_millis(1))
+        );
+        assert!(dc_sync_configuration(Some(config), DcSupport::Bits64, DcSync::Disabled).is_none());
+        assert!(dc_sync_configuration(Some(config), DcSupport::None, DcSync::Sync0).is_none());
+        assert!(dc_sync_configuration(None, DcSupport::Bits64, DcSync::Sync0).is_none());
+    }
+
+    fn io(inputs: Range<usize>, outputs: Range<usize>) -> IoRanges {
+        IoRanges {
+            input: PdiSegment {
+                bit_len: inputs.len() * 8,
+                bytes: inputs,
+            },
+            output: PdiSegment {
+                bit_len: outputs.len() * 8,
+                bytes: outputs,
+            },
+        }
+    }
+
+    #[test]
+    fn reconfigure_overlapping_outputs_offset() {
+        let mut subdevices = [
+            SubDevice {
+                configured_address: 0x1000,
+                ..SubDevice::default()
+            },
+            SubDevice {
+                configured_address: 0x1001,
+                ..SubDevice::default()
+            },
+        ];
+
+        subdevices[0].config.io = io(0..4, 0..2);
+        subdevices[1].config.io = io(4..6, 4..8);
+
+        let read_pdi_len = 8;
+
+        let (mappings, _pdi_len) = SubDeviceGroup::<2, 16, PreOp>::map_pdi(
+            PdiLayout::Overlapping,
+            subdevices.iter_mut(),
+            read_pdi_len,
+            8,
+        );
+
+        let outputs_offset =
+            SubDeviceGroup::<2, 16, Op>::outputs_offset(PdiLayout::Overlapping, read_pdi_len);
+
+        // The second SubDevice is reconfigured at the logical addresses it was mapped to before,
+        // then its outputs are moved to the same place in the PDI buffer.
+        let mapping = &mappings[1];
+
+        let mut reconfigured = io(mapping.inputs.clone(), mapping.outputs.clone());
+
+        assert_eq!(
+            reconfigured.check_lengths(&mapping.inputs, &mapping.outputs),
+            Ok(())
+        );
+
+        reconfigured.offset_outputs(outputs_offset);
+
+        assert_eq!(reconfigured, subdevices[1].config.io);
+        assert_eq!(reconfigured.output.bytes, 12..16);
+    }
+
+    #[test]
+    fn reconfigure_changed_length() {
+        let reconfigured = io(4..6, 4..7);
+
+        assert_eq!(
+            reconfigured.check_lengths(&(4..6), &(4..8)),
+            Err(PdiLayoutError::OutputLength {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            reconfigured.check_lengths(&(4..8), &(4..7)),
+            Err(PdiLayoutError::InputLength {
+                expected: 4,
+                actual: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,563 @@
+//! Stepping a group down from OP to INIT when the application exits.
+
+use super::{Init, Op, SafeOp, SubDeviceGroup, TransitionError, TransitionFailure};
+use crate::{
+    al_control::AlControl,
+    command::Command,
+    error::Error,
+    fmt,
+    subdevice::pdi::SubDevicePdi,
+    timer_factory::{timer, IntoTimeout},
+    MainDevice, RegisterAddress, SubDeviceRef, SubDeviceState,
+};
+use core::{cell::UnsafeCell, marker::PhantomData, time::Duration};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// Configuration for [`SubDeviceGroup::shutdown`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShutdownConfig {
+    /// The number of process data cycles to send with safe output values before leaving OP.
+    ///
+    /// Defaults to `10`. Set to `0` to leave OP without sending any more process data.
+    pub safe_output_cycles: u32,
+
+    /// The time between each process data cycle sent with safe output values.
+    ///
+    /// Defaults to 1ms.
+    pub cycle_time: Duration,
+
+    /// How long to wait for the group to reach SAFE-OP.
+    ///
+    /// Defaults to 5s.
+    pub safe_op_timeout: Duration,
+
+    /// How long to wait for the group to reach PRE-OP.
+    ///
+    /// Defaults to 5s.
+    pub pre_op_timeout: Duration,
+
+    /// How long to wait for the group to reach INIT.
+    ///
+    /// Defaults to 5s.
+    pub init_timeout: Duration,
+}
+
+impl ShutdownConfig {
+    /// The states to step down through from `from`, and how long to wait for each.
+    fn states_below(&self, from: SubDeviceState) -> heapless::Vec<(SubDeviceState, Duration), 3> {
+        let states = [
+            (SubDeviceState::SafeOp, self.safe_op_timeout),
+            (SubDeviceState::PreOp, self.pre_op_timeout),
+            (SubDeviceState::Init, self.init_timeout),
+        ];
+
+        let below = match from {
+            SubDeviceState::Op => &states[..],
+            SubDeviceState::SafeOp => &states[1..],
+            SubDeviceState::PreOp => &states[2..],
+            _ => &[],
+        };
+
+        // Can't overflow as there are at most three states
+        heapless::Vec::from_slice(below).unwrap_or_default()
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            safe_output_cycles: 10,
+            cycle_time: Duration::from_millis(1),
+            safe_op_timeout: Duration::from_millis(5000),
+            pre_op_timeout: Duration::from_millis(5000),
+            init_timeout: Duration::from_millis(5000),
+        }
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>
+{
+    /// Zero all outputs, then step the group down through SAFE-OP and PRE-OP into INIT.
+    ///
+    /// The zeroed outputs are sent for [`ShutdownConfig::safe_output_cycles`] cycles before the
+    /// group leaves OP so every SubDevice has applied them. Use
+    /// [`shutdown_with`](SubDeviceGroup::shutdown_with) if zero is not a safe output value for
+    /// every SubDevice.
+    ///
+    /// This method is intended to be called as the application exits, so it does its best to
+    /// reach INIT instead of stopping at the first problem:
+    ///
+    /// - SubDevices that no longer respond, e.g. because they were unplugged, are skipped.
+    /// - If some SubDevices do not reach SAFE-OP or PRE-OP in time, they are logged and the group
+    ///   continues to the next state.
+    /// - Errors while sending the safe outputs are logged and the group moves on to SAFE-OP.
+    ///
+    /// An error is only returned if a responding SubDevice is not in INIT after
+    /// [`ShutdownConfig::init_timeout`], or if the state requests cannot be sent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{
+    /// #     error::Error, MainDevice, SubDeviceGroup,
+    /// #     subdevice_group::{Op, ShutdownConfig},
+    /// # };
+    /// # async fn case(maindevice: &MainDevice<'_>, group: SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let group = group.shutdown(maindevice, ShutdownConfig::default()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(
+        self,
+        maindevice: &MainDevice<'_>,
+        config: ShutdownConfig,
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
+        self.shutdown_with(maindevice, config, |_| ()).await
+    }
+
+    /// Like [`shutdown`](SubDeviceGroup::shutdown), but with custom safe output values.
+    ///
+    /// `safe_outputs` is called once for each SubDevice in the group after its outputs have been
+    /// zeroed, and may write any values that are safe to hold while the group leaves OP, e.g. a
+    /// controlword that disables a drive's power stage.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{
+    /// #     error::Error, MainDevice, SubDeviceGroup,
+    /// #     subdevice_group::{Op, ShutdownConfig},
+    /// # };
+    /// # async fn case(maindevice: &MainDevice<'_>, group: SubDeviceGroup<16, 64, Op>) -> Result<(), Error> {
+    /// let group = group
+    ///     .shutdown_with(maindevice, ShutdownConfig::default(), |subdevice| {
+    ///         // Disable voltage on any drives in the group
+    ///         if subdevice.name() == "AKD" {
+    ///             subdevice.outputs_raw_mut()[0..2].copy_from_slice(&0x0000u16.to_le_bytes());
+    ///         }
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        config: ShutdownConfig,
+        mut safe_outputs: impl FnMut(&mut SubDeviceRef<'_, SubDevicePdi<'_>>),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
+        for index in 0..self.len() {
+            let mut subdevice = self.subdevice(maindevice, index)?;
+
+            subdevice.outputs_raw_mut().fill(0);
+
+            safe_outputs(&mut subdevice);
+        }
+
+        for _ in 0..config.safe_output_cycles {
+            if let Err(e) = self.tx_rx(maindevice).await {
+                fmt::warn!("Failed to send safe outputs during shutdown: {}", e);
+
+                break;
+            }
+
+            timer(config.cycle_time).await;
+        }
+
+        self.step_down(maindevice, &config.states_below(SubDeviceState::Op))
+            .await
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>
+{
+    /// Step the group down through PRE-OP into INIT.
+    ///
+    /// Outputs are not sent in SAFE-OP, so [`ShutdownConfig::safe_output_cycles`] is ignored.
+    /// SubDevices that have gone away or do not follow the group are handled the same way as when
+    /// shutting down a group in OP.
+    pub async fn shutdown(
+        self,
+        maindevice: &MainDevice<'_>,
+        config: ShutdownConfig,
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
+        self.step_down(maindevice, &config.states_below(SubDeviceState::SafeOp))
+            .await
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+{
+    /// Request each state in turn, continuing to the next state if the group times out.
+    ///
+    /// Only a failure to reach the last state is returned as an error.
+    async fn step_down(
+        self,
+        maindevice: &MainDevice<'_>,
+        states: &[(SubDeviceState, Duration)],
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, TransitionError<MAX_SUBDEVICES>>
+    {
+        let mut failures = StepDownFailures::default();
+
+        for &(state, timeout) in states {
+            self.request_responding(maindevice, state).await?;
+
+            fmt::debug!("Waiting for group state {} during shutdown", state);
+
+            let failed = self.wait_for_responding(maindevice, state, timeout).await?;
+
+            failures.record(state, failed);
+        }
+
+        if let Err((state, failed)) = failures.into_result() {
+            return Err(self.transition_error(maindevice, state, failed).await);
+        }
+
+        fmt::debug!("--> Group shutdown complete");
+
+        Ok(SubDeviceGroup {
+            id: self.id,
+            pdi: self.pdi,
+            read_pdi_len: self.read_pdi_len,
+            pdi_len: self.pdi_len,
+            inner: UnsafeCell::new(self.inner.into_inner()),
+            dc_conf: self.dc_conf,
+            _state: PhantomData,
+        })
+    }
+
+    /// Request a state from every SubDevice in the group, skipping any that do not respond.
+    async fn request_responding(
+        &self,
+        maindevice: &MainDevice<'_>,
+        state: SubDeviceState,
+    ) -> Result<(), Error> {
+        for subdevice in self.inner().subdevices.iter() {
+            let configured_address = subdevice.borrow().configured_address();
+
+            let result = SubDeviceRef::new(maindevice, configured_address, ())
+                .request_state_nowait(state)
+                .await;
+
+            skip_unresponsive(configured_address, state, result)?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for every responding SubDevice in the group to reach a state, returning those that
+    /// did not reach it before the timeout.
+    async fn wait_for_responding(
+        &self,
+        maindevice: &MainDevice<'_>,
+        state: SubDeviceState,
+        timeout: Duration,
+    ) -> Result<heapless::Vec<TransitionFailure, MAX_SUBDEVICES>, Error> {
+        let subdevices = &self.inner().subdevices;
+
+        let mut failed = heapless::Vec::new();
+
+        let result = async {
+            loop {
+                let commands = subdevices
+                    .iter()
+                    .map(|subdevice| {
+                        Command::fprd(
+                            subdevice.borrow().configured_address(),
+                            RegisterAddress::AlStatus.into(),
+                        )
+                        .into()
+                    })
+                    .collect::<heapless::Vec<Command, MAX_SUBDEVICES>>();
+
+                failed.clear();
+
+                maindevice
+                    .batch_pdus(commands, AlControl::PACKED_LEN as u16, |index, pdu| {
+                        if let Some(failure) = responding_failure(
+                            index,
+                            subdevices[index].borrow().configured_address(),
+                            pdu.working_counter,
+                            &pdu,
+                            state,
+                        )? {
+                            // Can't overflow as there is at most one entry per SubDevice
+                            let _ = failed.push(failure);
+                        }
+
+                        Ok(())
+                    })
+                    .await?;
+
+                if failed.is_empty() {
+                    break Ok(());
+                }
+
+                maindevice.timeouts.loop_tick().await;
+            }
+        }
+        .timeout(timeout)
+        .await;
+
+        match result {
+            Ok(()) | Err(Error::Timeout) => Ok(failed),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Ignore a failed state request if the SubDevice did not respond to it.
+fn skip_unresponsive(
+    configured_address: u16,
+    state: SubDeviceState,
+    result: Result<(), Error>,
+) -> Result<(), Error> {
+    match result.map_err(Error::without_context) {
+        Ok(()) => Ok(()),
+        Err(Error::WorkingCounter { .. } | Error::Timeout) => {
+            fmt::warn!(
+                "SubDevice {:#06x} did not respond to {} request during shutdown, skipping",
+                configured_address,
+                state
+            );
+
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Check the AL status read from a SubDevice during shutdown.
+///
+/// Returns `None` if the SubDevice is in `state`, or if it did not respond as SubDevices that have
+/// gone away can't be stepped down.
+fn responding_failure(
+    index: usize,
+    configured_address: u16,
+    working_counter: u16,
+    data: &[u8],
+    state: SubDeviceState,
+) -> Result<Option<TransitionFailure>, Error> {
+    if working_counter == 0 {
+        return Ok(None);
+    }
+
+    let status = AlControl::unpack_from_slice(data)?;
+
+    Ok(TransitionFailure::check(
+        index,
+        configured_address,
+        Some(status),
+        state,
+    ))
+}
+
+/// The SubDevices that did not reach each state while stepping down.
+///
+/// Failures to reach intermediate states are only logged, as the next state is requested anyway.
+struct StepDownFailures<const MAX_SUBDEVICES: usize> {
+    last: Option<(
+        SubDeviceState,
+        heapless::Vec<TransitionFailure, MAX_SUBDEVICES>,
+    )>,
+}
+
+impl<const MAX_SUBDEVICES: usize> Default for StepDownFailures<MAX_SUBDEVICES> {
+    fn default() -> Self {
+        Self { last: None }
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize> StepDownFailures<MAX_SUBDEVICES> {
+    fn record(
+        &mut self,
+        state: SubDeviceState,
+        failed: heapless::Vec<TransitionFailure, MAX_SUBDEVICES>,
+    ) {
+        for failure in failed.iter() {
+            fmt::warn!(
+                "SubDevice {:#06x} did not reach {} during shutdown, state is {:?}",
+                failure.configured_address,
+                state,
+                failure.state
+            );
+        }
+
+        self.last = Some((state, failed));
+    }
+
+    /// Only a failure to reach the last state is an error.
+    fn into_result(
+        self,
+    ) -> Result<
+        (),
+        (
+            SubDeviceState,
+            heapless::Vec<TransitionFailure, MAX_SUBDEVICES>,
+        ),
+    > {
+        match self.last {
+            Some((state, failed)) if !failed.is_empty() => Err((state, failed)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PduError;
+    use ethercrab_wire::EtherCrabWireWrite;
+
+    fn failure(index: usize, state: SubDeviceState) -> TransitionFailure {
+        TransitionFailure {
+            index,
+            configured_address: 0x1000 + index as u16,
+            state: Some(state),
+            status_code: None,
+        }
+    }
+
+    #[test]
+    fn states_from_op() {
+        let config = ShutdownConfig {
+            safe_op_timeout: Duration::from_millis(1),
+            pre_op_timeout: Duration::from_millis(2),
+            init_timeout: Duration::from_millis(3),
+            ..ShutdownConfig::default()
+        };
+
+        assert_eq!(
+            config.states_below(SubDeviceState::Op),
+            [
+                (SubDeviceState::SafeOp, Duration::from_millis(1)),
+                (SubDeviceState::PreOp, Duration::from_millis(2)),
+                (SubDeviceState::Init, Duration::from_millis(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn states_from_safe_op() {
+        let config = ShutdownConfig::default();
+
+        assert_eq!(
+            config.states_below(SubDeviceState::SafeOp),
+            [
+                (SubDeviceState::PreOp, config.pre_op_timeout),
+                (SubDeviceState::Init, config.init_timeout),
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_unresponsive_requests() {
+        assert_eq!(
+            skip_unresponsive(
+                0x1000,
+                SubDeviceState::Init,
+                Err(Error::WorkingCounter {
+                    expected: 1,
+                    received: 0
+                })
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            skip_unresponsive(0x1000, SubDeviceState::Init, Err(Error::Timeout)),
+            Ok(())
+        );
+        assert_eq!(
+            skip_unresponsive(
+                0x1000,
+                SubDeviceState::Init,
+                Err(Error::Pdu(PduError::TooLong))
+            ),
+            Err(Error::Pdu(PduError::TooLong))
+        );
+    }
+
+    #[test]
+    fn skip_unresponsive_status() {
+        // Whatever is in the buffer is ignored if nothing responded
+        assert_eq!(
+            responding_failure(0, 0x1000, 0, &[0x04, 0x00], SubDeviceState::Init),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn responding_status() {
+        let mut buf = [0u8; 2];
+
+        AlControl::new(SubDeviceState::Init)
+            .pack_to_slice(&mut buf)
+            .unwrap();
+
+        assert_eq!(
+            responding_failure(0, 0x1000, 1, &buf, SubDeviceState::Init),
+            Ok(None)
+        );
+
+        AlControl::new(SubDeviceState::PreOp)
+            .pack_to_slice(&mut buf)
+            .unwrap();
+
+        assert_eq!(
+            responding_failure(2, 0x1002, 1, &buf, SubDeviceState::Init),
+            Ok(Some(TransitionFailure {
+                index: 2,
+                configured_address: 0x1002,
+                state: Some(SubDeviceState::PreOp),
+                status_code: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn only_last_state_fails() {
+        let mut failures = StepDownFailures::<4>::default();
+
+        failures.record(
+            SubDeviceState::SafeOp,
+            heapless::Vec::from_slice(&[failure(0, SubDeviceState::Op)]).unwrap(),
+        );
+        failures.record(
+            SubDeviceState::PreOp,
+            heapless::Vec::from_slice(&[failure(1, SubDeviceState::SafeOp)]).unwrap(),
+        );
+        failures.record(SubDeviceState::Init, heapless::Vec::new());
+
+        assert_eq!(failures.into_result(), Ok(()));
+    }
+
+    #[test]
+    fn last_state_failure() {
+        let mut failures = StepDownFailures::<4>::default();
+
+        failures.record(SubDeviceState::PreOp, heapless::Vec::new());
+        failures.record(
+            SubDeviceState::Init,
+            heapless::Vec::from_slice(&[failure(3, SubDeviceState::PreOp)]).unwrap(),
+        );
+
+        assert_eq!(
+            failures.into_result(),
+            Err((
+                SubDeviceState::Init,
+                heapless::Vec::from_slice(&[failure(3, SubDeviceState::PreOp)]).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn no_states() {
+        assert_eq!(StepDownFailures::<4>::default().into_result(), Ok(()));
+    }
+}
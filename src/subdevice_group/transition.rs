@@ -0,0 +1,275 @@
+//! Reporting of SubDevices that fail to follow a group state transition.
+
+use super::SubDeviceGroup;
+use crate::{
+    al_control::AlControl, al_status_code::AlStatusCode, command::Command, error::Error,
+    events::Event, fmt, MainDevice, RegisterAddress, SubDeviceState,
+};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
+
+/// A SubDevice that did not reach the state requested for its group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TransitionFailure {
+    /// The index of the SubDevice in the group.
+    pub index: usize,
+
+    /// The configured station address of the SubDevice.
+    pub configured_address: u16,
+
+    /// The state the SubDevice was in when the transition timed out, or `None` if it did not
+    /// respond.
+    pub state: Option<SubDeviceState>,
+
+    /// The AL status code read from the SubDevice, or `None` if it did not respond.
+    pub status_code: Option<AlStatusCode>,
+}
+
+/// The error returned when a [`SubDeviceGroup`] fails to transition to a new state.
+///
+/// This converts into an [`Error`] so `?` can be used in functions returning EtherCrab's own error
+/// type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionError<const MAX_SUBDEVICES: usize> {
+    /// The cause of the failure.
+    ///
+    /// If the transition timed out, this is [`Error::TransitionFailed`] for the first SubDevice in
+    /// [`failed`](TransitionError::failed).
+    pub error: Error,
+
+    /// Every SubDevice that did not reach the requested state.
+    ///
+    /// This is empty if the transition failed for a reason other than a timeout, for example a
+    /// failed state request or an invalid watchdog configuration.
+    pub failed: heapless::Vec<TransitionFailure, MAX_SUBDEVICES>,
+}
+
+impl TransitionFailure {
+    /// Check the AL status of a SubDevice, where `status` is `None` if the SubDevice did not
+    /// respond.
+    ///
+    /// Returns `None` if the SubDevice is in `desired_state`.
+    pub(super) fn check(
+        index: usize,
+        configured_address: u16,
+        status: Option<AlControl>,
+        desired_state: SubDeviceState,
+    ) -> Option<Self> {
+        let state = status.map(|status| status.state);
+
+        (state != Some(desired_state)).then_some(Self {
+            index,
+            configured_address,
+            state,
+            status_code: None,
+        })
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize> From<Error> for TransitionError<MAX_SUBDEVICES> {
+    fn from(error: Error) -> Self {
+        Self {
+            error,
+            failed: heapless::Vec::new(),
+        }
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize> From<TransitionError<MAX_SUBDEVICES>> for Error {
+    fn from(value: TransitionError<MAX_SUBDEVICES>) -> Self {
+        value.error
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize> core::fmt::Display for TransitionError<MAX_SUBDEVICES> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "group transition failed: {}", self.error)?;
+
+        if self.failed.len() > 1 {
+            write!(f, " ({} SubDevices failed)", self.failed.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const MAX_SUBDEVICES: usize> std::error::Error for TransitionError<MAX_SUBDEVICES> {}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+{
+    /// Read the AL status of every SubDevice in the group in as few frames as possible, returning
+    /// those that are not in the desired state.
+    pub(super) async fn not_in_state(
+        &self,
+        maindevice: &MainDevice<'_>,
+        desired_state: SubDeviceState,
+    ) -> Result<heapless::Vec<TransitionFailure, MAX_SUBDEVICES>, Error> {
+        let subdevices = &self.inner().subdevices;
+
+        let commands = subdevices
+            .iter()
+            .map(|subdevice| {
+                Command::fprd(
+                    subdevice.borrow().configured_address(),
+                    RegisterAddress::AlStatus.into(),
+                )
+                .into()
+            })
+            .collect::<heapless::Vec<Command, MAX_SUBDEVICES>>();
+
+        let mut failed = heapless::Vec::new();
+
+        maindevice
+            .batch_pdus(commands, AlControl::PACKED_LEN as u16, |index, pdu| {
+                let status = if pdu.working_counter == 1 {
+                    Some(AlControl::unpack_from_slice(&pdu)?)
+                } else {
+                    None
+                };
+
+                if let Some(failure) = TransitionFailure::check(
+                    index,
+                    subdevices[index].borrow().configured_address(),
+                    status,
+                    desired_state,
+                ) {
+                    // Can't overflow as there is at most one entry per SubDevice
+                    let _ = failed.push(failure);
+                }
+
+                Ok(())
+            })
+            .await?;
+
+        Ok(failed)
+    }
+
+    /// Read the AL status code of every SubDevice that did not complete a transition.
+    pub(super) async fn transition_error(
+        &self,
+        maindevice: &MainDevice<'_>,
+        desired_state: SubDeviceState,
+        mut failed: heapless::Vec<TransitionFailure, MAX_SUBDEVICES>,
+    ) -> TransitionError<MAX_SUBDEVICES> {
+        let commands = failed
+            .iter()
+            .map(|failure| {
+                Command::fprd(
+                    failure.configured_address,
+                    RegisterAddress::AlStatusCode.into(),
+                )
+                .into()
+            })
+            .collect::<heapless::Vec<Command, MAX_SUBDEVICES>>();
+
+        let codes = maindevice
+            .batch_pdus(commands, AlStatusCode::PACKED_LEN as u16, |idx, pdu| {
+                if let Some(failure) = failed.get_mut(idx) {
+                    if pdu.working_counter == 1 {
+                        failure.status_code = Some(AlStatusCode::unpack_from_slice(&pdu)?);
+                    }
+                }
+
+                Ok(())
+            })
+            .await;
+
+        if let Err(e) = codes {
+            fmt::warn!("Failed to read AL status codes: {}", e);
+        }
+
+        for failure in failed.iter() {
+            fmt::error!(
+                "SubDevice {:#06x} did not reach {}, state is {:?}: {:?}",
+                failure.configured_address,
+                desired_state,
+                failure.state,
+                failure.status_code
+            );
+
+            if let Some(status_code) = failure
+                .status_code
+                .filter(|code| *code != AlStatusCode::NoError)
+            {
+                maindevice.publish(Event::AlStatus {
+                    configured_address: failure.configured_address,
+                    state: failure.state.unwrap_or(SubDeviceState::None),
+                    status_code,
+                });
+            }
+        }
+
+        let error = failed
+            .first()
+            .map_or(Error::Timeout, |failure| Error::TransitionFailed {
+                configured_address: failure.configured_address,
+                expected: desired_state,
+                actual: failure.state.unwrap_or(SubDeviceState::None),
+                status_code: failure
+                    .status_code
+                    .unwrap_or(AlStatusCode::UnspecifiedError),
+            });
+
+        TransitionError { error, failed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_round_trip() {
+        let error = TransitionError::<4>::from(Error::Timeout);
+
+        assert!(error.failed.is_empty());
+        assert_eq!(Error::from(error), Error::Timeout);
+    }
+
+    #[test]
+    fn check_state() {
+        assert_eq!(
+            TransitionFailure::check(
+                0,
+                0x1000,
+                Some(AlControl::new(SubDeviceState::Op)),
+                SubDeviceState::Op
+            ),
+            None
+        );
+
+        assert_eq!(
+            TransitionFailure::check(
+                1,
+                0x1001,
+                Some(AlControl::new(SubDeviceState::SafeOp)),
+                SubDeviceState::Op
+            ),
+            Some(TransitionFailure {
+                index: 1,
+                configured_address: 0x1001,
+                state: Some(SubDeviceState::SafeOp),
+                status_code: None
+            })
+        );
+    }
+
+    #[test]
+    fn check_no_response() {
+        assert_eq!(
+            TransitionFailure::check(2, 0x1002, None, SubDeviceState::Op),
+            Some(TransitionFailure {
+                index: 2,
+                configured_address: 0x1002,
+                state: None,
+                status_code: None
+            })
+        );
+
+        // A SubDevice that doesn't respond is never in the desired state, even if that is `None`
+        assert!(TransitionFailure::check(2, 0x1002, None, SubDeviceState::None).is_some());
+    }
+}
@@ -0,0 +1,279 @@
+//! Expected working counter calculation and diagnostics for cyclic process data exchange.
+
+use super::cyclic_command::PdiCommand;
+use crate::{AlStatusCode, SubDeviceState};
+use core::ops::Range;
+
+/// Bit 0 of [`RegisterAddress::SyncManagerWatchdogStatus`](crate::RegisterAddress::SyncManagerWatchdogStatus)
+/// is cleared when the process data watchdog has expired.
+pub(crate) const WATCHDOG_ACTIVE: u8 = 0b0000_0001;
+
+/// The result of a process data exchange with a [`SubDeviceGroup`](crate::SubDeviceGroup).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TxRxResponse {
+    /// The working counter returned by the network.
+    ///
+    /// If the PDI is sent in multiple chunks, this is the sum of the working counter of every
+    /// chunk.
+    pub working_counter: u16,
+
+    /// The working counter expected when every SubDevice in the group exchanges process data.
+    ///
+    /// With the default
+    /// [`CyclicCommandStrategy::Lrw`](crate::subdevice_group::CyclicCommandStrategy::Lrw), each
+    /// SubDevice adds `1` if it has inputs and `2` if it has outputs. When inputs and outputs are
+    /// exchanged with separate `LRD` and `LWR` commands, each SubDevice adds `1` for each instead.
+    ///
+    /// Note that SubDevices usually do not accept outputs until they are in OP, so the working
+    /// counter may be lower than this value while the group is in PRE-OP or SAFE-OP.
+    pub expected_working_counter: u16,
+}
+
+impl TxRxResponse {
+    /// Returns `true` if the received working counter is the expected value.
+    pub fn working_counter_ok(&self) -> bool {
+        self.working_counter == self.expected_working_counter
+    }
+
+    pub(crate) fn add_chunk(&mut self, chunk: TxRxResponse) {
+        self.working_counter = self.working_counter.wrapping_add(chunk.working_counter);
+        self.expected_working_counter = self
+            .expected_working_counter
+            .wrapping_add(chunk.expected_working_counter);
+    }
+}
+
+/// The part of the group PDI mapped to a single SubDevice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PdiMapping {
+    pub configured_address: u16,
+    /// Input bytes, relative to the start of the group PDI.
+    pub inputs: Range<usize>,
+    /// Output bytes, relative to the start of the group PDI.
+    pub outputs: Range<usize>,
+}
+
+impl PdiMapping {
+    /// The working counter this SubDevice adds to `command` covering `chunk` of the group PDI.
+    pub fn expected_working_counter(&self, command: PdiCommand, chunk: &Range<usize>) -> u16 {
+        let overlaps = |range: &Range<usize>| {
+            u16::from(!range.is_empty() && range.start < chunk.end && chunk.start < range.end)
+        };
+
+        match command {
+            PdiCommand::Lrw => overlaps(&self.inputs) + 2 * overlaps(&self.outputs),
+            PdiCommand::Lrd => overlaps(&self.inputs),
+            PdiCommand::Lwr => overlaps(&self.outputs),
+        }
+    }
+
+    pub fn has_outputs(&self) -> bool {
+        !self.outputs.is_empty()
+    }
+}
+
+/// Status of a single SubDevice, read by
+/// [`SubDeviceGroup::working_counter_diagnostics`](crate::SubDeviceGroup::working_counter_diagnostics).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SubDeviceDiagnostic {
+    /// Configured station address of the SubDevice.
+    pub configured_address: u16,
+
+    /// Whether the SubDevice responded to the diagnostic read.
+    ///
+    /// If this is `false`, the SubDevice is likely disconnected or powered off and the remaining
+    /// fields hold default values.
+    pub responding: bool,
+
+    /// Current AL state.
+    pub state: SubDeviceState,
+
+    /// Whether the AL status error flag is set.
+    pub error: bool,
+
+    /// AL status code.
+    pub status_code: AlStatusCode,
+
+    /// Whether the SubDevice's process data watchdog has expired.
+    ///
+    /// This is always `false` for SubDevices without outputs.
+    pub watchdog_expired: bool,
+}
+
+impl SubDeviceDiagnostic {
+    pub(crate) fn new(configured_address: u16) -> Self {
+        Self {
+            configured_address,
+            responding: false,
+            state: SubDeviceState::None,
+            error: false,
+            status_code: AlStatusCode::NoError,
+            watchdog_expired: false,
+        }
+    }
+
+    /// Returns `true` if this SubDevice is likely to be the cause of a working counter mismatch in
+    /// a group that should be in `expected_state`.
+    pub fn is_faulty(&self, expected_state: SubDeviceState) -> bool {
+        !self.responding || self.error || self.watchdog_expired || self.state != expected_state
+    }
+}
+
+/// Status of every SubDevice in a group, used to find the cause of a working counter mismatch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WkcDiagnostics<const MAX_SUBDEVICES: usize> {
+    pub(crate) expected_state: SubDeviceState,
+    pub(crate) subdevices: heapless::Vec<SubDeviceDiagnostic, MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> WkcDiagnostics<MAX_SUBDEVICES> {
+    /// The state all SubDevices in the group are expected to be in.
+    pub fn expected_state(&self) -> SubDeviceState {
+        self.expected_state
+    }
+
+    /// Status of every SubDevice in the group, in group order.
+    pub fn subdevices(&self) -> &[SubDeviceDiagnostic] {
+        &self.subdevices
+    }
+
+    /// SubDevices that are not responding, are not in the expected state, have an AL error or have
+    /// an expired watchdog.
+    pub fn faulty(&self) -> impl Iterator<Item = &SubDeviceDiagnostic> {
+        self.subdevices
+            .iter()
+            .filter(|subdevice| subdevice.is_faulty(self.expected_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_wkc() {
+        let mapping = PdiMapping {
+            configured_address: 0x1000,
+            inputs: 4..8,
+            outputs: 20..22,
+        };
+
+        // Whole PDI
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(0..32)),
+            3
+        );
+        // Inputs only
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(0..5)),
+            1
+        );
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(7..20)),
+            1
+        );
+        // Outputs only
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(8..21)),
+            2
+        );
+        // Neither
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(0..4)),
+            0
+        );
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(22..32)),
+            0
+        );
+    }
+
+    #[test]
+    fn chunk_wkc_lrd_lwr() {
+        let mapping = PdiMapping {
+            configured_address: 0x1000,
+            inputs: 4..8,
+            outputs: 20..22,
+        };
+
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrd, &(0..20)),
+            1
+        );
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrd, &(8..20)),
+            0
+        );
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lwr, &(20..32)),
+            1
+        );
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lwr, &(0..20)),
+            0
+        );
+
+        // Outputs are only written once by an LWR, even though an LRW would count them twice
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrd, &(0..32))
+                + mapping.expected_working_counter(PdiCommand::Lwr, &(0..32)),
+            2
+        );
+    }
+
+    #[test]
+    fn no_io() {
+        let mapping = PdiMapping {
+            configured_address: 0x1000,
+            inputs: 4..4,
+            outputs: 20..20,
+        };
+
+        assert_eq!(
+            mapping.expected_working_counter(PdiCommand::Lrw, &(0..32)),
+            0
+        );
+        assert!(!mapping.has_outputs());
+    }
+
+    #[test]
+    fn faulty() {
+        let ok = SubDeviceDiagnostic {
+            responding: true,
+            state: SubDeviceState::Op,
+            ..SubDeviceDiagnostic::new(0x1000)
+        };
+
+        let diagnostics = WkcDiagnostics::<8> {
+            expected_state: SubDeviceState::Op,
+            subdevices: heapless::Vec::from_slice(&[
+                ok,
+                SubDeviceDiagnostic::new(0x1001),
+                SubDeviceDiagnostic {
+                    configured_address: 0x1002,
+                    state: SubDeviceState::SafeOp,
+                    error: true,
+                    status_code: AlStatusCode::SyncManagerWatchdog,
+                    ..ok
+                },
+                SubDeviceDiagnostic {
+                    configured_address: 0x1003,
+                    watchdog_expired: true,
+                    ..ok
+                },
+            ])
+            .unwrap(),
+        };
+
+        assert_eq!(
+            diagnostics
+                .faulty()
+                .map(|subdevice| subdevice.configured_address)
+                .collect::<Vec<_>>(),
+            vec![0x1001, 0x1002, 0x1003]
+        );
+    }
+}
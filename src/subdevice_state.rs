@@ -45,3 +45,45 @@ impl core::fmt::Display for SubDeviceState {
         }
     }
 }
+
+/// A request to move a single SubDevice into a new AL state.
+///
+/// Written to register `0x0120` ([`RegisterAddress::AlControl`](crate::register::RegisterAddress::AlControl))
+/// by [`SubDeviceRef::request_state`](crate::SubDeviceRef::request_state). A
+/// [`SubDeviceState`] can be used directly where no error acknowledgement is required.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StateRequest {
+    /// The requested state.
+    pub state: SubDeviceState,
+    /// Set the Error Acknowledge bit to clear a pending AL status error.
+    pub acknowledge: bool,
+}
+
+impl StateRequest {
+    /// Request the given state without acknowledging any pending error.
+    pub const fn new(state: SubDeviceState) -> Self {
+        Self {
+            state,
+            acknowledge: false,
+        }
+    }
+
+    /// Also acknowledge any pending error on the SubDevice.
+    ///
+    /// A SubDevice that has set the error flag in its AL status will not move to a higher state
+    /// until the error is acknowledged.
+    pub const fn acknowledge_error(self) -> Self {
+        Self {
+            acknowledge: true,
+            ..self
+        }
+    }
+}
+
+impl From<SubDeviceState> for StateRequest {
+    fn from(state: SubDeviceState) -> Self {
+        Self::new(state)
+    }
+}
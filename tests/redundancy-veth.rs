@@ -0,0 +1,240 @@
+//! Check the redundant TX/RX driver against a simulated ring of SubDevices.
+//!
+//! Requires root and two veth pairs, one for each end of the ring:
+//!
+//! ```bash
+//! sudo ip link add ecat0 type veth peer name ring0
+//! sudo ip link add ecat1 type veth peer name ring1
+//! for i in ecat0 ring0 ecat1 ring1; do sudo ip link set $i up; done
+//! sudo -E cargo test --test redundancy-veth -- --ignored
+//! ```
+//!
+//! The MainDevice uses `ecat0` and `ecat1`. A thread forwards frames between `ring0` and `ring1`,
+//! acting as a line of SubDevices with an optional cable break.
+
+#![cfg(target_os = "linux")]
+
+use ethercrab::{
+    error::Error,
+    std::{tx_rx_task_redundant, RedundancyConfig, RingState},
+    Command, MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+};
+use std::{
+    io, mem,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+const SUBDEVICES: usize = 3;
+const ETHERCAT_ETHERTYPE: u16 = 0x88a4;
+
+/// A raw socket on one end of the simulated ring.
+struct Port {
+    fd: i32,
+}
+
+impl Port {
+    fn new(interface: &str) -> io::Result<Self> {
+        let protocol = ETHERCAT_ETHERTYPE.to_be();
+
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, i32::from(protocol)) };
+
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let name = std::ffi::CString::new(interface).unwrap();
+
+        let sockaddr = libc::sockaddr_ll {
+            sll_family: libc::AF_PACKET as u16,
+            sll_protocol: protocol,
+            sll_ifindex: unsafe { libc::if_nametoindex(name.as_ptr()) } as i32,
+            sll_hatype: 0,
+            sll_pkttype: 0,
+            sll_halen: 0,
+            sll_addr: [0; 8],
+        };
+
+        let res = unsafe {
+            libc::bind(
+                fd,
+                std::ptr::addr_of!(sockaddr).cast(),
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Receive a frame sent into the ring, ignoring frames sent by this port.
+    fn recv(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+
+        let n = unsafe {
+            libc::recvfrom(
+                self.fd,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                libc::MSG_DONTWAIT,
+                std::ptr::addr_of_mut!(addr).cast(),
+                &mut addr_len,
+            )
+        };
+
+        (n > 0 && addr.sll_pkttype != libc::PACKET_OUTGOING).then_some(n as usize)
+    }
+
+    fn send(&self, frame: &[u8]) {
+        let n = unsafe { libc::send(self.fd, frame.as_ptr().cast(), frame.len(), 0) };
+
+        assert_eq!(n, frame.len() as isize, "send failed");
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Process a frame by the SubDevices at `positions`, each setting its bit in the first data byte
+/// of every PDU and incrementing the working counter.
+fn process(frame: &mut [u8], positions: std::ops::Range<usize>) {
+    if positions.is_empty() {
+        return;
+    }
+
+    // U/L bit is set by the first SubDevice
+    frame[6] |= 0x02;
+
+    let mut pos = 16;
+
+    loop {
+        let flags = u16::from_le_bytes([frame[pos + 6], frame[pos + 7]]);
+        let len = usize::from(flags & 0x07ff);
+        let data = pos + 10;
+        let wkc = data + len;
+
+        for position in positions.clone() {
+            if len > 0 {
+                frame[data] |= 1 << position;
+            }
+
+            let count = u16::from_le_bytes([frame[wkc], frame[wkc + 1]]) + 1;
+
+            frame[wkc..wkc + 2].copy_from_slice(&count.to_le_bytes());
+        }
+
+        if flags & 0x8000 == 0 {
+            break;
+        }
+
+        pos = wkc + 2;
+    }
+}
+
+/// Forward frames around a line of SubDevices with a break after `break_after` of them, or an
+/// intact ring if `break_after` is `SUBDEVICES`.
+fn simulate_ring(break_after: Arc<AtomicUsize>, stop: Arc<AtomicBool>) -> io::Result<()> {
+    let primary = Port::new("ring0")?;
+    let secondary = Port::new("ring1")?;
+
+    let mut buf = [0u8; 1536];
+
+    while !stop.load(Ordering::Relaxed) {
+        let k = break_after.load(Ordering::Relaxed);
+
+        if let Some(n) = primary.recv(&mut buf) {
+            let frame = &mut buf[0..n];
+
+            if k == SUBDEVICES {
+                process(frame, 0..SUBDEVICES);
+                secondary.send(frame);
+            } else if k > 0 {
+                process(frame, 0..k);
+                primary.send(frame);
+            }
+        }
+
+        if let Some(n) = secondary.recv(&mut buf) {
+            let frame = &mut buf[0..n];
+
+            if k == SUBDEVICES {
+                // Passes through every SubDevice unprocessed
+                primary.send(frame);
+            } else {
+                process(frame, k..SUBDEVICES);
+                secondary.send(frame);
+            }
+        }
+
+        thread::sleep(Duration::from_micros(10));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires root and two veth pairs, see module docs"]
+async fn redundancy_veth() -> Result<(), Error> {
+    static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+
+    let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+
+    let break_after = Arc::new(AtomicUsize::new(SUBDEVICES));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let ring = thread::spawn({
+        let break_after = break_after.clone();
+        let stop = stop.clone();
+
+        move || simulate_ring(break_after, stop)
+    });
+
+    let (task, status) =
+        tx_rx_task_redundant("ecat0", "ecat1", tx, rx, RedundancyConfig::default())
+            .expect("open interfaces");
+
+    tokio::spawn(task);
+
+    let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+
+    // Intact ring, then breaks between SubDevices, after the primary interface and before the
+    // secondary interface.
+    for (k, expected_state) in [
+        (SUBDEVICES, RingState::Intact),
+        (1, RingState::Broken),
+        (2, RingState::Broken),
+        (0, RingState::Broken),
+        (SUBDEVICES, RingState::Intact),
+    ] {
+        break_after.store(k, Ordering::Relaxed);
+
+        // A few rounds so frames in flight during the change are flushed out
+        for _ in 0..3 {
+            let value = Command::brd(0x0000)
+                .with_wkc(SUBDEVICES as u16)
+                .receive::<u8>(&maindevice)
+                .await?;
+
+            assert_eq!(value, 0b111, "break after {k}");
+        }
+
+        assert_eq!(status.ring_state(), expected_state, "break after {k}");
+    }
+
+    stop.store(true, Ordering::Relaxed);
+
+    ring.join().unwrap().expect("simulated ring");
+
+    Ok(())
+}
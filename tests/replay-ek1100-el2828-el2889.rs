@@ -96,7 +96,11 @@ async fn replay_ek1100_el2828_el2889() -> Result<(), Error> {
 
     // Animate slow pattern for 8 ticks
     for _ in 0..8 {
-        slow_outputs.tx_rx(&maindevice).await.expect("TX/RX");
+        let response = slow_outputs.tx_rx(&maindevice).await.expect("TX/RX");
+
+        // EL2889 outputs only. EK1100 has no process data.
+        assert_eq!(response.expected_working_counter, 2);
+        assert!(response.working_counter_ok());
 
         let (_i, o) = el2889.io_raw_mut();
 
@@ -112,7 +116,11 @@ async fn replay_ek1100_el2828_el2889() -> Result<(), Error> {
 
     // Count up to 255 in binary
     for _ in 0..255 {
-        fast_outputs.tx_rx(&maindevice).await.expect("TX/RX");
+        let response = fast_outputs.tx_rx(&maindevice).await.expect("TX/RX");
+
+        // EL2828 outputs only
+        assert_eq!(response.expected_working_counter, 2);
+        assert!(response.working_counter_ok());
 
         // Increment every output byte for every SubDevice by one
         for mut subdevice in fast_outputs.iter(&maindevice) {
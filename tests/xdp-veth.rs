@@ -0,0 +1,194 @@
+//! Check the AF_XDP TX/RX driver in generic XDP mode against simulated SubDevices.
+//!
+//! Requires root and a veth pair:
+//!
+//! ```bash
+//! sudo ip link add xdp0 type veth peer name xdp1
+//! sudo ip link set xdp0 up
+//! sudo ip link set xdp1 up
+//! sudo -E cargo test --test xdp-veth -- --ignored
+//! ```
+//!
+//! The MainDevice uses `xdp0`. A thread answers frames arriving on `xdp1`, acting as a line of
+//! SubDevices.
+
+#![cfg(target_os = "linux")]
+
+use ethercrab::{
+    error::Error,
+    std::{tx_rx_task_xdp, XdpConfig},
+    Command, MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+};
+use std::{
+    io, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+const SUBDEVICES: u16 = 3;
+const ETHERCAT_ETHERTYPE: u16 = 0x88a4;
+
+/// A raw socket on the SubDevice end of the veth pair.
+struct Port {
+    fd: i32,
+}
+
+impl Port {
+    fn new(interface: &str) -> io::Result<Self> {
+        let protocol = ETHERCAT_ETHERTYPE.to_be();
+
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, i32::from(protocol)) };
+
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let name = std::ffi::CString::new(interface).unwrap();
+
+        let sockaddr = libc::sockaddr_ll {
+            sll_family: libc::AF_PACKET as u16,
+            sll_protocol: protocol,
+            sll_ifindex: unsafe { libc::if_nametoindex(name.as_ptr()) } as i32,
+            sll_hatype: 0,
+            sll_pkttype: 0,
+            sll_halen: 0,
+            sll_addr: [0; 8],
+        };
+
+        let res = unsafe {
+            libc::bind(
+                fd,
+                std::ptr::addr_of!(sockaddr).cast(),
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Receive a frame sent by the MainDevice, ignoring frames sent by this port.
+    fn recv(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+
+        let n = unsafe {
+            libc::recvfrom(
+                self.fd,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                libc::MSG_DONTWAIT,
+                std::ptr::addr_of_mut!(addr).cast(),
+                &mut addr_len,
+            )
+        };
+
+        (n > 0 && addr.sll_pkttype != libc::PACKET_OUTGOING).then_some(n as usize)
+    }
+
+    fn send(&self, frame: &[u8]) {
+        let n = unsafe { libc::send(self.fd, frame.as_ptr().cast(), frame.len(), 0) };
+
+        assert_eq!(n, frame.len() as isize, "send failed");
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Increment the working counter of every PDU once for each SubDevice, and set the U/L bit of the
+/// source address like a real SubDevice.
+fn process(frame: &mut [u8]) {
+    frame[6] |= 0x02;
+
+    let mut pos = 16;
+
+    loop {
+        let flags = u16::from_le_bytes([frame[pos + 6], frame[pos + 7]]);
+        let len = usize::from(flags & 0x07ff);
+        let wkc = pos + 10 + len;
+
+        let count = u16::from_le_bytes([frame[wkc], frame[wkc + 1]]) + SUBDEVICES;
+
+        frame[wkc..wkc + 2].copy_from_slice(&count.to_le_bytes());
+
+        if flags & 0x8000 == 0 {
+            break;
+        }
+
+        pos = wkc + 2;
+    }
+}
+
+fn simulate_subdevices(stop: Arc<AtomicBool>) -> io::Result<()> {
+    let port = Port::new("xdp1")?;
+
+    let mut buf = [0u8; 1536];
+
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(n) = port.recv(&mut buf) {
+            let frame = &mut buf[0..n];
+
+            process(frame);
+            port.send(frame);
+        }
+
+        thread::sleep(Duration::from_micros(10));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires root and a veth pair, see module docs"]
+async fn xdp_veth() -> Result<(), Error> {
+    static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+
+    let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let subdevices = thread::spawn({
+        let stop = stop.clone();
+
+        move || simulate_subdevices(stop)
+    });
+
+    thread::spawn(move || {
+        tx_rx_task_xdp(
+            "xdp0",
+            tx,
+            rx,
+            XdpConfig {
+                generic: true,
+                ..XdpConfig::default()
+            },
+        )
+        .expect("XDP TX/RX task")
+    });
+
+    let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+
+    for _ in 0..100 {
+        Command::brd(0x0000)
+            .with_wkc(SUBDEVICES)
+            .receive::<u8>(&maindevice)
+            .await?;
+    }
+
+    stop.store(true, Ordering::Relaxed);
+
+    subdevices.join().unwrap().expect("simulated SubDevices");
+
+    Ok(())
+}